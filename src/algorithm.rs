@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::Writer;
+
+/// A trait for hashes describing their algorithm.
+pub trait Algorithm: Hash {
+    /// Name of the algorithm, like `SHA-256`.
+    const NAME: &'static str;
+    /// Length of the digest in bytes.
+    const DIGEST_SIZE: usize;
+    /// Length of the internal block in bytes.
+    const BLOCK_SIZE: usize;
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: Algorithm,
+{
+    /// Returns name of the hash algorithm.
+    #[must_use]
+    pub const fn algorithm_name(&self) -> &'static str {
+        H::NAME
+    }
+
+    /// Returns length of the digest in bytes.
+    #[must_use]
+    pub const fn digest_size(&self) -> usize {
+        H::DIGEST_SIZE
+    }
+
+    /// Returns length of the internal block of the hash in bytes.
+    #[must_use]
+    pub const fn block_size(&self) -> usize {
+        H::BLOCK_SIZE
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Algorithm,
+{
+    /// Returns name of the hash algorithm.
+    #[must_use]
+    pub const fn algorithm_name(&self) -> &'static str {
+        H::NAME
+    }
+
+    /// Returns length of the digest in bytes.
+    #[must_use]
+    pub const fn digest_size(&self) -> usize {
+        H::DIGEST_SIZE
+    }
+
+    /// Returns length of the internal block of the hash in bytes.
+    #[must_use]
+    pub const fn block_size(&self) -> usize {
+        H::BLOCK_SIZE
+    }
+}
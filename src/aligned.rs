@@ -0,0 +1,386 @@
+use std::fmt::{self, Debug, Formatter};
+use std::fs::File;
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+use crate::error::IntoInnerError;
+
+/// Underlying writers which can discard bytes from their end.
+///
+/// Used by [`AlignedWriter::finish`] to drop the padding written after the unaligned tail.
+pub trait Truncate {
+    /// Discards the last `n` bytes.
+    fn truncate_tail(&mut self, n: u64) -> io::Result<()>;
+}
+
+impl Truncate for File {
+    fn truncate_tail(&mut self, n: u64) -> io::Result<()> {
+        Truncate::truncate_tail(&mut &*self, n)
+    }
+}
+
+impl Truncate for &File {
+    fn truncate_tail(&mut self, n: u64) -> io::Result<()> {
+        let length = self.metadata()?.len();
+        self.set_len(length.saturating_sub(n))
+    }
+}
+
+impl<T> Truncate for &mut T
+where
+    T: Truncate + ?Sized,
+{
+    fn truncate_tail(&mut self, n: u64) -> io::Result<()> {
+        (**self).truncate_tail(n)
+    }
+}
+
+/// Buffer whose contents start at an address aligned to the requested alignment.
+struct AlignedBuffer {
+    storage: Box<[u8]>,
+    offset: usize,
+    capacity: usize,
+    filled: usize,
+}
+
+impl AlignedBuffer {
+    fn new(alignment: usize, capacity: usize) -> Self {
+        let storage = vec![0; capacity + alignment - 1].into_boxed_slice();
+        let address = storage.as_ptr() as usize;
+        let offset = (alignment - address % alignment) % alignment;
+        Self {
+            storage,
+            offset,
+            capacity,
+            filled: 0,
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.storage[self.offset..self.offset + self.filled]
+    }
+
+    fn push(&mut self, data: &[u8]) -> usize {
+        let n = data.len().min(self.capacity - self.filled);
+        let start = self.offset + self.filled;
+        self.storage[start..start + n].copy_from_slice(&data[..n]);
+        self.filled += n;
+        n
+    }
+
+    fn pad(&mut self, n: usize) {
+        let start = self.offset + self.filled;
+        self.storage[start..start + n].fill(0);
+        self.filled += n;
+    }
+
+    fn consume(&mut self, n: usize) {
+        let start = self.offset;
+        self.storage.copy_within(start + n..start + self.filled, start);
+        self.filled -= n;
+    }
+
+    const fn is_full(&self) -> bool {
+        self.filled == self.capacity
+    }
+}
+
+impl Debug for AlignedBuffer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedBuffer")
+            .field("capacity", &self.capacity)
+            .field("filled", &self.filled)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wraps a writer, passing data to it only in blocks of aligned size from an aligned memory buffer.
+///
+/// This allows the underlying [`File`] to be opened with `O_DIRECT`, while the digest still covers exactly the bytes
+/// written. The unaligned tail is kept in the buffer until [`AlignedWriter::finish`] is called.
+#[derive(Debug)]
+pub struct AlignedWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    alignment: usize,
+    buffer: AlignedBuffer,
+    padding: usize,
+}
+
+impl<W, H> AlignedWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Default alignment, matching the logical block size of most storage devices.
+    pub const DEFAULT_ALIGNMENT: usize = 4096;
+    /// Default buffer capacity.
+    pub const DEFAULT_CAPACITY: usize = 1024 * 1024;
+
+    /// Creates new [`AlignedWriter`] with default alignment and buffer capacity.
+    pub fn new(inner: W) -> Self {
+        Self::with_alignment(inner, Self::DEFAULT_ALIGNMENT, Self::DEFAULT_CAPACITY)
+    }
+
+    /// Creates new [`AlignedWriter`] with default alignment and provided buffer capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is not a nonzero multiple of [`AlignedWriter::DEFAULT_ALIGNMENT`].
+    pub fn with_capacity(inner: W, capacity: usize) -> Self {
+        Self::with_alignment(inner, Self::DEFAULT_ALIGNMENT, capacity)
+    }
+
+    /// Creates new [`AlignedWriter`] with provided alignment and buffer capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alignment` is not a power of two, or if `capacity` is not a nonzero multiple of `alignment`.
+    pub fn with_alignment(inner: W, alignment: usize, capacity: usize) -> Self {
+        let hash = H::default();
+        Self::with_alignment_and_hash(inner, alignment, capacity, hash)
+    }
+
+    /// Creates new [`AlignedWriter`] with provided alignment, buffer capacity and hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alignment` is not a power of two, or if `capacity` is not a nonzero multiple of `alignment`.
+    pub fn with_alignment_and_hash(inner: W, alignment: usize, capacity: usize, hash: H) -> Self {
+        assert!(alignment.is_power_of_two(), "alignment must be a power of two");
+        assert!(
+            capacity != 0 && capacity % alignment == 0,
+            "capacity must be a nonzero multiple of alignment"
+        );
+        let buffer = AlignedBuffer::new(alignment, capacity);
+        Self {
+            inner,
+            hash,
+            alignment,
+            buffer,
+            padding: 0,
+        }
+    }
+
+    /// Returns alignment of blocks passed to the underlying writer.
+    #[must_use]
+    pub const fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    /// Returns capacity of the internal buffer.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.buffer.capacity
+    }
+
+    /// Returns buffered bytes, not yet passed to the underlying writer.
+    #[must_use]
+    pub fn buffer(&self) -> &[u8] {
+        self.buffer.as_slice()
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+
+    /// Writes all aligned blocks from the buffer to the underlying writer.
+    fn flush_aligned(&mut self) -> io::Result<()> {
+        let length = self.buffer.filled - self.buffer.filled % self.alignment;
+        self.write_buffered(length)
+    }
+
+    /// Writes first `length` bytes from the buffer, consuming them as they are written so a failed write can be
+    /// retried.
+    ///
+    /// The buffer always starts at an aligned address and the written data ends at an aligned offset, as long as the
+    /// underlying writer accepts multiples of the alignment. Any other short write results in an error of kind
+    /// [`io::ErrorKind::InvalidData`], as it would leave the next write unaligned.
+    fn write_buffered(&mut self, mut length: usize) -> io::Result<()> {
+        while length > 0 {
+            match self.inner.write(&self.buffer.as_slice()[..length]) {
+                Ok(0) => {
+                    let error = io::Error::new(io::ErrorKind::WriteZero, "failed to write the buffered data");
+                    return Err(error);
+                },
+                Ok(n) => {
+                    self.buffer.consume(n);
+                    length -= n;
+                    if n % self.alignment != 0 {
+                        let error = io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "underlying writer accepted unaligned number of bytes",
+                        );
+                        return Err(error);
+                    }
+                },
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the unaligned tail padded with zeros and discards the padding.
+    ///
+    /// The padding stays buffered until it is written, so a retry only writes the rest.
+    fn finish_tail(&mut self) -> io::Result<()>
+    where
+        W: Truncate,
+    {
+        let tail = self.buffer.filled % self.alignment;
+        if tail > 0 {
+            let padding = self.alignment - tail;
+            self.buffer.pad(padding);
+            self.padding += padding;
+        }
+        self.write_buffered(self.buffer.filled)?;
+        if self.padding > 0 {
+            self.inner.flush()?;
+            self.inner.truncate_tail(self.padding as u64)?;
+            self.padding = 0;
+        }
+        self.inner.flush()
+    }
+
+    /// Writes the remaining data, including the unaligned tail, and returns the underlying writer with calculated
+    /// hash digest.
+    ///
+    /// The tail is padded with zeros up to the alignment, and the padding is then discarded with
+    /// [`Truncate::truncate_tail`].
+    ///
+    /// On failure the writer is returned within the error, with unwritten data and padding still buffered, so finishing
+    /// can be retried.
+    pub fn finish(mut self) -> Result<(W, H::Digest), IntoInnerError<Self>>
+    where
+        W: Truncate,
+    {
+        if let Err(error) = self.finish_tail() {
+            return Err(IntoInnerError::new(self, error));
+        }
+        let digest = self.hash.digest();
+        let Self { inner, .. } = self;
+        Ok((inner, digest))
+    }
+}
+
+impl<W, H> Write for AlignedWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buffer.is_full() {
+            self.flush_aligned()?;
+        }
+        let n = self.buffer.push(buf);
+        self.hash.update(&buf[..n]);
+        Ok(n)
+    }
+
+    /// Writes all aligned blocks and flushes the underlying writer, keeping the unaligned tail buffered.
+    fn flush(&mut self) -> io::Result<()> {
+        self.flush_aligned()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use chksum_core::Hash;
+
+    use super::{AlignedWriter, Truncate};
+    use crate::testing::Sha256;
+
+    /// Accepts at most `limit` bytes per write like a file opened with `O_DIRECT`, failing unaligned writes.
+    #[derive(Debug, Default)]
+    struct DirectWriter {
+        data: Vec<u8>,
+        limit: usize,
+        alignment: usize,
+    }
+
+    impl DirectWriter {
+        fn new(alignment: usize, limit: usize) -> Self {
+            Self {
+                data: Vec::new(),
+                limit,
+                alignment,
+            }
+        }
+    }
+
+    impl Write for DirectWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let aligned = buf.as_ptr() as usize % self.alignment == 0 && self.data.len() % self.alignment == 0;
+            if !aligned || buf.len() % self.alignment != 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "unaligned write"));
+            }
+            let n = buf.len().min(self.limit);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Truncate for DirectWriter {
+        fn truncate_tail(&mut self, n: u64) -> io::Result<()> {
+            self.data.truncate(self.data.len() - n as usize);
+            Ok(())
+        }
+    }
+
+    fn data() -> Vec<u8> {
+        (0..1000u32).map(|index| index as u8).collect()
+    }
+
+    #[test]
+    fn aligned_short_writes() {
+        for limit in [16, 48, 1024] {
+            let mut writer = AlignedWriter::<_, Sha256>::with_alignment(DirectWriter::new(16, limit), 16, 64);
+            for piece in data().chunks(7) {
+                writer.write_all(piece).unwrap();
+            }
+            writer.flush().unwrap();
+            assert_eq!(writer.buffer().len(), 1000 % 16);
+            let (inner, digest) = writer.finish().unwrap();
+            assert_eq!(inner.data, data());
+            assert_eq!(digest, Sha256::hash(data()));
+        }
+    }
+
+    #[test]
+    fn unaligned_short_write() {
+        let mut writer = AlignedWriter::<_, Sha256>::with_alignment(DirectWriter::new(1, 10), 16, 64);
+        writer.write_all(&data()[..64]).unwrap();
+        let error = writer.write_all(&data()[64..128]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn retries_tail() {
+        let mut writer = AlignedWriter::<_, Sha256>::with_alignment(DirectWriter::new(16, 1024), 16, 64);
+        writer.write_all(&data()[..20]).unwrap();
+        // the padded tail is rejected once
+        writer.inner.alignment = 64;
+        let error = writer.finish().unwrap_err();
+        let mut writer = error.into_inner();
+        assert_eq!(writer.buffer().len(), 32);
+        writer.inner.alignment = 16;
+        let (inner, digest) = writer.finish().unwrap();
+        assert_eq!(inner.data, data()[..20]);
+        assert_eq!(digest, Sha256::hash(&data()[..20]));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{Context, Poll};
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::Writer;
+
+/// Maximal number of bytes of the running digest kept in [`AuditEntry`].
+const PREFIX: usize = 8;
+
+/// Record of a single write, kept by [`AuditWriter`] and [`AsyncAuditWriter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AuditEntry {
+    offset: u64,
+    length: usize,
+    prefix: [u8; PREFIX],
+    prefix_length: usize,
+}
+
+impl AuditEntry {
+    fn new(offset: u64, length: usize, digest: &[u8]) -> Self {
+        let prefix_length = digest.len().min(PREFIX);
+        let mut prefix = [0; PREFIX];
+        prefix[..prefix_length].copy_from_slice(&digest[..prefix_length]);
+        Self {
+            offset,
+            length,
+            prefix,
+            prefix_length,
+        }
+    }
+
+    /// Returns stream offset of the write.
+    #[must_use]
+    pub const fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns number of bytes accepted by the write.
+    #[must_use]
+    pub const fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Returns up to 8 leading bytes of the running digest after the write.
+    #[must_use]
+    pub fn digest_prefix(&self) -> &[u8] {
+        &self.prefix[..self.prefix_length]
+    }
+}
+
+impl Display for AuditEntry {
+    /// Formats the entry as `<offset> <length> <digest prefix>`, with the prefix in hexadecimal.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ", self.offset, self.length)?;
+        for byte in self.digest_prefix() {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Destination of [`AuditEntry`] records.
+pub trait AuditSink {
+    /// Records entry of a single write.
+    fn record(&mut self, entry: AuditEntry);
+}
+
+impl AuditSink for Vec<AuditEntry> {
+    fn record(&mut self, entry: AuditEntry) {
+        self.push(entry);
+    }
+}
+
+impl<S> AuditSink for &mut S
+where
+    S: AuditSink + ?Sized,
+{
+    fn record(&mut self, entry: AuditEntry) {
+        (**self).record(entry);
+    }
+}
+
+/// Ring buffer keeping the given number of the most recent [`AuditEntry`] records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditRing {
+    entries: VecDeque<AuditEntry>,
+    capacity: usize,
+}
+
+impl AuditRing {
+    /// Creates new [`AuditRing`] keeping at most `capacity` entries.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Returns kept entries, from the oldest to the most recent.
+    pub fn entries(&self) -> impl Iterator<Item = &AuditEntry> {
+        self.entries.iter()
+    }
+}
+
+impl AuditSink for AuditRing {
+    fn record(&mut self, entry: AuditEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+/// Wraps a [`Writer`] and records offset, length and running digest prefix of every write into a sink.
+///
+/// Calculating the running digest after every write is expensive, so this is meant for debugging digest mismatches
+/// rather than for regular use.
+#[derive(Clone, Debug)]
+pub struct AuditWriter<W, H, S>
+where
+    W: Write,
+    H: Hash,
+    S: AuditSink,
+{
+    writer: Writer<W, H>,
+    sink: S,
+}
+
+impl<W, H, S> AuditWriter<W, H, S>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    S: AuditSink,
+{
+    /// Creates new [`AuditWriter`].
+    #[must_use]
+    pub const fn new(writer: Writer<W, H>, sink: S) -> Self {
+        Self { writer, sink }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Returns the sink of the records.
+    #[must_use]
+    pub const fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Unwraps this [`AuditWriter`], returning the audited writer and the sink.
+    #[must_use]
+    pub fn into_parts(self) -> (Writer<W, H>, S) {
+        let Self { writer, sink } = self;
+        (writer, sink)
+    }
+}
+
+impl<W, H, S> Write for AuditWriter<W, H, S>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    S: AuditSink,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let offset = self.writer.count();
+        let n = self.writer.write(buf)?;
+        let entry = AuditEntry::new(offset, n, self.writer.digest().as_ref());
+        self.sink.record(entry);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Wraps an [`AsyncWriter`] and records offset, length and running digest prefix of every write into a sink.
+///
+/// Calculating the running digest after every write is expensive, so this is meant for debugging digest mismatches
+/// rather than for regular use.
+#[cfg(feature = "async-runtime-tokio")]
+#[derive(Clone, Debug)]
+pub struct AsyncAuditWriter<W, H, S>
+where
+    W: AsyncWrite,
+    H: Hash,
+    S: AuditSink,
+{
+    writer: AsyncWriter<W, H>,
+    sink: S,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H, S> AsyncAuditWriter<W, H, S>
+where
+    W: AsyncWrite,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    S: AuditSink,
+{
+    /// Creates new [`AsyncAuditWriter`].
+    #[must_use]
+    pub const fn new(writer: AsyncWriter<W, H>, sink: S) -> Self {
+        Self { writer, sink }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Returns the sink of the records.
+    #[must_use]
+    pub const fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Unwraps this [`AsyncAuditWriter`], returning the audited writer and the sink.
+    #[must_use]
+    pub fn into_parts(self) -> (AsyncWriter<W, H>, S) {
+        let Self { writer, sink } = self;
+        (writer, sink)
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H, S> AsyncWrite for AsyncAuditWriter<W, H, S>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+    H::Digest: AsRef<[u8]>,
+    S: AuditSink + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let Self { writer, sink } = self.get_mut();
+        let offset = writer.count();
+        match pin!(&mut *writer).poll_write(cx, buf) {
+            Poll::Ready(Ok(n)) => {
+                let entry = AuditEntry::new(offset, n, writer.digest().as_ref());
+                sink.record(entry);
+                Poll::Ready(Ok(n))
+            },
+            poll => poll,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,96 @@
+/// Alphabet of the standard base64 encoding.
+pub(crate) const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes bytes with standard base64, without padding.
+pub(crate) fn encode_unpadded(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity((bytes.len() * 4 + 2) / 3);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, &byte)| {
+            group | u32::from(byte) << (16 - 8 * index)
+        });
+        // without padding, a chunk of n bytes takes n + 1 characters
+        for index in 0..=chunk.len() {
+            let sextet = (group >> (18 - 6 * index)) & 0x3F;
+            text.push(char::from(ALPHABET[sextet as usize]));
+        }
+    }
+    text
+}
+
+/// Decodes standard base64 text with optional padding, returning [`None`] if it is malformed.
+#[cfg(feature = "content-digest")]
+pub(crate) fn decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    if text.len() % 4 == 1 {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut group, mut bits) = (0u32, 0);
+    for byte in text.bytes() {
+        let sextet = ALPHABET.iter().position(|&char| char == byte)?;
+        group = (group << 6) | sextet as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((group >> bits) as u8);
+            group &= (1 << bits) - 1;
+        }
+    }
+    // leftover bits of the last character must be zero in canonical encoding
+    (group == 0).then_some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "content-digest")]
+    use super::decode;
+    use super::encode_unpadded;
+
+    #[test]
+    fn encodes_without_padding() {
+        let vectors = [
+            ("", ""),
+            ("f", "Zg"),
+            ("fo", "Zm8"),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg"),
+            ("fooba", "Zm9vYmE"),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (bytes, text) in vectors {
+            assert_eq!(encode_unpadded(bytes.as_bytes()), text, "{bytes}");
+        }
+        assert_eq!(encode_unpadded(&[0xFB, 0xFF, 0xBF]), "+/+/");
+    }
+
+    #[cfg(feature = "content-digest")]
+    #[test]
+    fn rfc4648_vectors() {
+        let vectors = [
+            ("", ""),
+            ("Zg==", "f"),
+            ("Zm8=", "fo"),
+            ("Zm9v", "foo"),
+            ("Zm9vYg==", "foob"),
+            ("Zm9vYmE=", "fooba"),
+            ("Zm9vYmFy", "foobar"),
+        ];
+        for (text, bytes) in vectors {
+            assert_eq!(decode(text).as_deref(), Some(bytes.as_bytes()), "{text}");
+            assert_eq!(
+                decode(text.trim_end_matches('=')).as_deref(),
+                Some(bytes.as_bytes()),
+                "{text}"
+            );
+        }
+        assert_eq!(decode("+/+/").unwrap(), [0xFB, 0xFF, 0xBF]);
+    }
+
+    #[cfg(feature = "content-digest")]
+    #[test]
+    fn rejects_malformed() {
+        for text in ["Z", "Zm9vY", "Zm9v!", "Zm-_", "Zh==", "Zm9=", "Zm 9v"] {
+            assert_eq!(decode(text), None, "{text}");
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::io::{self, Read, Write};
+use std::num::NonZeroU64;
+
+use crate::record::DigestSet;
+
+/// Magic bytes starting a serialized Bloom filter.
+const MAGIC: &[u8; 8] = b"CHKSBLOM";
+/// Version of the serialized Bloom filter format.
+const VERSION: u16 = 1;
+/// Maximum number of hash functions, well above what any practical false positive rate needs.
+const MAX_HASHES: u32 = 64;
+
+/// Approximate-membership filter of digests, answering whether a digest was possibly inserted.
+///
+/// Lets a peer test cheaply whether it already has a chunk or a record, without transferring all the digests. Created
+/// by [`HashListWriter::bloom_filter`](crate::HashListWriter::bloom_filter) and
+/// [`RecordWriter::bloom_filter`](crate::RecordWriter::bloom_filter), and exchanged with [`BloomFilter::write_to`]
+/// and [`BloomFilter::read_from`].
+///
+/// Bit positions of a digest are derived by double hashing of its 64-bit FNV-1a hash, so digests of any length,
+/// including short checksums, are spread over the whole filter. Filters are only comparable if created with the same
+/// number of bits and hash functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    words: Vec<u64>,
+    bits: NonZeroU64,
+    hashes: u32,
+}
+
+impl BloomFilter {
+    /// Creates new empty [`BloomFilter`] with the given number of bits and hash functions.
+    ///
+    /// The number of hash functions is clamped between 1 and 64.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of bits does not fit into memory.
+    #[must_use]
+    pub fn new(bits: NonZeroU64, hashes: u32) -> Self {
+        let words = usize::try_from((bits.get() - 1) / 64 + 1).expect("number of bits fits into memory");
+        let words = vec![0; words];
+        let hashes = hashes.clamp(1, MAX_HASHES);
+        Self { words, bits, hashes }
+    }
+
+    /// Creates new empty [`BloomFilter`] sized for the expected number of items and false positive rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is not between 0 and 1, exclusive.
+    #[must_use]
+    pub fn with_rate(items: usize, rate: f64) -> Self {
+        assert!(rate > 0.0 && rate < 1.0, "false positive rate must be between 0 and 1");
+        let items = items.max(1) as f64;
+        let bits = (-items * rate.ln() / (std::f64::consts::LN_2 * std::f64::consts::LN_2)).ceil();
+        let bits = NonZeroU64::new(bits as u64).unwrap_or(NonZeroU64::MIN);
+        let hashes = (bits.get() as f64 / items * std::f64::consts::LN_2).round() as u32;
+        Self::new(bits, hashes)
+    }
+
+    /// Returns number of bits of the filter.
+    #[must_use]
+    pub const fn bits(&self) -> NonZeroU64 {
+        self.bits
+    }
+
+    /// Returns number of hash functions of the filter.
+    #[must_use]
+    pub const fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    /// Inserts the digest into the filter.
+    pub fn insert(&mut self, digest: impl AsRef<[u8]>) {
+        for index in self.indices(digest.as_ref()) {
+            self.words[index / 64] |= 1 << (index % 64);
+        }
+    }
+
+    /// Returns `false` if the digest was definitely not inserted, and `true` if it possibly was.
+    #[must_use]
+    pub fn contains(&self, digest: impl AsRef<[u8]>) -> bool {
+        self.indices(digest.as_ref())
+            .all(|index| self.words[index / 64] & (1 << (index % 64)) != 0)
+    }
+
+    fn indices(&self, digest: &[u8]) -> impl Iterator<Item = usize> {
+        let first = fnv1a(digest);
+        let second = mix(first) | 1;
+        let bits = self.bits.get();
+        (0..u64::from(self.hashes)).map(move |index| {
+            // the index is lower than the number of bits, which fits into memory
+            (first.wrapping_add(index.wrapping_mul(second)) % bits) as usize
+        })
+    }
+
+    /// Writes this filter in binary format.
+    ///
+    /// The filter starts with a header of the magic bytes `CHKSBLOM`, the format version, the number of hash
+    /// functions and the number of bits, followed by the bits packed into 64-bit words, least significant bit first.
+    /// Integers are big-endian, the version takes 2 bytes, the number of hash functions 4 bytes and the rest 8 bytes.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_be_bytes())?;
+        writer.write_all(&self.hashes.to_be_bytes())?;
+        writer.write_all(&self.bits.get().to_be_bytes())?;
+        for word in &self.words {
+            writer.write_all(&word.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads filter from binary format, see [`BloomFilter::write_to`] for the format.
+    ///
+    /// Unknown magic bytes or version, inconsistent headers and more than 64 hash functions result in an error of kind
+    /// [`io::ErrorKind::InvalidData`].
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a bloom filter"));
+        }
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        if u16::from_be_bytes(version) != VERSION {
+            return Err(invalid("unsupported bloom filter version"));
+        }
+        let mut hashes = [0; 4];
+        reader.read_exact(&mut hashes)?;
+        let hashes = u32::from_be_bytes(hashes);
+        let mut bits = [0; 8];
+        reader.read_exact(&mut bits)?;
+        let bits = NonZeroU64::new(u64::from_be_bytes(bits)).ok_or_else(|| invalid("zero number of bits"))?;
+        if hashes == 0 {
+            return Err(invalid("zero number of hash functions"));
+        }
+        if hashes > MAX_HASHES {
+            return Err(invalid("too many hash functions"));
+        }
+        let words = usize::try_from((bits.get() - 1) / 64 + 1).map_err(|_| invalid("too many bits"))?;
+        let mut filter = Self {
+            words: Vec::new(),
+            bits,
+            hashes,
+        };
+        for _ in 0..words {
+            let mut word = [0; 8];
+            reader.read_exact(&mut word)?;
+            filter.words.push(u64::from_be_bytes(word));
+        }
+        Ok(filter)
+    }
+}
+
+impl<D> DigestSet<D> for BloomFilter
+where
+    D: AsRef<[u8]>,
+{
+    fn insert(&mut self, digest: &D) -> bool {
+        let inserted = self.contains(digest);
+        Self::insert(self, digest);
+        !inserted
+    }
+}
+
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01B3)
+    })
+}
+
+/// Finalizer of SplitMix64, deriving the second hash from the first one.
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::num::NonZeroU64;
+
+    use super::{fnv1a, BloomFilter, MAGIC, VERSION};
+
+    #[test]
+    fn fnv1a_known_answers() {
+        assert_eq!(fnv1a(b""), 0xCBF2_9CE4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xAF63_DC4C_8601_EC8C);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_F739_67E8);
+    }
+
+    #[test]
+    fn contains_inserted_digests() {
+        let mut filter = BloomFilter::with_rate(100, 0.01);
+        for index in 0..100_u32 {
+            filter.insert(index.to_be_bytes());
+        }
+        assert!((0..100_u32).all(|index| filter.contains(index.to_be_bytes())));
+        let false_positives = (100..10_100_u32)
+            .filter(|index| filter.contains(index.to_be_bytes()))
+            .count();
+        assert!(false_positives < 300, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut filter = BloomFilter::new(NonZeroU64::new(100).unwrap(), 3);
+        filter.insert(b"digest");
+        let mut bytes = Vec::new();
+        filter.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + 2 + 4 + 8 + 2 * 8);
+        assert_eq!(BloomFilter::read_from(&bytes[..]).unwrap(), filter);
+    }
+
+    #[test]
+    fn clamps_hashes() {
+        assert_eq!(BloomFilter::new(NonZeroU64::MIN, 0).hashes(), 1);
+        assert_eq!(BloomFilter::new(NonZeroU64::MIN, 1000).hashes(), 64);
+    }
+
+    #[test]
+    fn rejects_too_many_hashes() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_be_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+        bytes.extend_from_slice(&64_u64.to_be_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+        let error = BloomFilter::read_from(&bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::{self, Write};
+use std::mem;
+use std::num::NonZeroU64;
+use std::ops::RangeInclusive;
+
+use chksum_core::Hash;
+
+use crate::Algorithm;
+
+/// Wraps a writer of a disk image and builds its block map in the [bmaptool] XML format.
+///
+/// Blocks consisting only of zero bytes are treated as unmapped, flasher tools can skip them. Contiguous mapped blocks
+/// are merged into ranges, and every range is described with the digest of its data. The current block is buffered
+/// until it is complete, so the buffer never exceeds the block size.
+///
+/// The checksum type is derived from [`Algorithm::NAME`], so `SHA-256` becomes `sha256`, and digests are formatted with
+/// their [`Display`](std::fmt::Display) implementation, expected to produce lowercase hexadecimal strings.
+///
+/// [bmaptool]: https://github.com/yoctoproject/bmaptool
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BmapWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    block_size: NonZeroU64,
+    block: Vec<u8>,
+    block_len: usize,
+    size: u64,
+    blocks: u64,
+    start: Option<u64>,
+    ranges: Vec<(RangeInclusive<u64>, H::Digest)>,
+}
+
+impl<W, H> BmapWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    /// Creates new [`BmapWriter`] with the given block size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block size does not fit into memory.
+    pub fn new(inner: W, block_size: NonZeroU64) -> Self {
+        let block_len = usize::try_from(block_size.get()).expect("block size fits into memory");
+        Self {
+            inner,
+            hash: H::default(),
+            block_size,
+            block: Vec::with_capacity(block_len),
+            block_len,
+            size: 0,
+            blocks: 0,
+            start: None,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Returns size of the mapped blocks.
+    #[must_use]
+    pub const fn block_size(&self) -> NonZeroU64 {
+        self.block_size
+    }
+
+    /// Finishes the image, flushes the underlying writer and writes the block map into `bmap`.
+    ///
+    /// The checksum of the block map itself is calculated with its value replaced by zeros, as bmaptool expects.
+    pub fn finish(mut self, mut bmap: impl Write) -> io::Result<W> {
+        if !self.block.is_empty() {
+            self.end_block();
+        }
+        self.end_range();
+        self.inner.flush()?;
+        let zeros = "0".repeat(H::DIGEST_SIZE * 2);
+        let xml = self.xml(&zeros);
+        let checksum = H::hash(xml.as_bytes()).to_string();
+        let xml = xml.replacen(&zeros, &checksum, 1);
+        bmap.write_all(xml.as_bytes())?;
+        let Self { inner, .. } = self;
+        Ok(inner)
+    }
+
+    /// Returns block map with the given checksum of the block map itself.
+    fn xml(&self, checksum: &str) -> String {
+        let checksum_type: String = H::NAME
+            .chars()
+            .filter(|&char| char != '-')
+            .map(|char| char.to_ascii_lowercase())
+            .collect();
+        let mapped: u64 = self
+            .ranges
+            .iter()
+            .map(|(range, _)| range.end() - range.start() + 1)
+            .sum();
+        let mut lines = vec![
+            "<?xml version=\"1.0\" ?>".to_owned(),
+            "<bmap version=\"2.0\">".to_owned(),
+            format!("    <ImageSize> {} </ImageSize>", self.size),
+            format!("    <BlockSize> {} </BlockSize>", self.block_size),
+            format!("    <BlocksCount> {} </BlocksCount>", self.blocks),
+            format!("    <MappedBlocksCount> {mapped} </MappedBlocksCount>"),
+            format!("    <ChecksumType> {checksum_type} </ChecksumType>"),
+            format!("    <BmapFileChecksum> {checksum} </BmapFileChecksum>"),
+            "    <BlockMap>".to_owned(),
+        ];
+        for (range, digest) in &self.ranges {
+            let (first, last) = (range.start(), range.end());
+            if first == last {
+                lines.push(format!("        <Range chksum=\"{digest}\"> {first} </Range>"));
+            } else {
+                lines.push(format!("        <Range chksum=\"{digest}\"> {first}-{last} </Range>"));
+            }
+        }
+        lines.push("    </BlockMap>".to_owned());
+        lines.push("</bmap>".to_owned());
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let remaining = self.block_len - self.block.len();
+            let (chunk, rest) = data.split_at(data.len().min(remaining));
+            self.block.extend_from_slice(chunk);
+            if self.block.len() == self.block_len {
+                self.end_block();
+                self.block.clear();
+            }
+            data = rest;
+        }
+    }
+
+    fn end_block(&mut self) {
+        if self.block.iter().all(|&byte| byte == 0) {
+            self.end_range();
+        } else {
+            self.start.get_or_insert(self.blocks);
+            self.hash.update(&self.block);
+        }
+        self.blocks += 1;
+    }
+
+    fn end_range(&mut self) {
+        if let Some(start) = self.start.take() {
+            let hash = mem::take(&mut self.hash);
+            self.ranges.push((start..=self.blocks - 1, hash.digest()));
+        }
+    }
+}
+
+impl<W, H> Write for BmapWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::num::NonZeroU64;
+
+    use super::BmapWriter;
+    use crate::testing::Sha256;
+
+    const BMAP: &str = r#"<?xml version="1.0" ?>
+<bmap version="2.0">
+    <ImageSize> 18 </ImageSize>
+    <BlockSize> 4 </BlockSize>
+    <BlocksCount> 5 </BlocksCount>
+    <MappedBlocksCount> 4 </MappedBlocksCount>
+    <ChecksumType> sha256 </ChecksumType>
+    <BmapFileChecksum> e3995f29968f74157c55368f9ac63ebb5f0ebf38d4809ab09d5eb1f6845da78f </BmapFileChecksum>
+    <BlockMap>
+        <Range chksum="88d4266fd4e6338d13b845fcf289579d209c897823b9217da3e161936f031589"> 0 </Range>
+        <Range chksum="533491ff738d367ed4ff59bd3303954c86704ea83410b945b9ebda7e76185e2a"> 2-4 </Range>
+    </BlockMap>
+</bmap>
+"#;
+
+    #[test]
+    fn block_map() {
+        let image = b"abcd\0\0\0\0efghijklmn";
+        for piece in [1, 3, 4, image.len()] {
+            let mut writer = BmapWriter::<_, Sha256>::new(Vec::new(), NonZeroU64::new(4).unwrap());
+            for piece in image.chunks(piece) {
+                writer.write_all(piece).unwrap();
+            }
+            let mut bmap = Vec::new();
+            assert_eq!(writer.finish(&mut bmap).unwrap(), image);
+            assert_eq!(String::from_utf8(bmap).unwrap(), BMAP);
+        }
+    }
+
+    #[test]
+    fn unmapped_image() {
+        let mut writer = BmapWriter::<_, Sha256>::new(io::sink(), NonZeroU64::new(4).unwrap());
+        writer.write_all(&[0; 10]).unwrap();
+        let mut bmap = Vec::new();
+        writer.finish(&mut bmap).unwrap();
+        let bmap = String::from_utf8(bmap).unwrap();
+        assert!(bmap.contains("<BlocksCount> 3 </BlocksCount>"));
+        assert!(bmap.contains("<MappedBlocksCount> 0 </MappedBlocksCount>"));
+        assert!(bmap.contains("    <BlockMap>\n    </BlockMap>\n"));
+    }
+}
@@ -0,0 +1,144 @@
+use std::io::Write;
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+use crate::guard::GuardBuilder;
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{UpdateOrder, Writer};
+
+/// Part of the suffix folded into the hash at finalize.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Suffix {
+    /// Fixed bytes.
+    Bytes(Vec<u8>),
+    /// Number of bytes written, as a big-endian 64-bit integer.
+    Length,
+}
+
+/// Folds the suffix into the hash, leaving the suffix empty so it is never folded twice.
+pub(crate) fn fold<H>(hash: &mut H, suffix: &mut Vec<Suffix>, count: u64)
+where
+    H: Hash,
+{
+    for part in suffix.drain(..) {
+        match part {
+            Suffix::Bytes(bytes) => hash.update(bytes),
+            Suffix::Length => hash.update(count.to_be_bytes()),
+        }
+    }
+}
+
+/// Builder of [`Writer`] and [`AsyncWriter`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Builder<H>
+where
+    H: Hash,
+{
+    hash: H,
+    suffix: Vec<Suffix>,
+    order: UpdateOrder,
+}
+
+impl<H> Builder<H>
+where
+    H: Hash,
+{
+    /// Creates new [`Builder`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates new [`Builder`] with provided hash.
+    #[must_use]
+    pub const fn with_hash(hash: H) -> Self {
+        let suffix = Vec::new();
+        let order = UpdateOrder::AfterWrite;
+        Self { hash, suffix, order }
+    }
+
+    /// Creates new [`Builder`] with hash created by the factory.
+    #[must_use]
+    pub fn with_hash_fn(factory: impl FnOnce() -> H) -> Self {
+        let hash = factory();
+        Self::with_hash(hash)
+    }
+
+    /// Feeds the bytes into the hash before any stream data, without writing them to the underlying writer.
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: impl AsRef<[u8]>) -> Self {
+        self.hash.update(prefix);
+        self
+    }
+
+    /// Feeds the salt into the hash before any stream data, without writing it to the underlying writer.
+    ///
+    /// This is the same as [`Builder::with_prefix`].
+    #[must_use]
+    pub fn with_salt(self, salt: impl AsRef<[u8]>) -> Self {
+        self.with_prefix(salt)
+    }
+
+    /// Folds the bytes into the hash at finalize, without writing them to the underlying writer.
+    ///
+    /// Suffixes are folded in the order they were added.
+    #[must_use]
+    pub fn with_suffix(mut self, suffix: impl Into<Vec<u8>>) -> Self {
+        self.suffix.push(Suffix::Bytes(suffix.into()));
+        self
+    }
+
+    /// Folds the number of bytes written, as a big-endian 64-bit integer, into the hash at finalize.
+    ///
+    /// Suffixes are folded in the order they were added.
+    #[must_use]
+    pub fn with_length_suffix(mut self) -> Self {
+        self.suffix.push(Suffix::Length);
+        self
+    }
+
+    /// Sets whether the hash of the built writer is updated before or after the write to the underlying writer, see
+    /// [`UpdateOrder`].
+    #[must_use]
+    pub const fn with_update_order(mut self, order: UpdateOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets hook invoked with the final digest exactly once, when the built writer is finalized or shut down.
+    ///
+    /// The writer is built wrapped in a [`DigestGuard`](crate::DigestGuard) or an
+    /// [`AsyncDigestGuard`](crate::AsyncDigestGuard), which also asserts in debug builds that the digest is read.
+    #[must_use]
+    pub fn on_finalize(self, hook: impl FnOnce(&H::Digest) + Send + 'static) -> GuardBuilder<H> {
+        GuardBuilder::new(self, Box::new(hook))
+    }
+
+    /// Builds [`Writer`] wrapping the given writer.
+    #[must_use]
+    pub fn build<W>(self, inner: W) -> Writer<W, H>
+    where
+        W: Write,
+    {
+        let Self { hash, suffix, order } = self;
+        let mut writer = Writer::with_hash(inner, hash).with_update_order(order);
+        writer.suffix = suffix;
+        writer
+    }
+
+    /// Builds [`AsyncWriter`] wrapping the given writer.
+    #[cfg(feature = "async-runtime-tokio")]
+    #[must_use]
+    pub fn build_async<W>(self, inner: W) -> AsyncWriter<W, H>
+    where
+        W: AsyncWrite,
+    {
+        let Self { hash, suffix, order } = self;
+        let mut writer = AsyncWriter::with_hash(inner, hash).with_update_order(order);
+        writer.suffix = suffix;
+        writer
+    }
+}
@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::escape::{escape, unescape};
+use crate::temp::{SaveOptions, Temporary};
+use crate::{Algorithm, Writer};
+
+/// Identifies a file together with its size and modification time, so changed files are not matched.
+///
+/// The key includes the name of the algorithm, so digests of one algorithm are never returned for another.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Name of the hash algorithm, see [`Algorithm::NAME`].
+    pub algorithm: String,
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Size of the file.
+    pub size: u64,
+    /// Modification time of the file.
+    pub modified: SystemTime,
+}
+
+impl CacheKey {
+    /// Creates new [`CacheKey`] for the algorithm from metadata of the file.
+    pub fn from_path<H>(path: impl Into<PathBuf>) -> io::Result<Self>
+    where
+        H: Algorithm,
+    {
+        let path = path.into();
+        let metadata = fs::metadata(&path)?;
+        Ok(Self {
+            algorithm: H::NAME.to_owned(),
+            path,
+            size: metadata.len(),
+            modified: metadata.modified()?,
+        })
+    }
+}
+
+/// A trait for stores of digests used by [`DigestCache`].
+pub trait DigestStore {
+    /// Returns digest recorded for the key.
+    fn get(&self, key: &CacheKey) -> Option<String>;
+
+    /// Records digest for the key.
+    fn put(&mut self, key: CacheKey, digest: String);
+}
+
+/// In-memory [`DigestStore`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    digests: HashMap<CacheKey, String>,
+}
+
+impl MemoryStore {
+    /// Creates new empty [`MemoryStore`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DigestStore for MemoryStore {
+    fn get(&self, key: &CacheKey) -> Option<String> {
+        self.digests.get(key).cloned()
+    }
+
+    fn put(&mut self, key: CacheKey, digest: String) {
+        self.digests.insert(key, digest);
+    }
+}
+
+/// [`DigestStore`] persisted in a text file.
+///
+/// Records are kept in memory and written out by [`FileStore::save`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileStore {
+    path: PathBuf,
+    store: MemoryStore,
+    options: SaveOptions,
+}
+
+impl FileStore {
+    /// Opens [`FileStore`] at the given path, loading records if the file exists.
+    ///
+    /// Malformed lines result in an error of kind [`io::ErrorKind::InvalidData`].
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut store = MemoryStore::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for (number, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line?;
+                    let (key, digest) = parse_record(&line).ok_or_else(|| {
+                        let message = format!("malformed cache line {}", number + 1);
+                        io::Error::new(io::ErrorKind::InvalidData, message)
+                    })?;
+                    store.put(key, digest);
+                }
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+            Err(error) => return Err(error),
+        }
+        Ok(Self {
+            path,
+            store,
+            options: SaveOptions::new(),
+        })
+    }
+
+    /// Sets options of saving the records.
+    #[must_use]
+    pub fn with_options(mut self, options: SaveOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Writes all records to the file, replacing it.
+    ///
+    /// Algorithm names containing whitespace cannot be stored and result in an error of kind
+    /// [`io::ErrorKind::InvalidInput`].
+    pub fn save(&self) -> io::Result<()> {
+        let (temporary, file) = Temporary::create(&self.path, &self.options)?;
+        let mut writer = BufWriter::new(file);
+        for (key, digest) in &self.store.digests {
+            if key.algorithm.is_empty() || key.algorithm.contains(char::is_whitespace) {
+                let message = format!("invalid algorithm name {:?}", key.algorithm);
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+            }
+            let modified = key.modified.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+            let (escape, path) = escape(&key.path.to_string_lossy());
+            writeln!(
+                writer,
+                "{escape}{digest} {} {} {}.{:09} {path}",
+                key.algorithm,
+                key.size,
+                modified.as_secs(),
+                modified.subsec_nanos()
+            )?;
+        }
+        writer.into_inner()?.sync_all()?;
+        temporary.persist(&self.path)
+    }
+}
+
+impl DigestStore for FileStore {
+    fn get(&self, key: &CacheKey) -> Option<String> {
+        self.store.get(key)
+    }
+
+    fn put(&mut self, key: CacheKey, digest: String) {
+        self.store.put(key, digest);
+    }
+}
+
+fn parse_record(line: &str) -> Option<(CacheKey, String)> {
+    let (line, escaped) = match line.strip_prefix('\\') {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+    let mut parts = line.splitn(5, ' ');
+    let digest = parts.next()?;
+    let algorithm = parts.next().filter(|algorithm| !algorithm.is_empty())?;
+    let size = parts.next()?.parse().ok()?;
+    let (secs, nanos) = parts.next()?.split_once('.')?;
+    let nanos = nanos.parse().ok().filter(|&nanos| nanos < 1_000_000_000)?;
+    let modified = Duration::new(secs.parse().ok()?, nanos);
+    let path = parts.next()?;
+    let path = if escaped { unescape(path)? } else { path.to_owned() };
+    let key = CacheKey {
+        algorithm: algorithm.to_owned(),
+        path: PathBuf::from(path),
+        size,
+        modified: SystemTime::UNIX_EPOCH.checked_add(modified)?,
+    };
+    Some((key, digest.to_owned()))
+}
+
+/// Skips rehashing of files which did not change since their digest was recorded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigestCache<S>
+where
+    S: DigestStore,
+{
+    store: S,
+}
+
+impl<S> DigestCache<S>
+where
+    S: DigestStore,
+{
+    /// Creates new [`DigestCache`] backed by the given store.
+    #[must_use]
+    pub const fn new(store: S) -> Self {
+        Self { store }
+    }
+
+    /// Returns digest recorded for the file with the algorithm, if it did not change since.
+    pub fn lookup<H>(&self, path: impl AsRef<Path>) -> io::Result<Option<String>>
+    where
+        H: Algorithm,
+    {
+        let key = CacheKey::from_path::<H>(path.as_ref())?;
+        Ok(self.store.get(&key))
+    }
+
+    /// Records digest of the file computed with the algorithm, the file should be already finalized and flushed.
+    pub fn record<H>(&mut self, path: impl AsRef<Path>, digest: impl Display) -> io::Result<()>
+    where
+        H: Algorithm,
+    {
+        let key = CacheKey::from_path::<H>(path.as_ref())?;
+        self.store.put(key, digest.to_string());
+        Ok(())
+    }
+
+    /// Returns digest of the file, hashing it only if it changed since its digest was recorded.
+    pub fn hash_file<H>(&mut self, path: impl AsRef<Path>) -> io::Result<String>
+    where
+        H: Algorithm,
+    {
+        let path = path.as_ref();
+        let key = CacheKey::from_path::<H>(path)?;
+        if let Some(digest) = self.store.get(&key) {
+            return Ok(digest);
+        }
+        let mut writer = Writer::<_, H>::new(io::sink());
+        io::copy(&mut File::open(path)?, &mut writer)?;
+        let digest = writer.digest().to_string();
+        // the file may have changed while it was hashed
+        if CacheKey::from_path::<H>(path)? == key {
+            self.store.put(key, digest.clone());
+        }
+        Ok(digest)
+    }
+
+    /// Returns the underlying store.
+    #[must_use]
+    pub const fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Unwraps this [`DigestCache`], returning the underlying store.
+    #[must_use]
+    pub fn into_store(self) -> S {
+        let Self { store } = self;
+        store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+    use std::{fs, io, process, thread};
+
+    use chksum_core::Hash;
+
+    use super::{CacheKey, DigestCache, DigestStore, FileStore};
+    use crate::temp::SaveOptions;
+    use crate::testing::{Sha1, Sha256};
+
+    fn directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chksum-writer-cache-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+        path
+    }
+
+    fn key(path: &str, nanos: u32) -> CacheKey {
+        CacheKey {
+            algorithm: "SHA-256".to_owned(),
+            path: PathBuf::from(path),
+            size: 42,
+            modified: SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, nanos),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = directory("round-trip").join("cache");
+        let mut store = FileStore::open(&path).unwrap();
+        let keys = [key("plain", 0), key("with space", 1), key("line\nbreak\\", 999_999_999)];
+        for (index, key) in keys.iter().enumerate() {
+            store.put(key.clone(), format!("digest{index}"));
+        }
+        store.save().unwrap();
+
+        let store = FileStore::open(&path).unwrap();
+        for (index, key) in keys.iter().enumerate() {
+            assert_eq!(store.get(key), Some(format!("digest{index}")));
+        }
+        let other = CacheKey {
+            algorithm: "SHA-1".to_owned(),
+            ..key("plain", 0)
+        };
+        assert_eq!(store.get(&other), None);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_malformed_records() {
+        let path = directory("malformed").join("cache");
+        for record in [
+            "digest SHA-256 42 1.000000000\n",
+            "digest SHA-256 42 1.1000000000 path\n",
+            "digest SHA-256 size 1.0 path\n",
+        ] {
+            fs::write(&path, record).unwrap();
+            let error = FileStore::open(&path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{record:?}");
+        }
+        let mut store = FileStore::open(path.with_file_name("other")).unwrap();
+        store.put(
+            CacheKey {
+                algorithm: "SHA 256".to_owned(),
+                ..key("plain", 0)
+            },
+            "digest".to_owned(),
+        );
+        assert_eq!(store.save().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn keeps_temp_on_failure_if_set() {
+        for keep in [false, true] {
+            let directory = directory(&format!("keep-{keep}"));
+            let mut store = FileStore::open(directory.join("cache"))
+                .unwrap()
+                .with_options(SaveOptions::new().keep_temp_on_failure(keep));
+            let key = CacheKey {
+                algorithm: "SHA 256".to_owned(),
+                ..key("plain", 0)
+            };
+            store.put(key, "digest".to_owned());
+            store.save().unwrap_err();
+            let names: Vec<_> = fs::read_dir(&directory)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+                .collect();
+            if keep {
+                assert_eq!(names.len(), 1);
+                assert!(
+                    names[0].starts_with("cache.") && names[0].ends_with(".tmp"),
+                    "{names:?}"
+                );
+            } else {
+                assert!(names.is_empty(), "{names:?}");
+            }
+            fs::remove_dir_all(&directory).unwrap();
+        }
+    }
+
+    #[test]
+    fn skips_unchanged_files() {
+        let directory = directory("hash-file");
+        let file = directory.join("file");
+        fs::write(&file, "abc").unwrap();
+        let mut cache = DigestCache::new(FileStore::open(directory.join("cache")).unwrap());
+        assert_eq!(cache.lookup::<Sha256>(&file).unwrap(), None);
+        let digest = cache.hash_file::<Sha256>(&file).unwrap();
+        assert_eq!(digest, Sha256::hash(b"abc").to_string());
+        assert_eq!(cache.lookup::<Sha256>(&file).unwrap(), Some(digest));
+        assert_eq!(cache.lookup::<Sha1>(&file).unwrap(), None);
+        cache.store().save().unwrap();
+
+        let cache = DigestCache::new(FileStore::open(directory.join("cache")).unwrap());
+        assert!(cache.lookup::<Sha256>(&file).unwrap().is_some());
+        fs::write(&file, "abcd").unwrap();
+        assert_eq!(cache.lookup::<Sha256>(&file).unwrap(), None);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn concurrent_saves() {
+        let directory = directory("concurrent");
+        let path = directory.join("cache");
+        let savers: Vec<_> = (0..8)
+            .map(|index| {
+                let mut store = FileStore::open(&path).unwrap();
+                for record in 0..100 {
+                    store.put(key(&format!("{index}-{record}"), 0), format!("digest{index}"));
+                }
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        store.save().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for saver in savers {
+            saver.join().unwrap();
+        }
+        let store = FileStore::open(&path).unwrap();
+        let index = (0..8)
+            .find(|index| store.get(&key(&format!("{index}-0"), 0)).is_some())
+            .unwrap();
+        for record in 0..100 {
+            assert_eq!(
+                store.get(&key(&format!("{index}-{record}"), 0)),
+                Some(format!("digest{index}"))
+            );
+        }
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+use std::io::{self, Write};
+use std::mem;
+use std::num::NonZeroU64;
+
+use crate::sink::ChunkSink;
+use crate::Algorithm;
+
+/// Type of the index header, `CA_FORMAT_INDEX`.
+const FORMAT_INDEX: u64 = 0x9682_4D9C_7B12_9FF9;
+/// Type of the chunk table header, `CA_FORMAT_TABLE`.
+const FORMAT_TABLE: u64 = 0xE75B_9E11_2F17_417D;
+/// Marker ending the chunk table, `CA_FORMAT_TABLE_TAIL_MARKER`.
+const TABLE_TAIL_MARKER: u64 = 0x4B4F_050E_5549_ECD1;
+/// Size of the index header.
+const INDEX_SIZE: u64 = 48;
+
+/// Random values of the gear hash, one per byte value.
+const GEAR: [u64; 256] = {
+    let mut table = [0; 256];
+    let mut state: u64 = 0x6361_7379_6E63_0000;
+    let mut index = 0;
+    while index < 256 {
+        // SplitMix64
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+};
+
+/// Wraps a writer, splits the stream into content-defined chunks and builds a [casync] chunk index (`.caibx`).
+///
+/// Chunk boundaries are found with a gear rolling hash, so an insertion only changes the chunks around it. Like in
+/// casync, a boundary is placed where the rolling hash modulo a discriminator derived from the average chunk size hits
+/// its maximum, within the minimum and maximum chunk size. The boundaries differ from the ones of casync's buzhash
+/// chunker, but the index is valid for any chunking, so casync and desync can extract the stream from a chunk store
+/// populated with the chunks, like `<id[..4]>/<id>.cacnk`.
+///
+/// Chunk IDs are the digests of the uncompressed chunks and must be 32 bytes long. They are SHA-256 digests unless the
+/// SHA-512/256 feature flag is set with [`CaibxWriter::with_feature_flags`]. Chunks can be passed to a [`ChunkSink`]
+/// with [`CaibxWriter::with_sink`], e.g. to store them.
+///
+/// [casync]: https://github.com/systemd/casync
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaibxWriter<W, H, S = ()>
+where
+    W: Write,
+    H: Algorithm,
+{
+    inner: W,
+    hash: H,
+    minimum: u64,
+    average: u64,
+    maximum: u64,
+    discriminator: u64,
+    flags: u64,
+    rolling: u64,
+    filled: u64,
+    length: u64,
+    chunks: Vec<(u64, H::Digest)>,
+    sink: S,
+}
+
+impl<W, H> CaibxWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Creates new [`CaibxWriter`] with the given average chunk size, and a quarter and four times of it as the minimum
+    /// and maximum chunk size, like casync.
+    pub fn new(inner: W, average: NonZeroU64) -> Self {
+        let average = average.get();
+        let minimum = NonZeroU64::new(average / 4).unwrap_or(NonZeroU64::MIN);
+        let maximum = average.saturating_mul(4);
+        Self::with_limits(inner, minimum, average, maximum)
+    }
+
+    /// Creates new [`CaibxWriter`] with the given minimum, average and maximum chunk size.
+    ///
+    /// # Panics
+    ///
+    /// Panics unless the minimum chunk size is at most the average and the average at most the maximum.
+    pub fn with_limits(inner: W, minimum: NonZeroU64, average: u64, maximum: u64) -> Self {
+        let minimum = minimum.get();
+        assert!(
+            minimum <= average && average <= maximum,
+            "chunk sizes must satisfy minimum <= average <= maximum"
+        );
+        Self {
+            inner,
+            hash: H::default(),
+            minimum,
+            average,
+            maximum,
+            discriminator: discriminator(average),
+            flags: 0,
+            rolling: 0,
+            filled: 0,
+            length: 0,
+            chunks: Vec::new(),
+            sink: (),
+        }
+    }
+
+    /// Passes every chunk to the sink. Should be called before writing.
+    #[must_use]
+    pub fn with_sink<S>(self, sink: S) -> CaibxWriter<W, H, S>
+    where
+        S: ChunkSink<H::Digest>,
+    {
+        let Self {
+            inner,
+            hash,
+            minimum,
+            average,
+            maximum,
+            discriminator,
+            flags,
+            rolling,
+            filled,
+            length,
+            chunks,
+            ..
+        } = self;
+        CaibxWriter {
+            inner,
+            hash,
+            minimum,
+            average,
+            maximum,
+            discriminator,
+            flags,
+            rolling,
+            filled,
+            length,
+            chunks,
+            sink,
+        }
+    }
+}
+
+impl<W, H, S> CaibxWriter<W, H, S>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+    S: ChunkSink<H::Digest>,
+{
+    /// Feature flag marking SHA-512/256 chunk IDs, `CA_FORMAT_SHA512_256`.
+    pub const SHA512_256: u64 = 0x2000_0000_0000_0000;
+
+    /// Sets feature flags written into the index header, like [`CaibxWriter::SHA512_256`].
+    #[must_use]
+    pub const fn with_feature_flags(mut self, flags: u64) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Returns end offsets and IDs of the completed chunks.
+    #[must_use]
+    pub fn chunks(&self) -> &[(u64, H::Digest)] {
+        &self.chunks
+    }
+
+    /// Ends the trailing chunk, flushes the underlying writer and writes the chunk index into `index`.
+    ///
+    /// Chunk IDs other than 32 bytes long result in an error of kind [`io::ErrorKind::InvalidInput`].
+    pub fn finish(mut self, mut index: impl Write) -> io::Result<W> {
+        if H::DIGEST_SIZE != 32 {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "chunk IDs must be 32 bytes long");
+            return Err(error);
+        }
+        if self.filled > 0 {
+            self.end_chunk();
+        }
+        self.inner.flush()?;
+        for value in [
+            INDEX_SIZE,
+            FORMAT_INDEX,
+            self.flags,
+            self.minimum,
+            self.average,
+            self.maximum,
+        ] {
+            index.write_all(&value.to_le_bytes())?;
+        }
+        index.write_all(&u64::MAX.to_le_bytes())?;
+        index.write_all(&FORMAT_TABLE.to_le_bytes())?;
+        for (offset, digest) in &self.chunks {
+            index.write_all(&offset.to_le_bytes())?;
+            index.write_all(digest.as_ref())?;
+        }
+        let table_size = 16 + 40 * self.chunks.len() as u64 + 40;
+        for value in [0, 0, INDEX_SIZE, table_size, TABLE_TAIL_MARKER] {
+            index.write_all(&value.to_le_bytes())?;
+        }
+        let Self { inner, .. } = self;
+        Ok(inner)
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let (length, boundary) = self.scan(data);
+            let (chunk, rest) = data.split_at(length);
+            self.hash.update(chunk);
+            self.length += length as u64;
+            if boundary {
+                self.end_chunk();
+            }
+            data = rest;
+        }
+    }
+
+    /// Feeds the data into the rolling hash, returning length of the data up to the next chunk boundary and whether
+    /// the boundary was found.
+    fn scan(&mut self, data: &[u8]) -> (usize, bool) {
+        for (index, &byte) in data.iter().enumerate() {
+            self.rolling = (self.rolling << 1).wrapping_add(GEAR[usize::from(byte)]);
+            self.filled += 1;
+            let boundary = self.rolling % self.discriminator == self.discriminator - 1;
+            if self.filled >= self.maximum || (self.filled >= self.minimum && boundary) {
+                return (index + 1, true);
+            }
+        }
+        (data.len(), false)
+    }
+
+    fn end_chunk(&mut self) {
+        let digest = mem::take(&mut self.hash).digest();
+        self.sink.on_chunk(self.length - self.filled, self.filled, &digest);
+        self.chunks.push((self.length, digest));
+        self.rolling = 0;
+        self.filled = 0;
+    }
+}
+
+impl<W, H, S> Write for CaibxWriter<W, H, S>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+    S: ChunkSink<H::Digest>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns discriminator giving chunks of the average size, with the formula of casync.
+fn discriminator(average: u64) -> u64 {
+    let average = average as f64;
+    let discriminator = average / (-1.428_888_52e-7 * average + 1.332_375_15);
+    (discriminator as u64).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::num::NonZeroU64;
+
+    use chksum_core::Hash;
+
+    use super::{discriminator, CaibxWriter, FORMAT_INDEX, FORMAT_TABLE, INDEX_SIZE, TABLE_TAIL_MARKER};
+    use crate::testing::{Sha1, Sha256};
+
+    fn data(length: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_4F6C_DD1D_u64;
+        (0..length)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn chunks(data: &[u8], piece: usize) -> Vec<(u64, Vec<u8>)> {
+        let mut writer = CaibxWriter::<_, Sha256>::new(io::sink(), NonZeroU64::new(256).unwrap());
+        for piece in data.chunks(piece) {
+            writer.write_all(piece).unwrap();
+        }
+        let mut chunks: Vec<_> = writer
+            .chunks()
+            .iter()
+            .map(|(end, digest)| (*end, digest.as_ref().to_vec()))
+            .collect();
+        let mut index = Vec::new();
+        writer.finish(&mut index).unwrap();
+        if chunks.last().map_or(0, |(end, _)| *end) < data.len() as u64 {
+            let start = chunks.last().map_or(0, |(end, _)| *end) as usize;
+            chunks.push((data.len() as u64, Sha256::hash(&data[start..]).as_ref().to_vec()));
+        }
+        chunks
+    }
+
+    fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+        u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn discriminator_like_casync() {
+        assert_eq!(discriminator(65536), 49535);
+        assert_eq!(discriminator(8192), 6153);
+        assert_eq!(discriminator(1), 1);
+    }
+
+    #[test]
+    fn index_layout() {
+        let data = data(10_000);
+        let mut writer = CaibxWriter::<_, Sha256>::new(Vec::new(), NonZeroU64::new(256).unwrap());
+        writer.write_all(&data).unwrap();
+        let mut index = Vec::new();
+        assert_eq!(writer.finish(&mut index).unwrap(), data);
+
+        let header: Vec<_> = (0..6).map(|field| u64_at(&index, field * 8)).collect();
+        assert_eq!(header, [INDEX_SIZE, FORMAT_INDEX, 0, 64, 256, 1024]);
+        assert_eq!(u64_at(&index, 48), u64::MAX);
+        assert_eq!(u64_at(&index, 56), FORMAT_TABLE);
+        let entries = (index.len() - 64 - 40) / 40;
+        let mut start = 0;
+        for entry in index[64..64 + entries * 40].chunks_exact(40) {
+            let end = u64_at(entry, 0);
+            assert!((64..=1024).contains(&(end - start)) || end == data.len() as u64);
+            assert_eq!(&entry[8..], Sha256::hash(&data[start as usize..end as usize]).as_ref());
+            start = end;
+        }
+        assert_eq!(start, data.len() as u64);
+        let tail: Vec<_> = (0..5)
+            .map(|field| u64_at(&index, 64 + entries * 40 + field * 8))
+            .collect();
+        assert_eq!(
+            tail,
+            [0, 0, INDEX_SIZE, 16 + 40 * entries as u64 + 40, TABLE_TAIL_MARKER]
+        );
+    }
+
+    #[test]
+    fn boundaries_depend_on_content_only() {
+        let data = data(20_000);
+        let whole = chunks(&data, data.len());
+        assert_eq!(chunks(&data, 1), whole);
+        assert_eq!(chunks(&data, 777), whole);
+
+        // an insertion only changes the chunks around it
+        let mut shifted = b"inserted".to_vec();
+        shifted.extend_from_slice(&data);
+        let shifted = chunks(&shifted, 4096);
+        let common = whole
+            .iter()
+            .filter(|(_, id)| shifted.iter().any(|(_, other)| other == id))
+            .count();
+        assert!(common + 3 >= whole.len(), "{common} of {} chunks kept", whole.len());
+    }
+
+    #[test]
+    fn rejects_short_chunk_ids() {
+        let writer = CaibxWriter::<_, Sha1>::new(io::sink(), NonZeroU64::new(256).unwrap());
+        let error = writer.finish(io::sink()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,242 @@
+use std::io::{self, Write};
+use std::num::NonZeroU64;
+use std::time::{Duration, Instant};
+
+use chksum_core::Hash;
+
+/// Hash digest of the stream up to the given offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint<D> {
+    /// Number of bytes covered by the digest.
+    pub offset: u64,
+    /// Hash digest of the first `offset` bytes.
+    pub digest: D,
+}
+
+/// Defines when intermediate digests are emitted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interval {
+    /// Emit after every given number of bytes, exactly on the boundary.
+    Bytes(NonZeroU64),
+    /// Emit on the first write after the given duration elapsed since the previous emission.
+    Duration(Duration),
+}
+
+/// Keeps track of when the next checkpoint is due.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Schedule {
+    interval: Interval,
+    emitted: Option<u64>,
+    instant: Instant,
+}
+
+impl Schedule {
+    pub(crate) fn new(interval: Interval) -> Self {
+        Self {
+            interval,
+            emitted: None,
+            instant: Instant::now(),
+        }
+    }
+
+    /// Limits the length of the write at `offset` so it ends on the next byte boundary.
+    pub(crate) fn limit(&self, offset: u64, length: usize) -> usize {
+        match self.interval {
+            Interval::Bytes(bytes) => {
+                let remaining = bytes.get() - offset % bytes.get();
+                length.min(usize::try_from(remaining).unwrap_or(usize::MAX))
+            },
+            Interval::Duration(_) => length,
+        }
+    }
+
+    /// Returns `true` if a checkpoint at `offset` is due.
+    pub(crate) fn is_due(&self, offset: u64) -> bool {
+        if self.emitted == Some(offset) {
+            return false;
+        }
+        match self.interval {
+            Interval::Bytes(bytes) => offset % bytes.get() == 0,
+            Interval::Duration(duration) => self.instant.elapsed() >= duration,
+        }
+    }
+
+    /// Returns `true` if no checkpoint was emitted at `offset` yet.
+    pub(crate) fn is_pending(&self, offset: u64) -> bool {
+        self.emitted != Some(offset)
+    }
+
+    /// Records a checkpoint emitted at `offset`.
+    pub(crate) fn mark(&mut self, offset: u64) {
+        self.emitted = Some(offset);
+        self.instant = Instant::now();
+    }
+}
+
+/// Wraps a writer and passes intermediate hash digests to a callback.
+///
+/// Checkpoints are only emitted while writing, so for time-based intervals on streams which may stall, call
+/// [`IntervalWriter::checkpoint_if_due`] from a timer as well.
+#[derive(Clone, Debug)]
+pub struct IntervalWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    F: FnMut(Checkpoint<H::Digest>),
+{
+    inner: W,
+    hash: H,
+    offset: u64,
+    schedule: Schedule,
+    callback: F,
+}
+
+impl<W, H, F> IntervalWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    F: FnMut(Checkpoint<H::Digest>),
+{
+    /// Creates new [`IntervalWriter`].
+    pub fn new(inner: W, interval: Interval, callback: F) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, interval, hash, callback)
+    }
+
+    /// Creates new [`IntervalWriter`] with provided hash.
+    pub fn with_hash(inner: W, interval: Interval, hash: H, callback: F) -> Self {
+        Self {
+            inner,
+            hash,
+            offset: 0,
+            schedule: Schedule::new(interval),
+            callback,
+        }
+    }
+
+    /// Returns number of bytes written so far.
+    #[must_use]
+    pub const fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Emits a checkpoint if one is due.
+    pub fn checkpoint_if_due(&mut self) {
+        if self.schedule.is_due(self.offset) {
+            self.emit();
+        }
+    }
+
+    /// Emits a checkpoint for the current offset, unless it was already emitted.
+    pub fn checkpoint(&mut self) {
+        if self.schedule.is_pending(self.offset) {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        let checkpoint = Checkpoint {
+            offset: self.offset,
+            digest: self.hash.digest(),
+        };
+        (self.callback)(checkpoint);
+        self.schedule.mark(self.offset);
+    }
+
+    /// Unwraps this [`IntervalWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+}
+
+impl<W, H, F> Write for IntervalWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    F: FnMut(Checkpoint<H::Digest>),
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = self.schedule.limit(self.offset, buf.len());
+        let n = self.inner.write(&buf[..length])?;
+        self.hash.update(&buf[..n]);
+        self.offset += n as u64;
+        if n > 0 {
+            self.checkpoint_if_due();
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::num::NonZeroU64;
+    use std::time::Duration;
+
+    use chksum_core::Hash;
+
+    use super::{Checkpoint, Interval, IntervalWriter, Schedule};
+    use crate::testing::Sha256;
+
+    const DATA: &[u8] = b"hello world";
+
+    fn every(bytes: u64) -> Interval {
+        Interval::Bytes(NonZeroU64::new(bytes).unwrap())
+    }
+
+    fn offsets(checkpoints: &[Checkpoint<<Sha256 as Hash>::Digest>]) -> Vec<u64> {
+        checkpoints.iter().map(|checkpoint| checkpoint.offset).collect()
+    }
+
+    #[test]
+    fn limits_writes_to_boundaries() {
+        let schedule = Schedule::new(every(4));
+        assert_eq!(schedule.limit(0, 11), 4);
+        assert_eq!(schedule.limit(3, 11), 1);
+        assert_eq!(schedule.limit(4, 2), 2);
+        let schedule = Schedule::new(Interval::Duration(Duration::from_secs(1)));
+        assert_eq!(schedule.limit(3, 11), 11);
+    }
+
+    #[test]
+    fn emits_on_boundaries() {
+        let mut checkpoints = Vec::new();
+        let mut writer =
+            IntervalWriter::<_, Sha256, _>::new(Vec::new(), every(4), |checkpoint| checkpoints.push(checkpoint));
+        writer.write_all(DATA).unwrap();
+        writer.checkpoint();
+        writer.checkpoint();
+        assert_eq!(writer.into_inner(), DATA);
+        assert_eq!(offsets(&checkpoints), [4, 8, 11]);
+        for checkpoint in checkpoints {
+            let length = usize::try_from(checkpoint.offset).unwrap();
+            assert_eq!(checkpoint.digest, Sha256::hash(&DATA[..length]));
+        }
+    }
+
+    #[test]
+    fn emits_after_duration() {
+        let mut checkpoints = Vec::new();
+        let interval = Interval::Duration(Duration::ZERO);
+        let mut writer =
+            IntervalWriter::<_, Sha256, _>::new(Vec::new(), interval, |checkpoint| checkpoints.push(checkpoint));
+        writer.write_all(b"hello").unwrap();
+        // nothing new to cover at the same offset
+        writer.checkpoint_if_due();
+        writer.write_all(b" world").unwrap();
+        drop(writer);
+        assert_eq!(offsets(&checkpoints), [5, 11]);
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+
+use chksum_core::Hash;
+
+use crate::{hkdf, Algorithm};
+
+/// HKDF info string used for deriving chunk keys.
+const INFO: &[u8] = b"chksum-writer convergent encryption";
+
+/// Result of [`ConvergentWriter::finish`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Convergent<D> {
+    /// Hash digest of the plaintext.
+    pub content: D,
+    /// Hash digest of the ciphertext.
+    pub ciphertext: D,
+    /// Hash digest of the concatenated chunk keys.
+    pub key_fingerprint: D,
+    /// Keys of the chunks, in order, needed for decryption.
+    pub keys: Vec<Vec<u8>>,
+}
+
+/// Wraps a writer and encrypts the data chunk-wise with keys derived from the chunk contents.
+///
+/// The plaintext is split into chunks of the given size, the last one may be shorter. The key of each chunk is derived
+/// from the digest of the chunk with HKDF, so identical chunks produce identical ciphertext and can be deduplicated.
+/// The cipher is called with the key and the plaintext of every chunk and returns its ciphertext, written to the
+/// underlying writer. Since the keys are derived from the content, the cipher must be deterministic, like AES-GCM-SIV
+/// with a fixed nonce.
+///
+/// Ciphertext of a completed chunk is written on the next write, flush or on finish, so a failed write of the
+/// underlying writer can be retried.
+pub struct ConvergentWriter<W, H, C>
+where
+    W: Write,
+    H: Hash,
+    C: FnMut(&[u8], &[u8]) -> io::Result<Vec<u8>>,
+{
+    inner: W,
+    cipher: C,
+    chunk_size: NonZeroUsize,
+    key_length: usize,
+    chunk: Vec<u8>,
+    pending: Vec<u8>,
+    content: H,
+    ciphertext: H,
+    fingerprint: H,
+    keys: Vec<Vec<u8>>,
+}
+
+impl<W, H, C> ConvergentWriter<W, H, C>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+    C: FnMut(&[u8], &[u8]) -> io::Result<Vec<u8>>,
+{
+    /// Creates new [`ConvergentWriter`] with the given chunk size and length of the chunk keys.
+    ///
+    /// Keys longer than 255 times the digest length cannot be derived and result in an error of kind
+    /// [`io::ErrorKind::InvalidInput`] once the first chunk is completed.
+    pub fn new(inner: W, chunk_size: NonZeroUsize, key_length: usize, cipher: C) -> Self {
+        Self {
+            inner,
+            cipher,
+            chunk_size,
+            key_length,
+            chunk: Vec::new(),
+            pending: Vec::new(),
+            content: H::default(),
+            ciphertext: H::default(),
+            fingerprint: H::default(),
+            keys: Vec::new(),
+        }
+    }
+
+    /// Returns size of the encrypted chunks.
+    #[must_use]
+    pub const fn chunk_size(&self) -> NonZeroUsize {
+        self.chunk_size
+    }
+
+    /// Returns length of the derived chunk keys.
+    #[must_use]
+    pub const fn key_length(&self) -> usize {
+        self.key_length
+    }
+
+    /// Encrypts the trailing chunk, flushes the underlying writer and returns it with the digests and the keys.
+    pub fn finish(mut self) -> io::Result<(W, Convergent<H::Digest>)> {
+        if !self.chunk.is_empty() {
+            self.seal()?;
+        }
+        self.flush()?;
+        let Self {
+            inner,
+            content,
+            ciphertext,
+            fingerprint,
+            keys,
+            ..
+        } = self;
+        let convergent = Convergent {
+            content: content.digest(),
+            ciphertext: ciphertext.digest(),
+            key_fingerprint: fingerprint.digest(),
+            keys,
+        };
+        Ok((inner, convergent))
+    }
+
+    /// Encrypts the current chunk and queues its ciphertext.
+    fn seal(&mut self) -> io::Result<()> {
+        let digest = H::hash(&self.chunk);
+        let key = hkdf::<H>(&[], digest.as_ref(), INFO, self.key_length)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "key too long for hash"))?;
+        let ciphertext = (self.cipher)(&key, &self.chunk)?;
+        self.ciphertext.update(&ciphertext);
+        self.fingerprint.update(&key);
+        self.keys.push(key);
+        self.chunk.clear();
+        self.pending = ciphertext;
+        Ok(())
+    }
+
+    /// Writes the queued ciphertext.
+    fn drain(&mut self) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            match self.inner.write(&self.pending) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write ciphertext")),
+                Ok(n) => {
+                    self.pending.drain(..n);
+                },
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {},
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W, H, C> Debug for ConvergentWriter<W, H, C>
+where
+    W: Write + Debug,
+    H: Hash + Debug,
+    C: FnMut(&[u8], &[u8]) -> io::Result<Vec<u8>>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConvergentWriter")
+            .field("inner", &self.inner)
+            .field("chunk_size", &self.chunk_size)
+            .field("key_length", &self.key_length)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W, H, C> Write for ConvergentWriter<W, H, C>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+    C: FnMut(&[u8], &[u8]) -> io::Result<Vec<u8>>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.drain()?;
+        let remaining = self.chunk_size.get() - self.chunk.len();
+        let buf = &buf[..buf.len().min(remaining)];
+        self.chunk.extend_from_slice(buf);
+        if self.chunk.len() == self.chunk_size.get() {
+            if let Err(error) = self.seal() {
+                // leave the bytes unaccepted, so the write can be retried
+                self.chunk.truncate(self.chunk.len() - buf.len());
+                return Err(error);
+            }
+        }
+        self.content.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::num::NonZeroUsize;
+
+    use chksum_core::Hash;
+
+    use super::{Convergent, ConvergentWriter};
+    use crate::testing::Sha256;
+
+    fn hex(bytes: impl AsRef<[u8]>) -> String {
+        bytes.as_ref().iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    fn xor(key: &[u8], chunk: &[u8]) -> io::Result<Vec<u8>> {
+        Ok(chunk
+            .iter()
+            .zip(key.iter().cycle())
+            .map(|(byte, key)| byte ^ key)
+            .collect())
+    }
+
+    fn encrypt(data: &[u8]) -> (Vec<u8>, Convergent<<Sha256 as Hash>::Digest>) {
+        let chunk_size = NonZeroUsize::new(4).unwrap();
+        let mut writer = ConvergentWriter::<_, Sha256, _>::new(Vec::new(), chunk_size, 16, xor);
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn derives_keys_from_content() {
+        let (ciphertext, convergent) = encrypt(b"abcdabcdxy");
+        let keys: Vec<_> = convergent.keys.iter().map(hex).collect();
+        assert_eq!(
+            keys,
+            [
+                "8c39356b340cb58787966f5e682a41bc",
+                "8c39356b340cb58787966f5e682a41bc",
+                "85955aff8694b9b796002a3810682c0c",
+            ]
+        );
+        assert_eq!(
+            convergent.key_fingerprint.to_string(),
+            "0fb2b88d357cbe45777cfcf80479ff01bd6f72f0cc31566d6ad2ebc9c8773029"
+        );
+        assert_eq!(convergent.content, Sha256::hash(b"abcdabcdxy"));
+        assert_eq!(convergent.ciphertext, Sha256::hash(&ciphertext));
+        // identical chunks encrypt identically
+        assert_eq!(ciphertext[..4], ciphertext[4..8]);
+        assert_eq!(xor(&convergent.keys[2], &ciphertext[8..]).unwrap(), b"xy");
+    }
+
+    #[test]
+    fn identical_plaintext_converges() {
+        let (ciphertext, convergent) = encrypt(b"hello world");
+        assert_eq!(encrypt(b"hello world"), (ciphertext.clone(), convergent.clone()));
+        let (other_ciphertext, other) = encrypt(b"hello there");
+        assert_ne!(other_ciphertext, ciphertext);
+        assert_ne!(other.key_fingerprint, convergent.key_fingerprint);
+        assert_eq!(other.keys[0], convergent.keys[0]);
+        assert_ne!(other.keys[1], convergent.keys[1]);
+    }
+
+    #[test]
+    fn rejects_long_keys() {
+        let chunk_size = NonZeroUsize::new(4).unwrap();
+        let mut writer = ConvergentWriter::<_, Sha256, _>::new(Vec::new(), chunk_size, 255 * 32 + 1, xor);
+        assert_eq!(writer.write(b"abc").unwrap(), 3);
+        let error = writer.write(b"defg").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+/// Body canonicalization algorithm of DKIM signatures, defined by RFC 6376.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BodyCanonicalization {
+    /// Only empty lines at the end of the body are ignored, an empty body is hashed as a single line break.
+    #[default]
+    Simple,
+    /// Whitespace at the end of lines is ignored and other whitespace sequences are reduced to a single space, as well
+    /// as empty lines at the end of the body are ignored.
+    Relaxed,
+}
+
+/// Wraps a writer and calculates the DKIM body hash of a message body on the fly, the `bh=` tag of signatures.
+///
+/// The body is written to the underlying writer unchanged, only the hashed data is canonicalized. Lines end with
+/// CRLF, bare LF is accepted as a line end as well. Nothing is buffered except the count of trailing empty lines,
+/// which are hashed once followed by a non-empty line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DkimBodyWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    canonicalization: BodyCanonicalization,
+    /// Number of empty lines not hashed yet.
+    empty: u64,
+    /// Some content of the current line was hashed.
+    line: bool,
+    /// Some content of the body was hashed.
+    body: bool,
+    /// Whitespace not hashed yet, with the relaxed canonicalization.
+    space: bool,
+    /// Carriage return not hashed yet, as it may end the line.
+    cr: bool,
+    canonical: Vec<u8>,
+}
+
+impl<W, H> DkimBodyWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`DkimBodyWriter`] with the canonicalization.
+    pub fn new(inner: W, canonicalization: BodyCanonicalization) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash, canonicalization)
+    }
+
+    /// Creates new [`DkimBodyWriter`] with provided hash and the canonicalization.
+    #[must_use]
+    pub const fn with_hash(inner: W, hash: H, canonicalization: BodyCanonicalization) -> Self {
+        Self {
+            inner,
+            hash,
+            canonicalization,
+            empty: 0,
+            line: false,
+            body: false,
+            space: false,
+            cr: false,
+            canonical: Vec::new(),
+        }
+    }
+
+    /// Returns the canonicalization.
+    #[must_use]
+    pub const fn canonicalization(&self) -> BodyCanonicalization {
+        self.canonicalization
+    }
+
+    /// Appends canonicalized form of the bytes to the buffer.
+    fn canonicalize(&mut self, data: &[u8]) {
+        for &byte in data {
+            if self.cr {
+                self.cr = false;
+                if byte != b'\n' {
+                    self.content(b'\r');
+                }
+            }
+            match byte {
+                b'\r' => self.cr = true,
+                b'\n' => self.end_line(),
+                b' ' | b'\t' if self.canonicalization == BodyCanonicalization::Relaxed => self.space = true,
+                byte => self.content(byte),
+            }
+        }
+    }
+
+    /// Appends content byte of the current line, preceded by the deferred empty lines and whitespace.
+    fn content(&mut self, byte: u8) {
+        if !self.line {
+            for _ in 0..self.empty {
+                self.canonical.extend_from_slice(b"\r\n");
+            }
+            self.empty = 0;
+            self.line = true;
+            self.body = true;
+        }
+        if self.space {
+            self.canonical.push(b' ');
+            self.space = false;
+        }
+        self.canonical.push(byte);
+    }
+
+    /// Ends the current line, deferring it if empty.
+    fn end_line(&mut self) {
+        if self.line {
+            self.canonical.extend_from_slice(b"\r\n");
+            self.line = false;
+        } else {
+            self.empty += 1;
+        }
+        // trailing whitespace is ignored
+        self.space = false;
+    }
+
+    /// Hashes the canonicalized data.
+    fn update(&mut self) {
+        self.hash.update(&self.canonical);
+        self.canonical.clear();
+    }
+
+    /// Flushes the writer and returns the underlying writer with the body hash.
+    ///
+    /// The last line is hashed with a line break, even if the body does not end with one.
+    pub fn finish(mut self) -> io::Result<(W, H::Digest)> {
+        self.inner.flush()?;
+        if self.cr {
+            self.cr = false;
+            self.content(b'\r');
+        }
+        if self.line {
+            self.end_line();
+        }
+        if !self.body && self.canonicalization == BodyCanonicalization::Simple {
+            self.canonical.extend_from_slice(b"\r\n");
+        }
+        self.update();
+        let digest = self.hash.digest();
+        let Self { inner, .. } = self;
+        Ok((inner, digest))
+    }
+}
+
+impl<W, H> Write for DkimBodyWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.canonicalize(&buf[..n]);
+        self.update();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use super::{BodyCanonicalization, DkimBodyWriter};
+    use crate::testing::Sha256;
+
+    fn body_hash(body: &[u8], canonicalization: BodyCanonicalization, piece: usize) -> String {
+        let mut writer = DkimBodyWriter::<_, Sha256>::new(Vec::new(), canonicalization);
+        for piece in body.chunks(piece) {
+            writer.write_all(piece).unwrap();
+        }
+        let (inner, digest) = writer.finish().unwrap();
+        assert_eq!(inner, body);
+        digest.to_string()
+    }
+
+    #[test]
+    fn empty_body() {
+        // bh=frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY= and bh=47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU=
+        let simple = "7eb70257593da06f682a3ddda54a9d260d4fc514f645237f5ca74b08f8da61a6";
+        let relaxed = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        for body in [&b""[..], b"\r\n", b"\r\n\r\n", b"\n"] {
+            assert_eq!(body_hash(body, BodyCanonicalization::Simple, 1), simple);
+            assert_eq!(body_hash(body, BodyCanonicalization::Relaxed, 1), relaxed);
+        }
+        assert_eq!(body_hash(b" \t\r\n", BodyCanonicalization::Relaxed, 1), relaxed);
+    }
+
+    #[test]
+    fn rfc6376_examples() {
+        // section 3.4.5, hashes of " C \r\nD \t E\r\n" and " C\r\nD E\r\n"
+        let body = b" C \r\nD \t E\r\n\r\n\r\n";
+        let simple = "34e7a2bdb4250c7f5398d289530ec3e77c197ec93c60c67f853b955704e08bcb";
+        let relaxed = "ba76a4e891ead302fe4351cfedd5b5b63071f452c0e837dfa19d2aacbc1b6e9a";
+        for piece in [1, 2, 5, body.len()] {
+            assert_eq!(body_hash(body, BodyCanonicalization::Simple, piece), simple);
+            assert_eq!(body_hash(body, BodyCanonicalization::Relaxed, piece), relaxed);
+        }
+        // bare line feeds and a missing final line break
+        assert_eq!(body_hash(b" C \nD \t E", BodyCanonicalization::Simple, 1), simple);
+        assert_eq!(body_hash(b" C \nD \t E", BodyCanonicalization::Relaxed, 1), relaxed);
+    }
+
+    #[test]
+    fn keeps_bare_carriage_returns() {
+        let mut writer = DkimBodyWriter::<_, Sha256>::new(io::sink(), BodyCanonicalization::Simple);
+        writer.write_all(b"a\rb\r").unwrap();
+        let (_, digest) = writer.finish().unwrap();
+        assert_eq!(digest, <Sha256 as chksum_core::Hash>::hash(b"a\rb\r\r\n"));
+    }
+}
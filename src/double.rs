@@ -0,0 +1,251 @@
+use std::fmt::{self, Debug, Formatter};
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll};
+use std::{io, mem};
+
+use chksum_core::Hash;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::error::IntoInnerError;
+
+/// Wraps a writer and overlaps hashing of buffered data with writes of previously buffered data.
+///
+/// Data is accepted into the front buffer and hashed right away, while the back buffer is being written to the
+/// underlying writer. Once the front buffer is full and the back buffer is drained, the buffers are swapped.
+///
+/// Memory usage is twice the configured capacity. A producer faster than the underlying writer is held back with
+/// [`Poll::Pending`] once both buffers are full, the peak of the buffered bytes is reported by
+/// [`DoubleBufferedWriter::high_watermark`].
+///
+/// # Cancel safety
+///
+/// The digest covers all bytes accepted into the buffers, including those not yet written to the underlying writer,
+/// which are reported by [`DoubleBufferedWriter::buffered_len`]. Dropping a write future midway never loses accepted
+/// bytes, they are written by the next write or flush.
+pub struct DoubleBufferedWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    capacity: usize,
+    front: Vec<u8>,
+    back: Vec<u8>,
+    written: usize,
+    peak: usize,
+}
+
+impl<W, H> DoubleBufferedWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    /// Default capacity of each buffer.
+    pub const DEFAULT_CAPACITY: usize = 64 * 1024;
+
+    /// Creates new [`DoubleBufferedWriter`] with default buffer capacity.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(inner, Self::DEFAULT_CAPACITY)
+    }
+
+    /// Creates new [`DoubleBufferedWriter`] with provided capacity of each buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(inner: W, capacity: usize) -> Self {
+        let hash = H::default();
+        Self::with_capacity_and_hash(inner, capacity, hash)
+    }
+
+    /// Creates new [`DoubleBufferedWriter`] with provided capacity of each buffer and hash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity_and_hash(inner: W, capacity: usize, hash: H) -> Self {
+        assert_ne!(capacity, 0, "capacity must be nonzero");
+        Self {
+            inner,
+            hash,
+            capacity,
+            front: Vec::with_capacity(capacity),
+            back: Vec::with_capacity(capacity),
+            written: 0,
+            peak: 0,
+        }
+    }
+
+    /// Returns capacity of each buffer.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns number of accepted bytes not yet written to the underlying writer.
+    #[must_use]
+    pub fn buffered_len(&self) -> usize {
+        self.front.len() + self.back.len() - self.written
+    }
+
+    /// Returns the highest number of accepted bytes not yet written to the underlying writer so far.
+    ///
+    /// It never exceeds twice the capacity, a value close to it means the underlying writer is the bottleneck.
+    #[must_use]
+    pub const fn high_watermark(&self) -> usize {
+        self.peak
+    }
+
+    /// Unwraps this [`DoubleBufferedWriter`], returning the underlying writer.
+    ///
+    /// Buffered data is lost, flush the writer first or use [`DoubleBufferedWriter::finish`].
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns calculated hash digest of all accepted bytes, including the buffered ones.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+}
+
+impl<W, H> DoubleBufferedWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash,
+{
+    /// Writes the back buffer until it is drained or the underlying writer is not ready.
+    fn poll_back(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.back.len() {
+            match pin!(&mut self.inner).poll_write(cx, &self.back[self.written..]) {
+                Poll::Ready(Ok(0)) => {
+                    let error = io::Error::new(io::ErrorKind::WriteZero, "failed to write the buffered data");
+                    return Poll::Ready(Err(error));
+                },
+                Poll::Ready(Ok(n)) => self.written += n,
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        self.back.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Moves the front buffer to the back, once the back buffer is drained.
+    fn swap(&mut self) {
+        mem::swap(&mut self.front, &mut self.back);
+    }
+}
+
+impl<W, H> DoubleBufferedWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+{
+    /// Flushes the buffered data and returns the underlying writer with calculated hash digest.
+    ///
+    /// On failure the writer is returned within the error, with unwritten data still buffered, so finishing can be
+    /// retried.
+    pub async fn finish(mut self) -> Result<(W, H::Digest), IntoInnerError<Self>> {
+        if let Err(error) = self.flush().await {
+            return Err(IntoInnerError::new(self, error));
+        }
+        let digest = self.hash.digest();
+        let Self { inner, .. } = self;
+        Ok((inner, digest))
+    }
+}
+
+impl<W, H> Debug for DoubleBufferedWriter<W, H>
+where
+    W: AsyncWrite + Debug,
+    H: Hash + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DoubleBufferedWriter")
+            .field("inner", &self.inner)
+            .field("hash", &self.hash)
+            .field("capacity", &self.capacity)
+            .field("buffered", &self.buffered_len())
+            .field("peak", &self.peak)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W, H> AsyncWrite for DoubleBufferedWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        // keep the write of the back buffer in flight before hashing anything new
+        let back = this.poll_back(cx);
+        if let Poll::Ready(Err(error)) = back {
+            return Poll::Ready(Err(error));
+        }
+        if this.front.len() == this.capacity {
+            if back.is_pending() {
+                return Poll::Pending;
+            }
+            this.swap();
+            if let Poll::Ready(Err(error)) = this.poll_back(cx) {
+                return Poll::Ready(Err(error));
+            }
+        }
+        let n = buf.len().min(this.capacity - this.front.len());
+        let buf = &buf[..n];
+        this.front.extend_from_slice(buf);
+        this.hash.update(buf);
+        this.peak = this.peak.max(this.buffered_len());
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        loop {
+            match this.poll_back(cx) {
+                Poll::Ready(Ok(())) => {},
+                poll => return poll,
+            }
+            if this.front.is_empty() {
+                break;
+            }
+            this.swap();
+        }
+        pin!(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        match self.as_mut().poll_flush(cx) {
+            Poll::Ready(Ok(())) => {},
+            poll => return poll,
+        }
+        let this = self.get_mut();
+        pin!(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use super::DoubleBufferedWriter;
+    use crate::testing::{block_on, Sha256};
+
+    #[test]
+    fn debug_omits_buffers() {
+        block_on(async {
+            let mut writer = DoubleBufferedWriter::<_, Sha256>::with_capacity(tokio::io::sink(), 16);
+            writer.write_all(b"secret").await.unwrap();
+            let debug = format!("{writer:?}");
+            assert!(debug.contains("buffered: 6"), "{debug}");
+            assert!(!debug.contains("front") && !debug.contains("back"), "{debug}");
+        });
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs::File;
+use std::io::{self, Write};
+
+use crate::state::{ResumableHash, StateStore};
+use crate::Writer;
+
+/// A trait for writers able to make written data durable, like [`File::sync_data`].
+pub trait SyncData {
+    /// Makes sure all written data reached the storage.
+    fn sync_data(&mut self) -> io::Result<()>;
+}
+
+impl SyncData for File {
+    fn sync_data(&mut self) -> io::Result<()> {
+        File::sync_data(self)
+    }
+}
+
+impl SyncData for &File {
+    fn sync_data(&mut self) -> io::Result<()> {
+        File::sync_data(self)
+    }
+}
+
+impl<T> SyncData for &mut T
+where
+    T: SyncData + ?Sized,
+{
+    fn sync_data(&mut self) -> io::Result<()> {
+        (**self).sync_data()
+    }
+}
+
+/// Wraps a writer and persists the hash state to a [`StateStore`] on every successful flush.
+///
+/// On flush the data is flushed and synced to the storage first, and only then the state is saved, so after a crash
+/// the stream can be resumed from the last flushed offset with a consistent digest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DurableWriter<W, H, S>
+where
+    W: Write + SyncData,
+    H: ResumableHash,
+    S: StateStore,
+{
+    writer: Writer<W, H>,
+    store: S,
+    id: String,
+}
+
+impl<W, H, S> DurableWriter<W, H, S>
+where
+    W: Write + SyncData,
+    H: ResumableHash,
+    S: StateStore,
+{
+    /// Creates new [`DurableWriter`] resuming from the state saved in the store under `id`, or from scratch if there
+    /// is none.
+    ///
+    /// The underlying writer should be positioned after the first [`DurableWriter::count`] bytes of the stream, e.g.
+    /// by truncating the file to that length and seeking to its end.
+    pub fn new(inner: W, store: S, id: impl Into<String>) -> io::Result<Self> {
+        let id = id.into();
+        let writer = Writer::resume_from(&store, &id, inner)?;
+        Ok(Self { writer, store, id })
+    }
+
+    /// Returns number of bytes written so far, including the resumed ones.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.writer.count()
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Flushes and syncs the writer, removes the saved state and returns the underlying writer with the hash digest.
+    pub fn finish(mut self) -> io::Result<(W, H::Digest)> {
+        self.writer.flush()?;
+        self.writer.inner.sync_data()?;
+        let Self { writer, mut store, id } = self;
+        store.remove(&id)?;
+        let digest = writer.digest();
+        Ok((writer.into_inner(), digest))
+    }
+}
+
+impl<W, H, S> Write for DurableWriter<W, H, S>
+where
+    W: Write + SyncData,
+    H: ResumableHash,
+    S: StateStore,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        // the data must be durable before the state claiming it is saved
+        self.writer.inner.sync_data()?;
+        self.writer.save_state(&mut self.store, &self.id)
+    }
+}
@@ -0,0 +1,252 @@
+use std::fmt::{self, Debug, Display, Formatter};
+use std::{error, io};
+
+/// Error returned by the verifying, finalizing and resuming operations of the writers.
+///
+/// Converts into [`io::Error`] of the kind returned by [`Error::kind`], wrapping itself unless it is [`Error::Io`], so
+/// it can be propagated from functions returning [`io::Result`] and recovered with [`io::Error::get_ref`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Input or output operation failed.
+    Io(io::Error),
+    /// Calculated hash digest differs from the expected one.
+    DigestMismatch {
+        /// Expected hash digest as hexadecimal string.
+        expected: String,
+        /// Calculated hash digest as hexadecimal string.
+        actual: String,
+        /// Offset of the first mismatching byte, if known.
+        offset: Option<u64>,
+    },
+    /// Number of written bytes differs from the expected one.
+    LengthMismatch {
+        /// Expected number of bytes.
+        expected: u64,
+        /// Number of bytes written.
+        actual: u64,
+    },
+    /// Offset reported by the other side of a transfer differs from the local one.
+    OffsetMismatch {
+        /// Local offset.
+        local: u64,
+        /// Offset reported by the other side.
+        remote: u64,
+    },
+    /// The underlying writer failed, with the context of the failure.
+    Write {
+        /// Number of bytes written and hashed before the failed write.
+        offset: u64,
+        /// The error of the underlying writer.
+        source: io::Error,
+    },
+    /// The writer was already finalized, like shut down.
+    Finalized,
+    /// Saved hash state could not be restored.
+    StateRestore {
+        /// Identifier of the state.
+        id: String,
+        /// The error which caused the failure.
+        source: io::Error,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => Display::fmt(error, f),
+            Self::DigestMismatch {
+                expected,
+                actual,
+                offset,
+            } => {
+                write!(f, "expected digest {expected}, found {actual}")?;
+                if let Some(offset) = offset {
+                    write!(f, ", first mismatch at offset {offset}")?;
+                }
+                Ok(())
+            },
+            Self::LengthMismatch { expected, actual } => write!(f, "expected {expected} bytes, found {actual}"),
+            Self::OffsetMismatch { local, remote } => {
+                write!(f, "local offset {local} differs from remote offset {remote}")
+            },
+            Self::Write { offset, .. } => write!(f, "write failed at offset {offset}"),
+            Self::Finalized => f.write_str("writer is finalized"),
+            Self::StateRestore { id, .. } => write!(f, "failed to restore state {id:?}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(error) | Self::Write { source: error, .. } | Self::StateRestore { source: error, .. } => {
+                Some(error)
+            },
+            Self::DigestMismatch { .. }
+            | Self::LengthMismatch { .. }
+            | Self::OffsetMismatch { .. }
+            | Self::Finalized => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl Error {
+    pub(crate) const fn write(offset: u64, source: io::Error) -> Self {
+        Self::Write { offset, source }
+    }
+
+    /// Returns kind of the corresponding [`io::Error`], the kind of the wrapped error where there is one.
+    #[must_use]
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::Io(error) | Self::Write { source: error, .. } | Self::StateRestore { source: error, .. } => {
+                error.kind()
+            },
+            Self::DigestMismatch { .. } | Self::LengthMismatch { .. } | Self::OffsetMismatch { .. } => {
+                io::ErrorKind::InvalidData
+            },
+            Self::Finalized => io::ErrorKind::BrokenPipe,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            error => Self::new(error.kind(), error),
+        }
+    }
+}
+
+/// Error returned when unwrapping a buffered writer fails, giving the writer back with its buffered data and hash
+/// state intact so the operation can be retried.
+///
+/// Modeled after [`std::io::IntoInnerError`].
+#[derive(Debug)]
+pub struct IntoInnerError<W> {
+    writer: W,
+    error: io::Error,
+}
+
+impl<W> IntoInnerError<W> {
+    pub(crate) const fn new(writer: W, error: io::Error) -> Self {
+        Self { writer, error }
+    }
+
+    /// Returns the error which caused the failure.
+    #[must_use]
+    pub const fn error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns the writer on which the failure occurred.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { writer, .. } = self;
+        writer
+    }
+
+    /// Returns the error which caused the failure, dropping the writer.
+    #[must_use]
+    pub fn into_error(self) -> io::Error {
+        let Self { error, .. } = self;
+        error
+    }
+
+    /// Returns the error which caused the failure and the writer on which it occurred.
+    #[must_use]
+    pub fn into_parts(self) -> (io::Error, W) {
+        let Self { writer, error } = self;
+        (error, writer)
+    }
+}
+
+impl<W> Display for IntoInnerError<W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl<W> error::Error for IntoInnerError<W>
+where
+    W: Debug,
+{
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<W> From<IntoInnerError<W>> for io::Error {
+    fn from(error: IntoInnerError<W>) -> Self {
+        error.into_error()
+    }
+}
+
+/// Error returned by [`parse_digest`](crate::parse_digest).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseDigestError {
+    /// The digest has a different number of bytes than the algorithm produces.
+    InvalidLength {
+        /// Number of bytes produced by the algorithm.
+        expected: usize,
+        /// Number of hexadecimal characters found.
+        actual: usize,
+    },
+    /// The digest contains a character which is not a hexadecimal digit.
+    InvalidCharacter {
+        /// Position of the character in the digest.
+        index: usize,
+        /// The invalid character.
+        character: char,
+    },
+    /// The digest is prefixed with a name of another algorithm.
+    AlgorithmMismatch {
+        /// Name of the expected algorithm.
+        expected: &'static str,
+        /// Name found in the prefix.
+        actual: String,
+    },
+}
+
+impl Display for ParseDigestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLength { expected, actual } => {
+                write!(f, "expected {} hexadecimal characters, found {actual}", expected * 2)
+            },
+            Self::InvalidCharacter { index, character } => {
+                write!(f, "invalid hexadecimal character {character:?} at position {index}")
+            },
+            Self::AlgorithmMismatch { expected, actual } => {
+                write!(f, "expected {expected} digest, found {actual}")
+            },
+        }
+    }
+}
+
+impl error::Error for ParseDigestError {}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+    use std::io;
+
+    use super::Error;
+
+    #[test]
+    fn write_error_reports_source_once() {
+        let source = io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed");
+        let error = Error::write(42, source);
+        assert_eq!(error.to_string(), "write failed at offset 42");
+        assert_eq!(error.source().unwrap().to_string(), "pipe closed");
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::BrokenPipe);
+    }
+}
@@ -0,0 +1,26 @@
+/// Escapes paths which would break the line format like coreutils, returning the line prefix and the path.
+pub(crate) fn escape(path: &str) -> (&'static str, String) {
+    if path.contains(['\\', '\n']) {
+        ("\\", path.replace('\\', "\\\\").replace('\n', "\\n"))
+    } else {
+        ("", path.to_owned())
+    }
+}
+
+/// Reverses [`escape`], returning [`None`] for unknown escape sequences.
+pub(crate) fn unescape(path: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(char) = chars.next() {
+        if char == '\\' {
+            match chars.next()? {
+                '\\' => unescaped.push('\\'),
+                'n' => unescaped.push('\n'),
+                _ => return None,
+            }
+        } else {
+            unescaped.push(char);
+        }
+    }
+    Some(unescaped)
+}
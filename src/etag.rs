@@ -0,0 +1,100 @@
+use std::fmt::{self, Display, Formatter};
+
+use chksum_core::{Digest, Hash};
+
+/// Entity tag of an HTTP resource, like the `ETag` header of an S3 object, compared with the digest of uploaded data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Etag {
+    value: String,
+    weak: bool,
+}
+
+/// Outcome of comparing a remote [`Etag`] with the one derived from the uploaded data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EtagMatch {
+    /// The remote resource holds the uploaded data.
+    Matched,
+    /// The remote resource holds different data.
+    Changed,
+    /// The tags are not derived the same way, like weak or server-generated ones, so they cannot be compared.
+    Unknown,
+}
+
+impl Etag {
+    /// Parses entity tag from a header value, like `"9e107d9d372bb6826bd81d3542a419d6"` or `W/"abc"`.
+    #[must_use]
+    pub fn parse(header: &str) -> Self {
+        let header = header.trim();
+        let (header, weak) = match header.strip_prefix("W/") {
+            Some(header) => (header, true),
+            None => (header, false),
+        };
+        let value = header
+            .strip_prefix('"')
+            .and_then(|header| header.strip_suffix('"'))
+            .unwrap_or(header);
+        let value = value.to_owned();
+        Self { value, weak }
+    }
+
+    /// Creates strong entity tag of the digest, like S3 does for objects uploaded in a single part with MD5.
+    #[must_use]
+    pub fn from_digest(digest: &impl Digest) -> Self {
+        let value = digest.to_string();
+        Self { value, weak: false }
+    }
+
+    /// Creates strong entity tag of a multipart upload, the hash of the concatenated part digests followed by the
+    /// number of parts, like S3 does with MD5.
+    #[must_use]
+    pub fn from_parts<H, D>(parts: &[D]) -> Self
+    where
+        H: Hash,
+        D: AsRef<[u8]>,
+    {
+        let mut hash = H::default();
+        for part in parts {
+            hash.update(part);
+        }
+        let value = format!("{}-{}", hash.digest(), parts.len());
+        Self { value, weak: false }
+    }
+
+    /// Returns the tag without quotes and weakness indicator.
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Returns `true` for weak tags, which identify semantically equivalent rather than identical data.
+    #[must_use]
+    pub const fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// Compares this remote tag, like the `ETag` returned by an upload, with the tag derived from the uploaded data.
+    ///
+    /// Weak tags and tags of different kinds, a multipart one and a single part one, give [`EtagMatch::Unknown`].
+    /// Otherwise the values are compared ignoring ASCII case.
+    #[must_use]
+    pub fn compare(&self, local: &Self) -> EtagMatch {
+        let multipart = |etag: &Self| etag.value.contains('-');
+        if self.weak || local.weak || multipart(self) != multipart(local) {
+            EtagMatch::Unknown
+        } else if self.value.eq_ignore_ascii_case(&local.value) {
+            EtagMatch::Matched
+        } else {
+            EtagMatch::Changed
+        }
+    }
+}
+
+impl Display for Etag {
+    /// Formats the tag as a header value, like for the `If-Match` header of a conditional request.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+        write!(f, "\"{}\"", self.value)
+    }
+}
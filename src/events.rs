@@ -0,0 +1,125 @@
+use std::io;
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll};
+
+use chksum_core::Hash;
+use tokio::io::AsyncWrite;
+use tokio::sync::broadcast::Sender;
+
+use crate::sink::ChunkSink;
+use crate::AsyncWriter;
+
+/// Event published to the subscribers of a [`broadcast`](tokio::sync::broadcast) channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DigestEvent<D> {
+    /// Chunk was finalized.
+    Chunk {
+        /// Offset of the chunk in the stream.
+        offset: u64,
+        /// Length of the chunk.
+        length: u64,
+        /// Digest of the chunk.
+        digest: D,
+    },
+    /// Stream was finalized.
+    Finished {
+        /// Length of the stream.
+        length: u64,
+        /// Digest of the stream.
+        digest: D,
+    },
+}
+
+/// Publishes chunks and the finalized stream, ignoring that there are no subscribers.
+impl<D> ChunkSink<D> for Sender<DigestEvent<D>>
+where
+    D: Clone,
+{
+    fn on_chunk(&mut self, offset: u64, length: u64, digest: &D) {
+        let digest = digest.clone();
+        let _ = self.send(DigestEvent::Chunk { offset, length, digest });
+    }
+
+    fn on_finish(&mut self, length: u64, digest: &D) {
+        let digest = digest.clone();
+        let _ = self.send(DigestEvent::Finished { length, digest });
+    }
+}
+
+/// Wraps an [`AsyncWriter`] and publishes [`DigestEvent::Finished`] with the digest on a successful shutdown.
+///
+/// Subscribers receive the digest without access to the writer, so integrity consumers are decoupled from the IO path.
+#[derive(Debug)]
+pub struct AsyncEventWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    writer: AsyncWriter<W, H>,
+    sender: Sender<DigestEvent<H::Digest>>,
+    finished: bool,
+}
+
+impl<W, H> AsyncEventWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    /// Creates new [`AsyncEventWriter`].
+    #[must_use]
+    pub const fn new(writer: AsyncWriter<W, H>, sender: Sender<DigestEvent<H::Digest>>) -> Self {
+        Self {
+            writer,
+            sender,
+            finished: false,
+        }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Unwraps this [`AsyncEventWriter`], returning the wrapped writer and the sender.
+    #[must_use]
+    pub fn into_parts(self) -> (AsyncWriter<W, H>, Sender<DigestEvent<H::Digest>>) {
+        let Self { writer, sender, .. } = self;
+        (writer, sender)
+    }
+}
+
+impl<W, H> AsyncWrite for AsyncEventWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self {
+            writer,
+            sender,
+            finished,
+        } = self.get_mut();
+        match pin!(&mut *writer).poll_shutdown(cx) {
+            Poll::Ready(Ok(())) => {
+                if !*finished {
+                    *finished = true;
+                    let (length, digest) = (writer.count(), writer.digest());
+                    let _ = sender.send(DigestEvent::Finished { length, digest });
+                }
+                Poll::Ready(Ok(()))
+            },
+            poll => poll,
+        }
+    }
+}
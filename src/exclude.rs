@@ -0,0 +1,261 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use chksum_core::Hash;
+
+use crate::builder::{self, Suffix};
+use crate::{Error, Writer};
+
+/// Byte ranges of a stream excluded from the digest by [`ExcludeWriter`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Exclusions {
+    ranges: Vec<Range<u64>>,
+    applied: Vec<Range<u64>>,
+    hashed: u64,
+}
+
+impl Exclusions {
+    /// Excludes the byte range of the stream from the digest.
+    ///
+    /// The range may lie in the current or in any future write, ranges overlapping already hashed bytes or with the
+    /// start past the end result in an error of kind [`io::ErrorKind::InvalidInput`]. Empty ranges are ignored.
+    pub fn exclude(&mut self, range: Range<u64>) -> io::Result<()> {
+        if range.start > range.end {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "range start is past its end",
+            ));
+        }
+        if range.start < self.hashed {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "range overlaps hashed bytes",
+            ));
+        }
+        if range.is_empty() {
+            return Ok(());
+        }
+        let index = self.ranges.partition_point(|other| other.start < range.start);
+        self.ranges.insert(index, range);
+        // merge overlapping and adjacent ranges
+        let mut merged: Vec<Range<u64>> = Vec::with_capacity(self.ranges.len());
+        for range in self.ranges.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        self.ranges = merged;
+        Ok(())
+    }
+
+    /// Returns pending ranges, not yet fully written.
+    #[must_use]
+    pub fn pending(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// Returns ranges of the written bytes which were excluded from the digest, with adjacent ranges merged.
+    #[must_use]
+    pub fn applied(&self) -> &[Range<u64>] {
+        &self.applied
+    }
+
+    /// Creates new [`Exclusions`] for a stream with the given number of bytes hashed already.
+    fn starting_at(hashed: u64) -> Self {
+        Self {
+            hashed,
+            ..Self::default()
+        }
+    }
+
+    /// Updates the hash with the data at the offset, skipping the excluded ranges.
+    fn update<H>(&mut self, hash: &mut H, offset: u64, data: &[u8])
+    where
+        H: Hash,
+    {
+        let end = offset + data.len() as u64;
+        let mut position = offset;
+        for range in self.ranges.iter().take_while(|range| range.start < end) {
+            if range.start > position {
+                hash.update(&data[(position - offset) as usize..(range.start - offset) as usize]);
+            }
+            let skipped = position.max(range.start)..range.end.min(end);
+            position = position.max(skipped.end);
+            match self.applied.last_mut() {
+                Some(last) if last.end >= skipped.start => last.end = last.end.max(skipped.end),
+                _ => self.applied.push(skipped),
+            }
+        }
+        if position < end {
+            hash.update(&data[(position - offset) as usize..]);
+        }
+        self.ranges.retain(|range| range.end > end);
+        self.hashed = end;
+    }
+}
+
+/// Wraps a writer and calculates the hash digest of the stream with byte ranges excluded by a format-aware hook.
+///
+/// The hook is called with the stream offset and the bytes of every write before they are written and hashed, so it
+/// can exclude ranges of the current write as well as of the future ones, like checksum and signature fields of PE
+/// headers for authenticode-like digests. All bytes are still written.
+///
+/// When the underlying writer accepts only a part of the bytes, the hook sees the rest again on the next write, so it
+/// should be prepared to see the same offsets repeatedly. Excluding the same range twice has no effect.
+///
+/// Created by [`Writer::exclude`], the byte count, the suffix configured with [`Builder`](crate::Builder), the expected
+/// length and the error context carry over from the writer, the hash is always updated after the write, like with
+/// [`UpdateOrder::AfterWrite`](crate::UpdateOrder::AfterWrite).
+#[derive(Clone, Debug)]
+pub struct ExcludeWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    F: FnMut(u64, &[u8], &mut Exclusions) -> io::Result<()>,
+{
+    inner: W,
+    hash: H,
+    hook: F,
+    exclusions: Exclusions,
+    offset: u64,
+    suffix: Vec<Suffix>,
+    expected: Option<u64>,
+    context: bool,
+}
+
+impl<W, H, F> ExcludeWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    F: FnMut(u64, &[u8], &mut Exclusions) -> io::Result<()>,
+{
+    /// Creates new [`ExcludeWriter`].
+    pub fn new(inner: W, hook: F) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash, hook)
+    }
+
+    /// Creates new [`ExcludeWriter`] with provided hash.
+    #[must_use]
+    pub fn with_hash(inner: W, hash: H, hook: F) -> Self {
+        Self {
+            inner,
+            hash,
+            hook,
+            exclusions: Exclusions::default(),
+            offset: 0,
+            suffix: Vec::new(),
+            expected: None,
+            context: false,
+        }
+    }
+
+    pub(crate) fn from_writer(writer: Writer<W, H>, hook: F) -> Self {
+        let Writer {
+            inner,
+            hash,
+            count,
+            suffix,
+            expected,
+            context,
+            ..
+        } = writer;
+        Self {
+            inner,
+            hash,
+            hook,
+            exclusions: Exclusions::starting_at(count),
+            offset: count,
+            suffix,
+            expected,
+            context,
+        }
+    }
+
+    /// Flushes the writer and returns the final hash digest, including the suffix, like [`Writer::finalize`].
+    ///
+    /// The expected length is compared with [`ExcludeWriter::offset`], which includes the excluded bytes.
+    pub fn finalize(mut self) -> io::Result<H::Digest> {
+        self.flush()?;
+        if let Some(expected) = self.expected.filter(|&expected| expected != self.offset) {
+            let actual = self.offset;
+            return Err(Error::LengthMismatch { expected, actual }.into());
+        }
+        builder::fold(&mut self.hash, &mut self.suffix, self.offset);
+        Ok(self.hash.digest())
+    }
+
+    /// Excludes the byte range of the stream from the digest, see [`Exclusions::exclude`] for details.
+    ///
+    /// Can be called before writing as well as between writes, for ranges at or past the current offset.
+    pub fn exclude_range(&mut self, range: Range<u64>) -> io::Result<()> {
+        self.exclusions.exclude(range)
+    }
+
+    /// Returns ranges of the written bytes which were excluded from the digest, with adjacent ranges merged.
+    #[must_use]
+    pub fn excluded(&self) -> &[Range<u64>] {
+        self.exclusions.applied()
+    }
+
+    /// Unwraps this [`ExcludeWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns number of bytes written so far, including the excluded ones.
+    #[must_use]
+    pub const fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+}
+
+/// Hook of an [`ExcludeWriter`] excluding nothing.
+pub type NoHook = fn(u64, &[u8], &mut Exclusions) -> io::Result<()>;
+
+impl<W, H> ExcludeWriter<W, H, NoHook>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`ExcludeWriter`] without a hook, excluding only the ranges passed to
+    /// [`ExcludeWriter::exclude_range`].
+    pub fn without_hook(inner: W) -> Self {
+        Self::new(inner, |_, _, _| Ok(()))
+    }
+}
+
+impl<W, H, F> Write for ExcludeWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    F: FnMut(u64, &[u8], &mut Exclusions) -> io::Result<()>,
+{
+    /// Writes the buffer and hashes the accepted bytes outside of the excluded ranges.
+    ///
+    /// An error returned by the hook is returned without writing anything.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.hook)(self.offset, buf, &mut self.exclusions)?;
+        let n = match self.inner.write(buf) {
+            Ok(n) => n,
+            Err(error) if self.context => return Err(Error::write(self.offset, error).into()),
+            Err(error) => return Err(error),
+        };
+        self.exclusions.update(&mut self.hash, self.offset, &buf[..n]);
+        self.offset += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
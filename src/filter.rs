@@ -0,0 +1,403 @@
+use std::io::{self, Write};
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{Context, Poll};
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+use crate::builder::{self, Suffix};
+#[cfg(feature = "async-runtime-tokio")]
+use crate::seal::Seal;
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{Error, Writer};
+
+/// Wraps a writer and calculates the hash digest of the writes accepted by a predicate.
+///
+/// The predicate is called with the stream offset of each write and the bytes accepted by the underlying writer.
+/// Rejected bytes are still written, they just do not contribute to the digest.
+///
+/// Created by [`Writer::filter`], the byte count, the suffix configured with [`Builder`](crate::Builder), the expected
+/// length and the error context carry over from the writer, the hash is always updated after the write, like with
+/// [`UpdateOrder::AfterWrite`](crate::UpdateOrder::AfterWrite).
+#[derive(Clone, Debug)]
+pub struct FilterWriter<W, H, P>
+where
+    W: Write,
+    H: Hash,
+    P: FnMut(u64, &[u8]) -> bool,
+{
+    inner: W,
+    hash: H,
+    predicate: P,
+    offset: u64,
+    suffix: Vec<Suffix>,
+    expected: Option<u64>,
+    context: bool,
+}
+
+impl<W, H, P> FilterWriter<W, H, P>
+where
+    W: Write,
+    H: Hash,
+    P: FnMut(u64, &[u8]) -> bool,
+{
+    /// Creates new [`FilterWriter`].
+    pub fn new(inner: W, predicate: P) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash, predicate)
+    }
+
+    /// Creates new [`FilterWriter`] with provided hash.
+    #[must_use]
+    pub const fn with_hash(inner: W, hash: H, predicate: P) -> Self {
+        Self {
+            inner,
+            hash,
+            predicate,
+            offset: 0,
+            suffix: Vec::new(),
+            expected: None,
+            context: false,
+        }
+    }
+
+    pub(crate) fn from_writer(writer: Writer<W, H>, predicate: P) -> Self {
+        let Writer {
+            inner,
+            hash,
+            count,
+            suffix,
+            expected,
+            context,
+            ..
+        } = writer;
+        Self {
+            inner,
+            hash,
+            predicate,
+            offset: count,
+            suffix,
+            expected,
+            context,
+        }
+    }
+
+    /// Flushes the writer and returns the final hash digest, including the suffix, like [`Writer::finalize`].
+    ///
+    /// The expected length is compared with [`FilterWriter::offset`], which includes the rejected bytes.
+    pub fn finalize(mut self) -> io::Result<H::Digest> {
+        self.flush()?;
+        if let Some(expected) = self.expected.filter(|&expected| expected != self.offset) {
+            let actual = self.offset;
+            return Err(Error::LengthMismatch { expected, actual }.into());
+        }
+        builder::fold(&mut self.hash, &mut self.suffix, self.offset);
+        Ok(self.hash.digest())
+    }
+
+    /// Unwraps this [`FilterWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns number of bytes written so far, including the ones excluded from the digest.
+    #[must_use]
+    pub const fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+}
+
+impl<W, H, P> Write for FilterWriter<W, H, P>
+where
+    W: Write,
+    H: Hash,
+    P: FnMut(u64, &[u8]) -> bool,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = match self.inner.write(buf) {
+            Ok(n) => n,
+            Err(error) if self.context => return Err(Error::write(self.offset, error).into()),
+            Err(error) => return Err(error),
+        };
+        let buf = &buf[..n];
+        if (self.predicate)(self.offset, buf) {
+            self.hash.update(buf);
+        }
+        self.offset += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Wraps a writer and calculates the hash digest of the writes accepted by a predicate.
+///
+/// The predicate is called with the stream offset of each write and the bytes accepted by the underlying writer.
+/// Rejected bytes are still written, they just do not contribute to the digest.
+///
+/// Created by [`AsyncWriter::filter`], the byte count, the suffix configured with [`Builder`](crate::Builder), the
+/// sealing state and the error context carry over from the writer, the hash is always updated after the write, like
+/// with [`UpdateOrder::AfterWrite`](crate::UpdateOrder::AfterWrite). Shutdown seals the writer like [`AsyncWriter`],
+/// folding in the suffix, and writes to a sealed writer result in [`Error::Finalized`].
+#[cfg(feature = "async-runtime-tokio")]
+#[derive(Clone, Debug)]
+pub struct AsyncFilterWriter<W, H, P>
+where
+    W: AsyncWrite,
+    H: Hash,
+    P: FnMut(u64, &[u8]) -> bool,
+{
+    inner: W,
+    hash: H,
+    predicate: P,
+    offset: u64,
+    suffix: Vec<Suffix>,
+    context: bool,
+    seal: Seal,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H, P> AsyncFilterWriter<W, H, P>
+where
+    W: AsyncWrite,
+    H: Hash,
+    P: FnMut(u64, &[u8]) -> bool,
+{
+    /// Creates new [`AsyncFilterWriter`].
+    pub fn new(inner: W, predicate: P) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash, predicate)
+    }
+
+    /// Creates new [`AsyncFilterWriter`] with provided hash.
+    #[must_use]
+    pub const fn with_hash(inner: W, hash: H, predicate: P) -> Self {
+        Self {
+            inner,
+            hash,
+            predicate,
+            offset: 0,
+            suffix: Vec::new(),
+            context: false,
+            seal: Seal::Open,
+        }
+    }
+
+    pub(crate) fn from_writer(writer: AsyncWriter<W, H>, predicate: P) -> Self {
+        let AsyncWriter {
+            inner,
+            hash,
+            count,
+            suffix,
+            context,
+            seal,
+            ..
+        } = writer;
+        Self {
+            inner,
+            hash,
+            predicate,
+            offset: count,
+            suffix,
+            context,
+            seal,
+        }
+    }
+
+    /// Returns `true` once the writer was shut down and the digest is final, see [`AsyncWriter::sealed`].
+    #[must_use]
+    pub const fn sealed(&self) -> bool {
+        matches!(self.seal, Seal::Sealed)
+    }
+
+    /// Unwraps this [`AsyncFilterWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns number of bytes written so far, including the ones excluded from the digest.
+    #[must_use]
+    pub const fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H, P> AsyncWrite for AsyncFilterWriter<W, H, P>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+    P: FnMut(u64, &[u8]) -> bool + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let Self {
+            inner,
+            hash,
+            predicate,
+            offset,
+            context,
+            seal,
+            ..
+        } = self.get_mut();
+        if *seal != Seal::Open {
+            let error = io::Error::from(Error::Finalized);
+            return Poll::Ready(Err(error));
+        }
+        match pin!(inner).poll_write(cx, buf) {
+            Poll::Ready(Ok(n)) => {
+                let buf = &buf[..n];
+                if predicate(*offset, buf) {
+                    hash.update(buf);
+                }
+                *offset += n as u64;
+                Poll::Ready(Ok(n))
+            },
+            Poll::Ready(Err(error)) if *context => Poll::Ready(Err(Error::write(*offset, error).into())),
+            poll => poll,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { inner, .. } = self.get_mut();
+        pin!(inner).poll_flush(cx)
+    }
+
+    /// Shuts down the underlying writer and seals the digest, see [`AsyncWriter`].
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self {
+            inner,
+            hash,
+            offset,
+            suffix,
+            seal,
+            ..
+        } = self.get_mut();
+        match seal {
+            Seal::Sealed => return Poll::Ready(Ok(())),
+            Seal::Open => *seal = Seal::Sealing,
+            Seal::Sealing => {},
+        }
+        match pin!(inner).poll_shutdown(cx) {
+            Poll::Ready(Ok(())) => {},
+            poll => return poll,
+        }
+        builder::fold(hash, suffix, *offset);
+        *seal = Seal::Sealed;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use chksum_core::Hash;
+    #[cfg(feature = "async-runtime-tokio")]
+    use tokio::io::AsyncWriteExt;
+
+    use super::FilterWriter;
+    #[cfg(feature = "async-runtime-tokio")]
+    use crate::testing::block_on;
+    use crate::testing::Sha256;
+    use crate::{Builder, Error};
+
+    const LINES: [&[u8]; 4] = [b"# header\n", b"data\n", b"# note\n", b"more\n"];
+
+    fn uncommented(_: u64, buf: &[u8]) -> bool {
+        !buf.starts_with(b"#")
+    }
+
+    #[test]
+    fn hashes_accepted_writes() {
+        let mut offsets = Vec::new();
+        let mut writer = FilterWriter::<_, Sha256, _>::new(Vec::new(), |offset, buf| {
+            offsets.push(offset);
+            uncommented(offset, buf)
+        });
+        for line in LINES {
+            writer.write_all(line).unwrap();
+        }
+        assert_eq!(writer.offset(), LINES.concat().len() as u64);
+        assert_eq!(writer.digest(), Sha256::hash(b"data\nmore\n"));
+        assert_eq!(writer.into_inner(), LINES.concat());
+        assert_eq!(offsets, [0, 9, 14, 21]);
+    }
+
+    #[test]
+    fn carries_over_writer_state() {
+        let length = 5 + LINES.concat().len() as u64;
+        let mut writer = Builder::<Sha256>::new()
+            .with_suffix("!")
+            .build(Vec::new())
+            .with_expected_len(length);
+        writer.write_all(b"head:").unwrap();
+        let mut writer = writer.filter(uncommented);
+        assert_eq!(writer.offset(), 5);
+        for line in LINES {
+            writer.write_all(line).unwrap();
+        }
+        assert_eq!(writer.finalize().unwrap(), Sha256::hash(b"head:data\nmore\n!"));
+    }
+
+    #[test]
+    fn checks_expected_length() {
+        let mut writer = Builder::<Sha256>::new()
+            .build(Vec::new())
+            .with_expected_len(100)
+            .filter(uncommented);
+        writer.write_all(LINES[1]).unwrap();
+        let error = writer.finalize().unwrap_err();
+        let error = error.get_ref().and_then(|error| error.downcast_ref::<Error>());
+        assert!(
+            matches!(
+                error,
+                Some(Error::LengthMismatch {
+                    expected: 100,
+                    actual: 5
+                })
+            ),
+            "{error:?}"
+        );
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_seals_on_shutdown() {
+        block_on(async {
+            let mut writer = Builder::<Sha256>::new()
+                .with_suffix("!")
+                .build_async(Vec::new())
+                .filter(uncommented);
+            for line in LINES {
+                writer.write_all(line).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+            assert!(writer.sealed());
+            assert_eq!(writer.digest(), Sha256::hash(b"data\nmore\n!"));
+            assert!(writer.write_all(b"late").await.is_err());
+            assert_eq!(writer.into_inner(), LINES.concat());
+        });
+    }
+}
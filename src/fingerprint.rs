@@ -0,0 +1,101 @@
+use std::fmt::Write as _;
+use std::io::Write;
+
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{base64, Algorithm, Writer};
+
+/// Returns OpenSSH-style fingerprint of the digest.
+fn fingerprint<H>(digest: &[u8]) -> String
+where
+    H: Algorithm,
+{
+    let name: String = H::NAME
+        .chars()
+        .filter(|&char| char != '-')
+        .map(|char| char.to_ascii_uppercase())
+        .collect();
+    let mut fingerprint = name + ":";
+    if fingerprint == "MD5:" {
+        // legacy fingerprints are colon-separated hexadecimal bytes
+        for (index, byte) in digest.iter().enumerate() {
+            let separator = if index == 0 { "" } else { ":" };
+            let _ = write!(fingerprint, "{separator}{byte:02x}");
+        }
+        return fingerprint;
+    }
+    fingerprint.push_str(&base64::encode_unpadded(digest));
+    fingerprint
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Returns fingerprint of the calculated hash digest as displayed by OpenSSH, like `SHA256:<base64>`.
+    ///
+    /// The prefix is [`Algorithm::NAME`] without dashes, and the digest is encoded with base64 without padding. MD5
+    /// digests use the legacy format of colon-separated hexadecimal bytes instead, like `MD5:0a:1b:…`.
+    #[must_use]
+    pub fn digest_fingerprint(&self) -> String {
+        fingerprint::<H>(self.digest().as_ref())
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Returns fingerprint of the calculated hash digest as displayed by OpenSSH, like `SHA256:<base64>`.
+    ///
+    /// See [`Writer::digest_fingerprint`] for details.
+    #[must_use]
+    pub fn digest_fingerprint(&self) -> String {
+        fingerprint::<H>(self.digest().as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use crate::testing::{Sha1, Sha256};
+    use crate::Writer;
+
+    /// Public key blob of `ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPuCypBIun/jSDixmld0G51lvOfgeRaY9TzMHBs96fuR`.
+    const KEY: &str =
+        "0000000b7373682d6564323535313900000020fb82ca9048ba7fe34838b19a57741b9d65bce7e0791698f53ccc1c1b3de9fb91";
+
+    fn unhex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&text[index..index + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn matches_ssh_keygen() {
+        // as printed by `ssh-keygen -l`
+        let mut writer = Writer::<_, Sha256>::new(Vec::new());
+        writer.write_all(&unhex(KEY)).unwrap();
+        assert_eq!(
+            writer.digest_fingerprint(),
+            "SHA256:zIUz/FDcZHKKTdEofJR/K5myHdXTpiRCMrrlok+vrxE"
+        );
+    }
+
+    #[test]
+    fn strips_dashes_from_name() {
+        let mut writer = Writer::<_, Sha1>::new(Vec::new());
+        writer.write_all(b"hello world").unwrap();
+        assert_eq!(writer.digest_fingerprint(), "SHA1:Kq5sNclPz7QV2+lfQIuc6R7oRu0");
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt::{self, Arguments};
+use std::io::{self, Write};
+
+/// Size of the stack buffer collecting formatted fragments.
+const CAPACITY: usize = 256;
+
+/// Collects formatted fragments in a stack buffer, passing them to the writer in as few writes as possible.
+struct Buffered<'a, W>
+where
+    W: Write + ?Sized,
+{
+    writer: &'a mut W,
+    buffer: [u8; CAPACITY],
+    length: usize,
+    error: Option<io::Error>,
+}
+
+impl<W> Buffered<'_, W>
+where
+    W: Write + ?Sized,
+{
+    fn drain(&mut self) -> io::Result<()> {
+        let length = self.length;
+        self.length = 0;
+        self.writer.write_all(&self.buffer[..length])
+    }
+}
+
+impl<W> fmt::Write for Buffered<'_, W>
+where
+    W: Write + ?Sized,
+{
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        let bytes = string.as_bytes();
+        let result = if self.length + bytes.len() <= CAPACITY {
+            self.buffer[self.length..self.length + bytes.len()].copy_from_slice(bytes);
+            self.length += bytes.len();
+            Ok(())
+        } else if bytes.len() < CAPACITY {
+            self.drain().map(|()| {
+                self.buffer[..bytes.len()].copy_from_slice(bytes);
+                self.length = bytes.len();
+            })
+        } else {
+            // fragments larger than the buffer are written directly instead of being split
+            self.drain().and_then(|()| self.writer.write_all(bytes))
+        };
+        result.map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Writes formatted arguments without allocating, merging fragments smaller than the stack buffer into single writes.
+pub(crate) fn write_fmt<W>(writer: &mut W, arguments: Arguments<'_>) -> io::Result<()>
+where
+    W: Write + ?Sized,
+{
+    if let Some(string) = arguments.as_str() {
+        return writer.write_all(string.as_bytes());
+    }
+    let mut buffered = Buffered {
+        writer,
+        buffer: [0; CAPACITY],
+        length: 0,
+        error: None,
+    };
+    match fmt::write(&mut buffered, arguments) {
+        Ok(()) => buffered.drain(),
+        Err(_) => {
+            Err(buffered
+                .error
+                .unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "formatter error")))
+        },
+    }
+}
@@ -0,0 +1,246 @@
+use std::io::{self, Write};
+use std::mem;
+
+use chksum_core::Hash;
+
+/// Magic number of the skippable frame holding the seek table of the zstd seekable format.
+const SKIPPABLE_MAGIC: u32 = 0x184D_2A5E;
+/// Magic number closing the seek table of the zstd seekable format.
+const SEEKABLE_MAGIC: u32 = 0x8F92_EAB1;
+/// Magic number of the skippable frame holding the frame digests.
+const DIGESTS_MAGIC: u32 = 0x184D_2A5D;
+
+/// Description of a single compressed frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame<D> {
+    /// Offset of the frame in the compressed stream.
+    pub offset: u64,
+    /// Number of compressed bytes in the frame.
+    pub compressed_len: u64,
+    /// Number of bytes the frame decompresses to.
+    pub decompressed_len: u64,
+    /// Hash digest of the compressed frame bytes.
+    pub digest: D,
+}
+
+/// Wraps the output of a frame-based compressor and calculates a digest of every compressed frame.
+///
+/// The compressor is expected to call [`FrameDigestWriter::end_frame`] after writing each frame. On
+/// [`FrameDigestWriter::finish`] the seek table of the [zstd seekable format] is appended, so individual frames can be
+/// located, and verified against their digests, without decompressing the whole stream.
+///
+/// The seek table has no room for strong digests, so they are written to a skippable frame with the magic number
+/// `0x184D2A5D` right before it. Its content is the digests of all frames, in order, followed by the length of a
+/// digest as a little-endian 32-bit integer. Decoders skip it like the seek table itself.
+///
+/// [zstd seekable format]: https://github.com/facebook/zstd/blob/dev/contrib/seekable_format/zstd_seekable_compression_format.md
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameDigestWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    offset: u64,
+    start: u64,
+    frames: Vec<Frame<H::Digest>>,
+}
+
+impl<W, H> FrameDigestWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`FrameDigestWriter`].
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hash: H::default(),
+            offset: 0,
+            start: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Ends the current frame, which decompresses to the given number of bytes, and returns its description.
+    pub fn end_frame(&mut self, decompressed_len: u64) -> &Frame<H::Digest> {
+        let hash = mem::take(&mut self.hash);
+        let frame = Frame {
+            offset: self.start,
+            compressed_len: self.offset - self.start,
+            decompressed_len,
+            digest: hash.digest(),
+        };
+        self.start = self.offset;
+        self.frames.push(frame);
+        self.frames.last().expect("frame was just pushed")
+    }
+
+    /// Returns descriptions of the ended frames.
+    #[must_use]
+    pub fn frames(&self) -> &[Frame<H::Digest>] {
+        &self.frames
+    }
+
+    /// Appends the frame digests and the seek table, flushes the underlying writer and returns it with descriptions of
+    /// the frames.
+    ///
+    /// Bytes written after the last ended frame result in an error of kind [`io::ErrorKind::InvalidInput`], as does a
+    /// frame too large for the seek table.
+    pub fn finish(mut self) -> io::Result<(W, Vec<Frame<H::Digest>>)>
+    where
+        H::Digest: AsRef<[u8]>,
+    {
+        if self.offset != self.start {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "last frame was not ended"));
+        }
+        write_digests(&mut self.inner, &self.frames)?;
+        write_seek_table(&mut self.inner, &self.frames)?;
+        self.inner.flush()?;
+        let Self { inner, frames, .. } = self;
+        Ok((inner, frames))
+    }
+}
+
+/// Writes the skippable frame holding digests of the given frames.
+fn write_digests<D>(mut writer: impl Write, frames: &[Frame<D>]) -> io::Result<()>
+where
+    D: AsRef<[u8]>,
+{
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "too many frames for digest table");
+    let length = frames.first().map_or(0, |frame| frame.digest.as_ref().len());
+    let length = u32::try_from(length).map_err(|_| too_large())?;
+    // digests and their length
+    let size = u32::try_from(frames.len())
+        .ok()
+        .and_then(|count| count.checked_mul(length))
+        .and_then(|size| size.checked_add(4))
+        .ok_or_else(too_large)?;
+    let mut table = Vec::with_capacity(size as usize + 8);
+    table.extend_from_slice(&DIGESTS_MAGIC.to_le_bytes());
+    table.extend_from_slice(&size.to_le_bytes());
+    for frame in frames {
+        table.extend_from_slice(frame.digest.as_ref());
+    }
+    table.extend_from_slice(&length.to_le_bytes());
+    writer.write_all(&table)
+}
+
+/// Writes the seek table of the zstd seekable format, without checksums, for the given frames.
+fn write_seek_table<D>(mut writer: impl Write, frames: &[Frame<D>]) -> io::Result<()> {
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, "frame too large for seek table");
+    let count = u32::try_from(frames.len()).map_err(|_| too_large())?;
+    // entries of 8 bytes and a footer of 9 bytes
+    let size = count
+        .checked_mul(8)
+        .and_then(|size| size.checked_add(9))
+        .ok_or_else(too_large)?;
+    let mut table = Vec::with_capacity(size as usize + 8);
+    table.extend_from_slice(&SKIPPABLE_MAGIC.to_le_bytes());
+    table.extend_from_slice(&size.to_le_bytes());
+    for frame in frames {
+        let compressed = u32::try_from(frame.compressed_len).map_err(|_| too_large())?;
+        let decompressed = u32::try_from(frame.decompressed_len).map_err(|_| too_large())?;
+        table.extend_from_slice(&compressed.to_le_bytes());
+        table.extend_from_slice(&decompressed.to_le_bytes());
+    }
+    table.extend_from_slice(&count.to_le_bytes());
+    // descriptor without the checksum flag
+    table.push(0);
+    table.extend_from_slice(&SEEKABLE_MAGIC.to_le_bytes());
+    writer.write_all(&table)
+}
+
+impl<W, H> Write for FrameDigestWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hash.update(&buf[..n]);
+        self.offset += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use chksum_core::{Digest, Hash};
+
+    use super::{FrameDigestWriter, DIGESTS_MAGIC, SEEKABLE_MAGIC, SKIPPABLE_MAGIC};
+    use crate::testing::Sha256;
+
+    const FRAMES: [(&[u8], u64); 3] = [(b"first frame", 100), (b"second", 64), (b"third frame!", 1000)];
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Parses the seek table and the digests preceding it, returning sizes and digest of every frame.
+    fn parse_tables(bytes: &[u8]) -> Vec<(u32, u32, Vec<u8>)> {
+        let end = bytes.len();
+        assert_eq!(u32_at(bytes, end - 4), SEEKABLE_MAGIC);
+        assert_eq!(bytes[end - 5], 0);
+        let count = u32_at(bytes, end - 9) as usize;
+        let table = end - 9 - 8 * count - 8;
+        assert_eq!(u32_at(bytes, table), SKIPPABLE_MAGIC);
+        assert_eq!(u32_at(bytes, table + 4) as usize, 8 * count + 9);
+        let length = u32_at(bytes, table - 4) as usize;
+        let digests = table - 4 - length * count - 8;
+        assert_eq!(u32_at(bytes, digests), DIGESTS_MAGIC);
+        assert_eq!(u32_at(bytes, digests + 4) as usize, length * count + 4);
+        (0..count)
+            .map(|index| {
+                let entry = table + 8 + 8 * index;
+                let digest = digests + 8 + length * index;
+                let digest = bytes[digest..digest + length].to_vec();
+                (u32_at(bytes, entry), u32_at(bytes, entry + 4), digest)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn writes_digests_and_seek_table() {
+        let mut writer = FrameDigestWriter::<_, Sha256>::new(Vec::new());
+        for (data, decompressed_len) in FRAMES {
+            writer.write_all(data).unwrap();
+            assert_eq!(writer.end_frame(decompressed_len).digest, Sha256::hash(data));
+        }
+        let (bytes, frames) = writer.finish().unwrap();
+        let data = FRAMES.map(|(frame, _)| frame).concat();
+        assert_eq!(bytes[..data.len()], data);
+        let entries = parse_tables(&bytes);
+        assert_eq!(entries.len(), FRAMES.len());
+        for ((entry, frame), (data, decompressed_len)) in entries.iter().zip(&frames).zip(FRAMES) {
+            assert_eq!(entry.0 as usize, data.len());
+            assert_eq!(u64::from(entry.1), decompressed_len);
+            assert_eq!(entry.2, Sha256::hash(data).as_bytes());
+            assert_eq!(frame.digest, Sha256::hash(data));
+            assert_eq!(frame.compressed_len, data.len() as u64);
+        }
+        assert_eq!(frames.iter().map(|frame| frame.offset).collect::<Vec<_>>(), [0, 11, 17]);
+    }
+
+    #[test]
+    fn writes_empty_tables() {
+        let (bytes, frames) = FrameDigestWriter::<_, Sha256>::new(Vec::new()).finish().unwrap();
+        assert!(frames.is_empty());
+        assert!(parse_tables(&bytes).is_empty());
+    }
+
+    #[test]
+    fn rejects_unended_frame() {
+        let mut writer = FrameDigestWriter::<_, Sha256>::new(Vec::new());
+        writer.write_all(b"data").unwrap();
+        let error = writer.finish().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+/// Type of a Git object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ObjectKind {
+    /// File contents.
+    Blob,
+    /// Directory listing.
+    Tree,
+    /// Commit.
+    Commit,
+    /// Annotated tag.
+    Tag,
+}
+
+impl ObjectKind {
+    /// Returns name of the type as used in object headers.
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Blob => "blob",
+            Self::Tree => "tree",
+            Self::Commit => "commit",
+            Self::Tag => "tag",
+        }
+    }
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn header(kind: ObjectKind, length: u64) -> String {
+    format!("{kind} {length}\0")
+}
+
+/// Wraps a writer and calculates Git object ID of the written data, the digest of `<type> <length>\0` followed by the
+/// data.
+///
+/// Repositories using SHA-1 and SHA-256 differ only in the hash. Since the header contains the length, it either has to
+/// be declared up front with [`GitObjectWriter::new`], or the data is kept in memory until the digest is requested with
+/// [`GitObjectWriter::buffered`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitObjectWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    kind: ObjectKind,
+    length: Option<u64>,
+    count: u64,
+    buffer: Vec<u8>,
+}
+
+impl<W, H> GitObjectWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`GitObjectWriter`] for an object of the given length.
+    ///
+    /// Writes beyond the declared length result in an error of kind [`io::ErrorKind::InvalidInput`].
+    pub fn new(inner: W, kind: ObjectKind, length: u64) -> Self {
+        let mut hash = H::default();
+        hash.update(header(kind, length));
+        Self {
+            inner,
+            hash,
+            kind,
+            length: Some(length),
+            count: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Creates new [`GitObjectWriter`] keeping the written data in memory, for objects of unknown length.
+    pub fn buffered(inner: W, kind: ObjectKind) -> Self {
+        Self::with_capacity(inner, kind, 0)
+    }
+
+    /// Creates new buffered [`GitObjectWriter`] with at least the specified capacity preallocated.
+    pub fn with_capacity(inner: W, kind: ObjectKind, capacity: usize) -> Self {
+        Self {
+            inner,
+            hash: H::default(),
+            kind,
+            length: None,
+            count: 0,
+            buffer: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns capacity of the internal buffer, zero for objects of declared length.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Returns number of bytes written so far.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns object ID of the data written so far.
+    ///
+    /// For objects of declared length the result is meaningful only once the whole object was written.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        if self.length.is_some() {
+            return self.hash.digest();
+        }
+        let mut hash = H::default();
+        hash.update(header(self.kind, self.count));
+        hash.update(&self.buffer);
+        hash.digest()
+    }
+
+    /// Flushes the underlying writer and returns it with the object ID.
+    ///
+    /// An object shorter than declared results in an error of kind [`io::ErrorKind::InvalidInput`].
+    pub fn finish(mut self) -> io::Result<(W, H::Digest)> {
+        if self.length.is_some_and(|length| length != self.count) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "object shorter than declared",
+            ));
+        }
+        self.inner.flush()?;
+        let digest = self.digest();
+        let Self { inner, .. } = self;
+        Ok((inner, digest))
+    }
+}
+
+impl<W, H> Write for GitObjectWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(length) = self.length {
+            if buf.len() as u64 > length - self.count {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "object longer than declared",
+                ));
+            }
+        }
+        let n = self.inner.write(buf)?;
+        let buf = &buf[..n];
+        if self.length.is_some() {
+            self.hash.update(buf);
+        } else {
+            self.buffer.extend_from_slice(buf);
+        }
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use super::{GitObjectWriter, ObjectKind};
+    use crate::testing::{Sha1, Sha256};
+
+    #[test]
+    fn blob_ids() {
+        // same as `git hash-object` and `git hash-object --object-format=sha256`
+        let (inner, digest) = GitObjectWriter::<_, Sha1>::new(Vec::new(), ObjectKind::Blob, 0)
+            .finish()
+            .unwrap();
+        assert!(inner.is_empty());
+        assert_eq!(digest.to_string(), "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391");
+
+        let mut writer = GitObjectWriter::<_, Sha1>::new(Vec::new(), ObjectKind::Blob, 6);
+        writer.write_all(b"hello\n").unwrap();
+        let (inner, digest) = writer.finish().unwrap();
+        assert_eq!(inner, b"hello\n");
+        assert_eq!(digest.to_string(), "ce013625030ba8dba906f756967f9e9ca394464a");
+
+        let mut writer = GitObjectWriter::<_, Sha256>::buffered(io::sink(), ObjectKind::Blob);
+        assert_eq!(
+            writer.digest().to_string(),
+            "473a0f4c3be8a93681a267e3b1e9a7dcda1185436fe141f7749120a303721813"
+        );
+        writer.write_all(b"hel").unwrap();
+        writer.write_all(b"lo\n").unwrap();
+        assert_eq!(
+            writer.finish().unwrap().1.to_string(),
+            "2cf8d83d9ee29543b34a87727421fdecb7e3f3a183d337639025de576db9ebb4"
+        );
+    }
+
+    #[test]
+    fn declared_length() {
+        let mut writer = GitObjectWriter::<_, Sha1>::new(io::sink(), ObjectKind::Blob, 4);
+        assert_eq!(
+            writer.write_all(b"hello").unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        writer.write_all(b"hel").unwrap();
+        assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
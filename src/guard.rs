@@ -0,0 +1,350 @@
+use std::cell::Cell;
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Write};
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{Context, Poll};
+use std::thread;
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{Builder, Writer};
+
+type Hook<H> = Box<dyn FnOnce(&<H as Hash>::Digest) + Send>;
+
+/// Fires a debug assertion when dropped while still armed.
+struct Armed(Cell<bool>);
+
+impl Armed {
+    const fn new() -> Self {
+        Self(Cell::new(true))
+    }
+
+    fn disarm(&self) {
+        self.0.set(false);
+    }
+}
+
+impl Drop for Armed {
+    fn drop(&mut self) {
+        // avoid turning an unrelated panic into an abort
+        if !thread::panicking() {
+            debug_assert!(!self.0.get(), "writer dropped without its digest ever being read");
+        }
+    }
+}
+
+/// Builder of [`DigestGuard`] and [`AsyncDigestGuard`] with a finalize hook.
+///
+/// Created by [`Builder::on_finalize`].
+pub struct GuardBuilder<H>
+where
+    H: Hash,
+{
+    builder: Builder<H>,
+    hook: Hook<H>,
+}
+
+impl<H> GuardBuilder<H>
+where
+    H: Hash,
+{
+    pub(crate) fn new(builder: Builder<H>, hook: Hook<H>) -> Self {
+        Self { builder, hook }
+    }
+
+    /// Builds [`DigestGuard`] of a [`Writer`] wrapping the given writer.
+    #[must_use]
+    pub fn build<W>(self, inner: W) -> DigestGuard<W, H>
+    where
+        W: Write,
+    {
+        let Self { builder, hook } = self;
+        let mut guard = DigestGuard::new(builder.build(inner));
+        guard.hook = Some(hook);
+        guard
+    }
+
+    /// Builds [`AsyncDigestGuard`] of an [`AsyncWriter`] wrapping the given writer.
+    #[cfg(feature = "async-runtime-tokio")]
+    #[must_use]
+    pub fn build_async<W>(self, inner: W) -> AsyncDigestGuard<W, H>
+    where
+        W: AsyncWrite,
+    {
+        let Self { builder, hook } = self;
+        let mut guard = AsyncDigestGuard::new(builder.build_async(inner));
+        guard.hook = Some(hook);
+        guard
+    }
+}
+
+impl<H> Debug for GuardBuilder<H>
+where
+    H: Hash + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardBuilder")
+            .field("builder", &self.builder)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Wraps a [`Writer`] and makes sure its digest is not silently lost.
+///
+/// In debug builds, dropping the guard without reading the digest (via [`DigestGuard::digest`] or
+/// [`DigestGuard::finalize`]) triggers an assertion.
+pub struct DigestGuard<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    writer: Writer<W, H>,
+    hook: Option<Hook<H>>,
+    armed: Armed,
+}
+
+impl<W, H> DigestGuard<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`DigestGuard`].
+    #[must_use]
+    pub const fn new(writer: Writer<W, H>) -> Self {
+        Self {
+            writer,
+            hook: None,
+            armed: Armed::new(),
+        }
+    }
+
+    /// Sets hook invoked with the digest exactly once, when the guard is finalized.
+    #[must_use]
+    pub fn on_finalize(mut self, hook: impl FnOnce(&H::Digest) + Send + 'static) -> Self {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.armed.disarm();
+        self.writer.digest()
+    }
+
+    /// Finalizes the writer, invokes the finalize hook and returns the final hash digest.
+    pub fn finalize(self) -> io::Result<H::Digest> {
+        let Self { writer, hook, armed } = self;
+        armed.disarm();
+        let digest = writer.finalize()?;
+        if let Some(hook) = hook {
+            hook(&digest);
+        }
+        Ok(digest)
+    }
+
+    /// Unwraps this [`DigestGuard`], returning the guarded writer without invoking the finalize hook.
+    #[must_use]
+    pub fn into_inner(self) -> Writer<W, H> {
+        let Self { writer, armed, .. } = self;
+        armed.disarm();
+        writer
+    }
+}
+
+impl<W, H> Debug for DigestGuard<W, H>
+where
+    W: Write + Debug,
+    H: Hash + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DigestGuard")
+            .field("writer", &self.writer)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W, H> Write for DigestGuard<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Wraps an [`AsyncWriter`] and makes sure its digest is not silently lost.
+///
+/// In debug builds, dropping the guard without reading the digest (via [`AsyncDigestGuard::digest`] or a successful
+/// shutdown) triggers an assertion.
+#[cfg(feature = "async-runtime-tokio")]
+pub struct AsyncDigestGuard<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    writer: AsyncWriter<W, H>,
+    hook: Option<Hook<H>>,
+    armed: Armed,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncDigestGuard<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    /// Creates new [`AsyncDigestGuard`].
+    #[must_use]
+    pub const fn new(writer: AsyncWriter<W, H>) -> Self {
+        Self {
+            writer,
+            hook: None,
+            armed: Armed::new(),
+        }
+    }
+
+    /// Sets hook invoked with the digest exactly once, when the guard is shut down.
+    #[must_use]
+    pub fn on_finalize(mut self, hook: impl FnOnce(&H::Digest) + Send + 'static) -> Self {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.armed.disarm();
+        self.writer.digest()
+    }
+
+    /// Unwraps this [`AsyncDigestGuard`], returning the guarded writer without invoking the finalize hook.
+    #[must_use]
+    pub fn into_inner(self) -> AsyncWriter<W, H> {
+        let Self { writer, armed, .. } = self;
+        armed.disarm();
+        writer
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> Debug for AsyncDigestGuard<W, H>
+where
+    W: AsyncWrite + Debug,
+    H: Hash + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncDigestGuard")
+            .field("writer", &self.writer)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWrite for AsyncDigestGuard<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, hook, armed } = self.get_mut();
+        match pin!(&mut *writer).poll_shutdown(cx) {
+            Poll::Ready(Ok(())) => {
+                armed.disarm();
+                if let Some(hook) = hook.take() {
+                    hook(&writer.digest());
+                }
+                Poll::Ready(Ok(()))
+            },
+            poll => poll,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::sync::mpsc;
+
+    use chksum_core::Hash;
+
+    use crate::testing::Sha256;
+    use crate::{Builder, Writer};
+
+    #[test]
+    fn hook_fires_once_at_finalize() {
+        let (sender, receiver) = mpsc::channel();
+        let mut guard = Builder::<Sha256>::new()
+            .with_suffix("!")
+            .on_finalize(move |digest| sender.send(digest.clone()).unwrap())
+            .build(Vec::new());
+        guard.write_all(b"data").unwrap();
+        assert!(receiver.try_recv().is_err());
+        assert_eq!(guard.finalize().unwrap(), Sha256::hash(b"data!"));
+        assert_eq!(receiver.iter().collect::<Vec<_>>(), [Sha256::hash(b"data!")]);
+    }
+
+    #[test]
+    fn into_inner_skips_hook() {
+        let (sender, receiver) = mpsc::channel::<()>();
+        let guard = Builder::<Sha256>::new()
+            .on_finalize(move |_| sender.send(()).unwrap())
+            .build(Vec::new());
+        let writer = guard.into_inner();
+        assert_eq!(writer.digest(), Sha256::hash(b""));
+        assert!(receiver.recv().is_err());
+    }
+
+    #[test]
+    fn digest_disarms() {
+        let guard = Writer::<_, Sha256>::new(Vec::new()).guard();
+        assert_eq!(guard.digest(), Sha256::hash(b""));
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "writer dropped without its digest ever being read")]
+    fn drop_without_digest_asserts() {
+        let mut guard = Writer::<_, Sha256>::new(Vec::new()).guard();
+        guard.write_all(b"data").unwrap();
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn hook_fires_once_at_shutdown() {
+        use tokio::io::AsyncWriteExt;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut guard = Builder::<Sha256>::new()
+            .on_finalize(move |digest| sender.send(digest.clone()).unwrap())
+            .build_async(Vec::new());
+        crate::testing::block_on(async {
+            guard.write_all(b"data").await.unwrap();
+            guard.shutdown().await.unwrap();
+            guard.shutdown().await.unwrap();
+        });
+        drop(guard);
+        assert_eq!(receiver.iter().collect::<Vec<_>>(), [Sha256::hash(b"data")]);
+    }
+}
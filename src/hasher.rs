@@ -0,0 +1,122 @@
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+
+use chksum_core::Hash;
+
+/// Adapts a hash to [`Hasher`], truncating the digest to its first 8 bytes, read as a big-endian integer.
+///
+/// Bytes can also be written through [`Write`], so large streamed values can be hashed with [`io::copy`] into 64-bit
+/// keys of [`HashMap`](std::collections::HashMap) or [`HashSet`](std::collections::HashSet) with
+/// [`BuildDigestHasher`]. Digests shorter than 8 bytes are padded with zeros.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DigestHasher<H>
+where
+    H: Hash,
+{
+    hash: H,
+}
+
+impl<H> DigestHasher<H>
+where
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Creates new [`DigestHasher`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates new [`DigestHasher`] with provided hash.
+    #[must_use]
+    pub const fn with_hash(hash: H) -> Self {
+        Self { hash }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+}
+
+impl<H> Hasher for DigestHasher<H>
+where
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+{
+    fn finish(&self) -> u64 {
+        let digest = self.hash.digest();
+        let digest = digest.as_ref();
+        let mut bytes = [0; 8];
+        let length = digest.len().min(8);
+        bytes[..length].copy_from_slice(&digest[..length]);
+        u64::from_be_bytes(bytes)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.hash.update(bytes);
+    }
+}
+
+impl<H> Write for DigestHasher<H>
+where
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hash.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Builds [`DigestHasher`] instances, for [`HashMap`](std::collections::HashMap) and
+/// [`HashSet`](std::collections::HashSet) keyed by content.
+pub struct BuildDigestHasher<H> {
+    hash: PhantomData<fn() -> H>,
+}
+
+impl<H> BuildDigestHasher<H> {
+    /// Creates new [`BuildDigestHasher`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { hash: PhantomData }
+    }
+}
+
+impl<H> BuildHasher for BuildDigestHasher<H>
+where
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+{
+    type Hasher = DigestHasher<H>;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        DigestHasher::new()
+    }
+}
+
+impl<H> Clone for BuildDigestHasher<H> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<H> Copy for BuildDigestHasher<H> {}
+
+impl<H> Debug for BuildDigestHasher<H> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuildDigestHasher").finish()
+    }
+}
+
+impl<H> Default for BuildDigestHasher<H> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,377 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::mem;
+use std::num::NonZeroU64;
+use std::ops::Range;
+
+use chksum_core::Hash;
+
+use crate::bloom::BloomFilter;
+use crate::locate::BlockManifest;
+use crate::report::DigestReport;
+use crate::sink::ChunkSink;
+use crate::verify::read_back;
+
+/// Returns preferred block size of the file, falling back to 4 KiB where it is not available.
+pub fn block_size(file: &File) -> io::Result<NonZeroU64> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let metadata = file.metadata()?;
+        if let Some(block_size) = NonZeroU64::new(metadata.blksize()) {
+            return Ok(block_size);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = file;
+    Ok(NonZeroU64::new(4096).expect("non-zero"))
+}
+
+/// Wraps a writer and calculates a hash list, the digest of the digests of fixed-size chunks.
+///
+/// The top digest is `H(d1 || d2 || ... || dn)` where `di` is the digest of the `i`-th chunk of the stream. The last
+/// chunk may be shorter than the chunk size, and an empty stream has no chunks. Chunk boundaries can be aligned to
+/// storage blocks with [`HashListWriter::with_alignment`], and completed chunks can be passed to a [`ChunkSink`] with
+/// [`HashListWriter::with_sink`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashListWriter<W, H, S = ()>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    chunk_size: NonZeroU64,
+    first: u64,
+    limit: u64,
+    filled: u64,
+    length: u64,
+    chunks: Vec<H::Digest>,
+    sink: S,
+}
+
+impl<W, H> HashListWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Creates new [`HashListWriter`] with the given chunk size.
+    pub fn new(inner: W, chunk_size: NonZeroU64) -> Self {
+        Self::with_layout(inner, chunk_size, chunk_size.get())
+    }
+
+    /// Passes every completed chunk to the sink. Should be called before writing.
+    #[must_use]
+    pub fn with_sink<S>(self, sink: S) -> HashListWriter<W, H, S>
+    where
+        S: ChunkSink<H::Digest>,
+    {
+        let Self {
+            inner,
+            hash,
+            chunk_size,
+            first,
+            limit,
+            filled,
+            length,
+            chunks,
+            ..
+        } = self;
+        HashListWriter {
+            inner,
+            hash,
+            chunk_size,
+            first,
+            limit,
+            filled,
+            length,
+            chunks,
+            sink,
+        }
+    }
+
+    /// Creates new [`HashListWriter`] with the given chunk layout.
+    pub(crate) fn with_layout(inner: W, chunk_size: NonZeroU64, first: u64) -> Self {
+        Self {
+            inner,
+            hash: H::default(),
+            chunk_size,
+            first,
+            limit: first,
+            filled: 0,
+            length: 0,
+            chunks: Vec::new(),
+            sink: (),
+        }
+    }
+}
+
+impl<W, H, S> HashListWriter<W, H, S>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    S: ChunkSink<H::Digest>,
+{
+    /// Aligns chunk boundaries to blocks of the underlying storage.
+    ///
+    /// The chunk size is rounded up to a multiple of `block_size`, and the first chunk is shortened so that chunk
+    /// boundaries lie on multiples of the chunk size in the storage, where the stream starts at `offset`. Should be
+    /// called before writing.
+    #[must_use]
+    pub fn with_alignment(mut self, block_size: NonZeroU64, offset: u64) -> Self {
+        let (chunk_size, block_size) = (self.chunk_size.get(), block_size.get());
+        let chunk_size = chunk_size + (block_size - chunk_size % block_size) % block_size;
+        self.chunk_size = NonZeroU64::new(chunk_size).unwrap_or(self.chunk_size);
+        self.first = self.chunk_size.get() - offset % self.chunk_size.get();
+        self.limit = self.first;
+        self
+    }
+
+    /// Returns size of the chunks.
+    #[must_use]
+    pub const fn chunk_size(&self) -> NonZeroU64 {
+        self.chunk_size
+    }
+
+    /// Returns digests of the completed chunks.
+    #[must_use]
+    pub fn completed(&self) -> &[H::Digest] {
+        &self.chunks
+    }
+
+    /// Returns digests of all chunks, including the trailing partial one.
+    #[must_use]
+    pub fn chunks(&self) -> Vec<H::Digest>
+    where
+        H::Digest: Clone,
+    {
+        let mut chunks = self.chunks.clone();
+        if self.filled > 0 {
+            chunks.push(self.hash.digest());
+        }
+        chunks
+    }
+
+    /// Returns number of bytes written so far.
+    #[must_use]
+    pub const fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns [`BlockManifest`] describing the chunks written so far, including the trailing partial one.
+    #[must_use]
+    pub fn block_manifest(&self) -> BlockManifest<H::Digest>
+    where
+        H::Digest: Clone,
+    {
+        BlockManifest {
+            chunk_size: self.chunk_size,
+            first: self.first,
+            length: self.length,
+            digests: self.chunks(),
+        }
+    }
+
+    /// Returns [`BloomFilter`] of the digests of all chunks, including the trailing partial one, sized for the given
+    /// false positive rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is not between 0 and 1, exclusive.
+    #[must_use]
+    pub fn bloom_filter(&self, rate: f64) -> BloomFilter
+    where
+        H::Digest: Clone,
+    {
+        let chunks = self.chunks();
+        let mut filter = BloomFilter::with_rate(chunks.len(), rate);
+        for chunk in &chunks {
+            filter.insert(chunk);
+        }
+        filter
+    }
+
+    /// Returns top digest over the digests of all chunks, including the trailing partial one.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        let mut top = H::default();
+        for chunk in &self.chunks {
+            top.update(chunk);
+        }
+        if self.filled > 0 {
+            top.update(self.hash.digest());
+        }
+        top.digest()
+    }
+
+    /// Returns structured summary of the top digest, including digests of all chunks.
+    #[must_use]
+    pub fn report(&self) -> DigestReport
+    where
+        H::Digest: Clone,
+    {
+        DigestReport::new::<H>(self.digest(), self.length, None).with_chunks(self.chunks())
+    }
+
+    /// Unwraps this [`HashListWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Passes the trailing partial chunk and the top digest to the sink, flushes the underlying writer and returns it.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.filled > 0 {
+            self.end_chunk();
+        }
+        let digest = self.digest();
+        self.sink.on_finish(self.length, &digest);
+        self.inner.flush()?;
+        let Self { inner, .. } = self;
+        Ok(inner)
+    }
+
+    /// Returns new [`HashListWriter`] with the same chunk layout, for rehashing the written data.
+    pub(crate) fn rehasher(&self) -> HashListWriter<io::Sink, H> {
+        HashListWriter::with_layout(io::sink(), self.chunk_size, self.first)
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let remaining = self.limit - self.filled;
+            let length = data.len().min(usize::try_from(remaining).unwrap_or(usize::MAX));
+            let (chunk, rest) = data.split_at(length);
+            self.hash.update(chunk);
+            self.filled += length as u64;
+            self.length += length as u64;
+            if self.filled == self.limit {
+                self.end_chunk();
+                self.limit = self.chunk_size.get();
+            }
+            data = rest;
+        }
+    }
+
+    fn end_chunk(&mut self) {
+        let digest = mem::take(&mut self.hash).digest();
+        self.sink.on_chunk(self.length - self.filled, self.filled, &digest);
+        self.chunks.push(digest);
+        self.filled = 0;
+    }
+}
+
+impl<W, H, S> HashListWriter<W, H, S>
+where
+    W: Read + Write + Seek,
+    H: Hash,
+    H::Digest: AsRef<[u8]> + Clone + PartialEq,
+    S: ChunkSink<H::Digest>,
+{
+    /// Reads the written data back from the underlying writer and compares chunk digests, returning byte ranges which
+    /// differ, relative to the start of the stream.
+    ///
+    /// The hashed stream is assumed to end at the current position of the underlying writer, which is restored
+    /// afterwards.
+    pub fn verify_written(&mut self) -> io::Result<Vec<Range<u64>>> {
+        let end = self.inner.stream_position()?;
+        let mut writer = self.rehasher();
+        read_back(&mut self.inner, end, self.length, &mut writer)?;
+        let expected = self.block_manifest();
+        let actual = writer.block_manifest();
+        Ok(expected.locate(&actual).unwrap_or_default())
+    }
+}
+
+impl<W, H, S> Write for HashListWriter<W, H, S>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    S: ChunkSink<H::Digest>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::{self, Seek, SeekFrom, Write};
+    use std::num::NonZeroU64;
+    use std::ops::Range;
+    use std::sync::mpsc;
+
+    use chksum_core::Hash;
+
+    use super::HashListWriter;
+    use crate::testing::Sha256;
+
+    fn size(size: u64) -> NonZeroU64 {
+        NonZeroU64::new(size).unwrap()
+    }
+
+    #[test]
+    fn top_digest() {
+        let mut writer = HashListWriter::<_, Sha256>::new(io::sink(), size(4));
+        assert_eq!(
+            writer.digest().to_string(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        for byte in b"abcdefghij" {
+            writer.write_all(&[*byte]).unwrap();
+        }
+        assert_eq!(writer.completed(), [Sha256::hash(b"abcd"), Sha256::hash(b"efgh")]);
+        assert_eq!(writer.chunks().last(), Some(&Sha256::hash(b"ij")));
+        assert_eq!(
+            writer.digest().to_string(),
+            "39bca6fa3619f67ee4d6f8df51491558560f429920593f7f649119ba3f285ed9"
+        );
+    }
+
+    #[test]
+    fn aligned_chunks() {
+        let (sender, receiver) = mpsc::channel();
+        // chunks of 5 rounded up to blocks of 4, with the stream starting 3 bytes into a chunk
+        let mut writer = HashListWriter::<_, Sha256>::new(io::sink(), size(5))
+            .with_alignment(size(4), 3)
+            .with_sink(sender);
+        assert_eq!(writer.chunk_size(), size(8));
+        writer.write_all(&[7; 20]).unwrap();
+        writer.finish().unwrap();
+        let chunks: Vec<_> = receiver.iter().map(|(offset, length, _)| (offset, length)).collect();
+        assert_eq!(chunks, [(0, 5), (5, 8), (13, 7)]);
+    }
+
+    #[test]
+    fn locates_corruption() {
+        let path = std::env::temp_dir().join(format!("chksum-writer-hashlist-{}", std::process::id()));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        let mut writer = HashListWriter::<_, Sha256>::new(file, size(16));
+        writer.write_all(&(0..100).collect::<Vec<u8>>()).unwrap();
+        assert!(writer.verify_written().unwrap().is_empty());
+
+        let mut other = OpenOptions::new().write(true).open(&path).unwrap();
+        other.seek(SeekFrom::Start(40)).unwrap();
+        other.write_all(b"x").unwrap();
+        let result = writer.verify_written();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(result.unwrap(), [Range { start: 32, end: 48 }]);
+    }
+}
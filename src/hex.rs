@@ -0,0 +1,175 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::algorithm::Algorithm;
+use crate::error::ParseDigestError;
+
+/// Digest parsed from a hexadecimal string, comparable with computed digests and other hexadecimal strings.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ParsedDigest {
+    bytes: Vec<u8>,
+}
+
+impl ParsedDigest {
+    /// Returns bytes of the digest.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl AsRef<[u8]> for ParsedDigest {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Display for ParsedDigest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for byte in &self.bytes {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq<[u8]> for ParsedDigest {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.bytes == other
+    }
+}
+
+impl PartialEq<&[u8]> for ParsedDigest {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self.bytes == *other
+    }
+}
+
+/// Compares with a hexadecimal string, ignoring case.
+impl PartialEq<str> for ParsedDigest {
+    fn eq(&self, other: &str) -> bool {
+        other.len() == self.bytes.len() * 2 && decode(other).is_ok_and(|bytes| bytes == self.bytes)
+    }
+}
+
+/// Compares with a hexadecimal string, ignoring case.
+impl PartialEq<&str> for ParsedDigest {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+/// Parses hexadecimal digest of the algorithm, optionally prefixed with the algorithm name, like `sha256:<digest>`.
+///
+/// The prefix name is compared with [`Algorithm::NAME`] ignoring case and dashes.
+pub fn parse_digest<H>(digest: &str) -> Result<ParsedDigest, ParseDigestError>
+where
+    H: Algorithm,
+{
+    let digest = match digest.split_once(':') {
+        Some((name, digest)) => {
+            if normalize(name) != normalize(H::NAME) {
+                return Err(ParseDigestError::AlgorithmMismatch {
+                    expected: H::NAME,
+                    actual: name.to_owned(),
+                });
+            }
+            digest
+        },
+        None => digest,
+    };
+    let bytes = decode(digest)?;
+    if bytes.len() != H::DIGEST_SIZE || digest.len() % 2 != 0 {
+        return Err(ParseDigestError::InvalidLength {
+            expected: H::DIGEST_SIZE,
+            actual: digest.len(),
+        });
+    }
+    Ok(ParsedDigest { bytes })
+}
+
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|&char| char != '-')
+        .map(|char| char.to_ascii_lowercase())
+        .collect()
+}
+
+/// Decodes pairs of hexadecimal digits, ignoring a trailing unpaired digit.
+fn decode(digest: &str) -> Result<Vec<u8>, ParseDigestError> {
+    let mut nibbles = Vec::with_capacity(digest.len());
+    for (index, character) in digest.chars().enumerate() {
+        let nibble = character
+            .to_digit(16)
+            .ok_or(ParseDigestError::InvalidCharacter { index, character })?;
+        nibbles.push(nibble as u8);
+    }
+    let bytes = nibbles.chunks_exact(2).map(|pair| pair[0] << 4 | pair[1]).collect();
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use chksum_core::{Digest, Hash};
+
+    use super::parse_digest;
+    use crate::error::ParseDigestError;
+    use crate::testing::{Sha1, Sha256};
+
+    const DIGEST: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+
+    #[test]
+    fn round_trips() {
+        let digest = Sha256::hash(b"hello world");
+        for text in [DIGEST.to_owned(), DIGEST.to_ascii_uppercase()] {
+            let parsed = parse_digest::<Sha256>(&text).unwrap();
+            assert_eq!(parsed.to_string(), DIGEST);
+            assert_eq!(parsed.as_bytes(), digest.as_bytes());
+            assert!(parsed == digest.as_bytes());
+            assert!(parsed == text.as_str());
+        }
+        let parsed = parse_digest::<Sha256>(DIGEST).unwrap();
+        assert!(parsed == DIGEST.to_ascii_uppercase().as_str());
+        assert!(parsed != DIGEST[..62]);
+        assert!(parsed != "zz");
+    }
+
+    #[test]
+    fn accepts_algorithm_prefix() {
+        for prefix in ["sha256", "SHA-256", "Sha256"] {
+            let parsed = parse_digest::<Sha256>(&format!("{prefix}:{DIGEST}")).unwrap();
+            assert_eq!(parsed.to_string(), DIGEST, "{prefix}");
+        }
+        assert_eq!(
+            parse_digest::<Sha1>(&format!("sha256:{DIGEST}")),
+            Err(ParseDigestError::AlgorithmMismatch {
+                expected: "SHA-1",
+                actual: "sha256".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert_eq!(
+            parse_digest::<Sha256>(&DIGEST[..62]),
+            Err(ParseDigestError::InvalidLength {
+                expected: 32,
+                actual: 62,
+            })
+        );
+        assert_eq!(
+            parse_digest::<Sha256>(&format!("{DIGEST}0")),
+            Err(ParseDigestError::InvalidLength {
+                expected: 32,
+                actual: 65,
+            })
+        );
+        assert_eq!(
+            parse_digest::<Sha256>(&DIGEST.replacen('e', "g", 1)),
+            Err(ParseDigestError::InvalidCharacter {
+                index: 13,
+                character: 'g'
+            })
+        );
+    }
+}
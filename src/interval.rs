@@ -0,0 +1,290 @@
+use std::future::poll_fn;
+use std::io;
+use std::pin::{pin, Pin};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+use chksum_core::Hash;
+use tokio::io::AsyncWrite;
+
+use crate::checkpoint::{Checkpoint, Interval, Schedule};
+
+/// Latest checkpoint not received yet, shared by an [`AsyncIntervalWriter`] and its [`DigestStream`].
+#[derive(Debug)]
+struct Slot<D> {
+    checkpoint: Option<Checkpoint<D>>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+fn lock<D>(slot: &Mutex<Slot<D>>) -> MutexGuard<'_, Slot<D>> {
+    slot.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Publishing end of a [`DigestStream`], closing it when dropped.
+#[derive(Debug)]
+struct Publisher<D>(Arc<Mutex<Slot<D>>>);
+
+impl<D> Publisher<D> {
+    /// Replaces the checkpoint not received yet, if any.
+    fn publish(&self, checkpoint: Checkpoint<D>) {
+        let waker = {
+            let mut slot = lock(&self.0);
+            slot.checkpoint = Some(checkpoint);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<D> Drop for Publisher<D> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut slot = lock(&self.0);
+            slot.closed = true;
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Async stream of intermediate digests produced by an [`AsyncIntervalWriter`].
+///
+/// The stream holds only the latest checkpoint, so memory use stays constant no matter how far the writer runs
+/// ahead. A consumer slower than the writer skips the intermediate checkpoints it has not received in time, but never
+/// the final one, emitted on shutdown, after which the stream ends.
+///
+/// `DigestStream` does not implement `Stream`, as this crate does not depend on `futures`, but
+/// [`DigestStream::poll_next`] and [`DigestStream::next`] mirror `Stream::poll_next` and `StreamExt::next`.
+#[derive(Debug)]
+pub struct DigestStream<D> {
+    slot: Arc<Mutex<Slot<D>>>,
+}
+
+impl<D> DigestStream<D> {
+    /// Polls for the next checkpoint, returning `None` once the writer is shut down or dropped.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Checkpoint<D>>> {
+        let mut slot = lock(&self.slot);
+        if let Some(checkpoint) = slot.checkpoint.take() {
+            return Poll::Ready(Some(checkpoint));
+        }
+        if slot.closed {
+            return Poll::Ready(None);
+        }
+        match &mut slot.waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {},
+            waker => *waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+
+    /// Receives the next checkpoint, returning `None` once the writer is shut down or dropped.
+    pub async fn next(&mut self) -> Option<Checkpoint<D>> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+}
+
+/// Wraps a writer and emits intermediate hash digests to a [`DigestStream`].
+#[derive(Debug)]
+pub struct AsyncIntervalWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    offset: u64,
+    schedule: Schedule,
+    publisher: Option<Publisher<H::Digest>>,
+}
+
+impl<W, H> AsyncIntervalWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    /// Creates new [`AsyncIntervalWriter`] along with the stream of its intermediate digests.
+    pub fn new(inner: W, interval: Interval) -> (Self, DigestStream<H::Digest>) {
+        let hash = H::default();
+        Self::with_hash(inner, interval, hash)
+    }
+
+    /// Creates new [`AsyncIntervalWriter`] with provided hash along with the stream of its intermediate digests.
+    pub fn with_hash(inner: W, interval: Interval, hash: H) -> (Self, DigestStream<H::Digest>) {
+        let slot = Slot {
+            checkpoint: None,
+            closed: false,
+            waker: None,
+        };
+        let slot = Arc::new(Mutex::new(slot));
+        let writer = Self {
+            inner,
+            hash,
+            offset: 0,
+            schedule: Schedule::new(interval),
+            publisher: Some(Publisher(Arc::clone(&slot))),
+        };
+        (writer, DigestStream { slot })
+    }
+
+    /// Returns number of bytes written so far.
+    #[must_use]
+    pub const fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Unwraps this [`AsyncIntervalWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+
+    /// Emits a checkpoint if one is due.
+    ///
+    /// Checkpoints are only emitted while writing, so for time-based intervals on streams which may stall, call this
+    /// from a timer as well.
+    pub fn checkpoint_if_due(&mut self) {
+        if self.schedule.is_due(self.offset) {
+            self.emit();
+        }
+    }
+
+    fn emit(&mut self) {
+        if let Some(publisher) = &self.publisher {
+            let checkpoint = Checkpoint {
+                offset: self.offset,
+                digest: self.hash.digest(),
+            };
+            publisher.publish(checkpoint);
+        }
+        self.schedule.mark(self.offset);
+    }
+}
+
+impl<W, H> AsyncWrite for AsyncIntervalWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        let length = this.schedule.limit(this.offset, buf.len());
+        match pin!(&mut this.inner).poll_write(cx, &buf[..length]) {
+            Poll::Ready(Ok(n)) => {
+                this.hash.update(&buf[..n]);
+                this.offset += n as u64;
+                if n > 0 && this.schedule.is_due(this.offset) {
+                    this.emit();
+                }
+                Poll::Ready(Ok(n))
+            },
+            poll => poll,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { inner, .. } = self.get_mut();
+        pin!(inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        match pin!(&mut this.inner).poll_shutdown(cx) {
+            Poll::Ready(Ok(())) => {
+                if this.schedule.is_pending(this.offset) {
+                    this.emit();
+                }
+                // close the stream
+                this.publisher = None;
+                Poll::Ready(Ok(()))
+            },
+            poll => poll,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use chksum_core::Hash;
+    use tokio::io::AsyncWriteExt;
+
+    use super::AsyncIntervalWriter;
+    use crate::testing::{block_on, Sha256};
+    use crate::{Checkpoint, Interval};
+
+    const DATA: &[u8] = b"hello world";
+
+    fn every(bytes: u64) -> Interval {
+        Interval::Bytes(NonZeroU64::new(bytes).unwrap())
+    }
+
+    fn checkpoint(offset: usize) -> Checkpoint<<Sha256 as Hash>::Digest> {
+        Checkpoint {
+            offset: offset as u64,
+            digest: Sha256::hash(&DATA[..offset]),
+        }
+    }
+
+    #[test]
+    fn emits_on_boundaries() {
+        block_on(async {
+            let (mut writer, mut stream) = AsyncIntervalWriter::<_, Sha256>::new(Vec::new(), every(4));
+            writer.write_all(&DATA[..4]).await.unwrap();
+            assert_eq!(stream.next().await, Some(checkpoint(4)));
+            writer.write_all(&DATA[4..8]).await.unwrap();
+            assert_eq!(stream.next().await, Some(checkpoint(8)));
+            writer.write_all(&DATA[8..]).await.unwrap();
+            writer.shutdown().await.unwrap();
+            assert_eq!(stream.next().await, Some(checkpoint(11)));
+            assert_eq!(stream.next().await, None);
+            assert_eq!(writer.into_inner(), DATA);
+        });
+    }
+
+    #[test]
+    fn keeps_latest_checkpoint() {
+        block_on(async {
+            let (mut writer, mut stream) = AsyncIntervalWriter::<_, Sha256>::new(Vec::new(), every(2));
+            writer.write_all(DATA).await.unwrap();
+            assert_eq!(stream.next().await, Some(checkpoint(10)));
+            writer.shutdown().await.unwrap();
+            assert_eq!(stream.next().await, Some(checkpoint(11)));
+            assert_eq!(stream.next().await, None);
+        });
+    }
+
+    #[test]
+    fn final_checkpoint_is_not_repeated() {
+        block_on(async {
+            let (mut writer, mut stream) = AsyncIntervalWriter::<_, Sha256>::new(Vec::new(), every(4));
+            writer.write_all(&DATA[..8]).await.unwrap();
+            writer.shutdown().await.unwrap();
+            assert_eq!(stream.next().await, Some(checkpoint(8)));
+            assert_eq!(stream.next().await, None);
+        });
+    }
+
+    #[test]
+    fn drop_closes_stream() {
+        block_on(async {
+            let (writer, mut stream) = AsyncIntervalWriter::<_, Sha256>::new(Vec::new(), every(4));
+            let consumer = tokio::spawn(async move { stream.next().await });
+            tokio::task::yield_now().await;
+            drop(writer);
+            assert_eq!(consumer.await.unwrap(), None);
+        });
+    }
+}
@@ -0,0 +1,215 @@
+use std::io::Write;
+
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{Algorithm, Writer};
+
+/// Calculates HMAC of the data with the key, as specified in RFC 2104.
+///
+/// The key is padded to [`Algorithm::BLOCK_SIZE`], or hashed first if it is longer.
+pub fn hmac<H>(key: &[u8], data: &[u8]) -> H::Digest
+where
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    let mut block = vec![0; H::BLOCK_SIZE];
+    if key.len() > H::BLOCK_SIZE {
+        let digest = H::hash(key);
+        let digest = digest.as_ref();
+        let length = digest.len().min(H::BLOCK_SIZE);
+        block[..length].copy_from_slice(&digest[..length]);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    // the same block holds the inner pad and then the outer pad
+    block.iter_mut().for_each(|byte| *byte ^= 0x36);
+    let mut inner = H::default();
+    inner.update(&block);
+    inner.update(data);
+    block.iter_mut().for_each(|byte| *byte ^= 0x36 ^ 0x5C);
+    let mut outer = H::default();
+    outer.update(&block);
+    outer.update(inner.digest());
+    outer.digest()
+}
+
+/// Derives key of the given length from the input keying material with HKDF, as specified in RFC 5869.
+///
+/// An empty salt is replaced by zeros of the digest length. Returns [`None`] if the length exceeds 255 times the
+/// digest length.
+#[must_use]
+pub fn hkdf<H>(salt: &[u8], ikm: &[u8], info: &[u8], length: usize) -> Option<Vec<u8>>
+where
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    let zeros;
+    let salt = if salt.is_empty() {
+        zeros = vec![0; H::DIGEST_SIZE];
+        &zeros
+    } else {
+        salt
+    };
+    let prk = hmac::<H>(salt, ikm);
+    let prk = prk.as_ref();
+    if length > 255 * prk.len() {
+        return None;
+    }
+    let mut okm = Vec::with_capacity(length);
+    let mut previous = Vec::new();
+    for counter in 1..=u8::MAX {
+        if okm.len() >= length {
+            break;
+        }
+        let mut input = previous;
+        input.extend_from_slice(info);
+        input.push(counter);
+        previous = hmac::<H>(prk, &input).as_ref().to_vec();
+        let remaining = length - okm.len();
+        okm.extend_from_slice(&previous[..remaining.min(previous.len())]);
+    }
+    Some(okm)
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Derives key of the given length from the calculated hash digest with HKDF, using an empty salt.
+    ///
+    /// Useful for convergent encryption, where the key is derived from the content. Returns [`None`] if the length
+    /// exceeds 255 times the digest length.
+    #[must_use]
+    pub fn derive_key(&self, info: &[u8], length: usize) -> Option<Vec<u8>> {
+        hkdf::<H>(&[], self.digest().as_ref(), info, length)
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Derives key of the given length from the calculated hash digest with HKDF, using an empty salt.
+    ///
+    /// See [`Writer::derive_key`] for details.
+    #[must_use]
+    pub fn derive_key(&self, info: &[u8], length: usize) -> Option<Vec<u8>> {
+        hkdf::<H>(&[], self.digest().as_ref(), info, length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chksum_core::Hash;
+
+    use super::{hkdf, hmac};
+    use crate::testing::{Sha1, Sha256};
+
+    fn hex(bytes: impl AsRef<[u8]>) -> String {
+        bytes.as_ref().iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn rfc4231_hmac_sha256() {
+        let cases: [(&[u8], &[u8], &str); 6] = [
+            (
+                &[0x0B; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                &[0xAA; 20],
+                &[0xDD; 50],
+                "773ea91e36800e46854db8ebd09181a72959098b3ef8c122d9635514ced565fe",
+            ),
+            (
+                &[
+                    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11,
+                    0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19,
+                ],
+                &[0xCD; 50],
+                "82558a389a443c0ea4cc819899f2083a85f0faa3e578f8077a2e3ff46729665b",
+            ),
+            // keys longer than the block size are hashed first
+            (
+                &[0xAA; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+            (
+                &[0xAA; 131],
+                b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to \
+                  be hashed before being used by the HMAC algorithm.",
+                "9b09ffa71b942fcb27635fbcd5b0e944bfdc63644f0713938a7f51535c3a35e2",
+            ),
+        ];
+        for (key, data, expected) in cases {
+            assert_eq!(hex(hmac::<Sha256>(key, data)), expected);
+        }
+    }
+
+    #[test]
+    fn rfc2202_hmac_sha1() {
+        let cases: [(&[u8], &[u8], &str); 3] = [
+            (&[0x0B; 20], b"Hi There", "b617318655057264e28bc0b6fb378c8ef146be00"),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            ),
+            (
+                &[0xAA; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            ),
+        ];
+        for (key, data, expected) in cases {
+            assert_eq!(hex(hmac::<Sha1>(key, data)), expected);
+        }
+    }
+
+    #[test]
+    fn rfc5869_hkdf() {
+        let salt: Vec<u8> = (0x00..=0x0C).collect();
+        let info: Vec<u8> = (0xF0..=0xF9).collect();
+        // test cases 1, 3 and 4
+        assert_eq!(
+            hex(hkdf::<Sha256>(&salt, &[0x0B; 22], &info, 42).unwrap()),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+        assert_eq!(
+            hex(hkdf::<Sha256>(&[], &[0x0B; 22], &[], 42).unwrap()),
+            "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8"
+        );
+        assert_eq!(
+            hex(hkdf::<Sha1>(&salt, &[0x0B; 11], &info, 42).unwrap()),
+            "085a01ea1b10f36933068b56efa5ad81a4f14b822f5b091568a9cdd4f155fda2c22e422478d305f3f896"
+        );
+    }
+
+    #[test]
+    fn hkdf_length_limit() {
+        let okm = hkdf::<Sha256>(b"salt", b"ikm", &[], 255 * 32).unwrap();
+        assert_eq!(okm.len(), 255 * 32);
+        assert_eq!(
+            hex(Sha256::hash(&okm)),
+            "a9c5ce9e0168d4c83eef976f1904269a496864f3e7d56dd0aac34e32d860429f"
+        );
+        assert_eq!(okm[..42], hkdf::<Sha256>(b"salt", b"ikm", &[], 42).unwrap());
+        assert_eq!(hkdf::<Sha256>(b"salt", b"ikm", &[], 255 * 32 + 1), None);
+        assert_eq!(hkdf::<Sha256>(b"salt", b"ikm", &[], 0), Some(Vec::new()));
+    }
+}
@@ -0,0 +1,20 @@
+use chksum_core::Hash;
+
+/// A trait for hashes supporting a keyed mode, like BLAKE3.
+pub trait KeyedHash: Hash {
+    /// The type of the key.
+    type Key: ?Sized;
+
+    /// Creates new hash in keyed mode.
+    #[must_use]
+    fn new_keyed(key: &Self::Key) -> Self;
+}
+
+/// A trait for hashes supporting a key derivation mode, like BLAKE3.
+pub trait DeriveKeyHash: Hash {
+    /// Creates new hash in key derivation mode for the given context string.
+    ///
+    /// The context should be hardcoded, globally unique and application-specific.
+    #[must_use]
+    fn new_derive_key(context: &str) -> Self;
+}
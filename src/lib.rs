@@ -23,6 +23,36 @@
 //!
 //! By default, neither of these features is enabled.
 //!
+//! ## Asynchronous Extensions
+//!
+//! * `async-double-buffer`: Enables [`DoubleBufferedWriter`] overlapping hashing with writes, at the cost of doubled
+//!   buffer memory.
+//! * `async-timeout`: Enables [`AsyncWriter::write_all_timeout`] and [`AsyncWriter::shutdown_timeout`].
+//!
+//! ## Integrations
+//!
+//! * `bmap`: Enables [`BmapWriter`] writing block maps of `bmaptool`.
+//! * `caibx`: Enables [`CaibxWriter`] writing chunk indexes of `casync`.
+//! * `content-digest`: Enables [`VerifyingWriter::from_content_digest`] and [`VerifyingWriter::from_content_md5`]
+//!   verifying HTTP request bodies.
+//! * `dkim`: Enables [`DkimBodyWriter`] calculating DKIM body hashes.
+//! * `etag`: Enables [`Etag`] comparing HTTP entity tags, like those of S3 objects, with uploaded data.
+//! * `git`: Enables [`GitObjectWriter`] calculating Git object IDs.
+//! * `librsync`: Enables [`SignatureWriter`] writing `librsync` signatures.
+//! * `manifest`: Enables reading, writing and verifying checksum manifests with [`parse_manifest`], [`write_manifest`]
+//!   and [`verify_manifest`].
+//! * `metrics`: Enables per-algorithm telemetry of many writers in the Prometheus text format with [`Collector`].
+//! * `multipart`: Enables [`MultipartWriter`] writing `multipart/form-data` bodies with per-part digests.
+//! * `nar`: Enables [`nar_hash`] and [`Writer::write_nar`] hashing Nix archives.
+//! * `upload`: Enables [`ResumableUpload`] hashing chunked uploads resumed from a saved state.
+//! * `zsync`: Enables [`ZsyncWriter`] writing `zsync` control files.
+//!
+//! By default, none of these features is enabled, and the crate depends on `chksum-core` only.
+//!
+//! ## Testing
+//!
+//! * `test-util`: Enables [`test_util`] module with writers injecting faults.
+//!
 //! # Usage
 //!
 //! ```rust,ignore
@@ -61,15 +91,202 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
 #![forbid(unsafe_code)]
 
+mod algorithm;
+mod aligned;
+mod audit;
+mod base64;
+mod bloom;
+#[cfg(feature = "bmap")]
+mod bmap;
+mod builder;
+mod cache;
+#[cfg(feature = "caibx")]
+mod caibx;
+mod checkpoint;
+mod convergent;
+#[cfg(feature = "dkim")]
+mod dkim;
+#[cfg(feature = "async-double-buffer")]
+mod double;
+mod durable;
+mod error;
+mod escape;
+#[cfg(feature = "etag")]
+mod etag;
+#[cfg(feature = "async-runtime-tokio")]
+mod events;
+mod exclude;
+mod filter;
+mod fingerprint;
+mod format;
+mod frame;
+#[cfg(feature = "git")]
+mod git;
+mod guard;
+mod hasher;
+mod hashlist;
+mod hex;
+#[cfg(feature = "async-runtime-tokio")]
+mod interval;
+mod kdf;
+mod keyed;
+mod line;
+mod locate;
+#[cfg(feature = "manifest")]
+mod manifest;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod mmr;
+#[cfg(feature = "multipart")]
+mod multipart;
+mod namespace;
+#[cfg(feature = "nar")]
+mod nar;
+mod parallel;
+mod pool;
+mod positioned;
+mod prealloc;
+mod progress;
+mod proof;
+mod record;
+mod report;
+#[cfg(any(feature = "librsync", feature = "zsync"))]
+mod rolling;
+#[cfg(feature = "async-runtime-tokio")]
+mod seal;
+mod seek;
+mod segment;
+#[cfg(feature = "librsync")]
+mod signature;
+mod sink;
+mod split;
+mod state;
+mod stats;
+mod temp;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+#[cfg(test)]
+mod testing;
+#[cfg(feature = "async-timeout")]
+mod timeout;
+mod update;
+#[cfg(feature = "upload")]
+mod upload;
+mod uuid;
+mod vec;
+mod verify;
+mod xof;
+#[cfg(feature = "zsync")]
+mod zsync;
+
+use std::fmt;
 use std::io::{self, Write};
 #[cfg(feature = "async-runtime-tokio")]
-use std::pin::{pin, Pin};
+use std::pin::Pin;
 #[cfg(feature = "async-runtime-tokio")]
-use std::task::{Context, Poll};
+use std::task::{ready, Context, Poll};
+use std::time::SystemTime;
 
 use chksum_core::Hash;
 #[cfg(feature = "async-runtime-tokio")]
+use pin_project_lite::pin_project;
+#[cfg(feature = "async-runtime-tokio")]
 use tokio::io::{AsyncWrite, AsyncWriteExt};
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::sync::broadcast;
+
+pub use crate::algorithm::Algorithm;
+pub use crate::aligned::{AlignedWriter, Truncate};
+#[cfg(feature = "async-runtime-tokio")]
+pub use crate::audit::AsyncAuditWriter;
+pub use crate::audit::{AuditEntry, AuditRing, AuditSink, AuditWriter};
+pub use crate::bloom::BloomFilter;
+#[cfg(feature = "bmap")]
+pub use crate::bmap::BmapWriter;
+pub use crate::builder::Builder;
+use crate::builder::Suffix;
+pub use crate::cache::{CacheKey, DigestCache, DigestStore, FileStore, MemoryStore};
+#[cfg(feature = "caibx")]
+pub use crate::caibx::CaibxWriter;
+pub use crate::checkpoint::{Checkpoint, Interval, IntervalWriter};
+pub use crate::convergent::{Convergent, ConvergentWriter};
+#[cfg(feature = "dkim")]
+pub use crate::dkim::{BodyCanonicalization, DkimBodyWriter};
+#[cfg(feature = "async-double-buffer")]
+pub use crate::double::DoubleBufferedWriter;
+pub use crate::durable::{DurableWriter, SyncData};
+pub use crate::error::{Error, IntoInnerError, ParseDigestError};
+#[cfg(feature = "etag")]
+pub use crate::etag::{Etag, EtagMatch};
+#[cfg(feature = "async-runtime-tokio")]
+pub use crate::events::{AsyncEventWriter, DigestEvent};
+pub use crate::exclude::{ExcludeWriter, Exclusions, NoHook};
+#[cfg(feature = "async-runtime-tokio")]
+pub use crate::filter::AsyncFilterWriter;
+pub use crate::filter::FilterWriter;
+pub use crate::frame::{Frame, FrameDigestWriter};
+#[cfg(feature = "git")]
+pub use crate::git::{GitObjectWriter, ObjectKind};
+#[cfg(feature = "async-runtime-tokio")]
+pub use crate::guard::AsyncDigestGuard;
+pub use crate::guard::{DigestGuard, GuardBuilder};
+pub use crate::hasher::{BuildDigestHasher, DigestHasher};
+pub use crate::hashlist::{block_size, HashListWriter};
+pub use crate::hex::{parse_digest, ParsedDigest};
+#[cfg(feature = "async-runtime-tokio")]
+pub use crate::interval::{AsyncIntervalWriter, DigestStream};
+pub use crate::kdf::{hkdf, hmac};
+pub use crate::keyed::{DeriveKeyHash, KeyedHash};
+pub use crate::line::LineDigestWriter;
+pub use crate::locate::BlockManifest;
+#[cfg(feature = "manifest")]
+pub use crate::manifest::{parse_manifest, parse_sfv, write_manifest, write_sfv_comment, Format, ManifestEntry};
+#[cfg(all(feature = "async-runtime-tokio", feature = "metrics"))]
+pub use crate::metrics::AsyncMetricsWriter;
+#[cfg(feature = "metrics")]
+pub use crate::metrics::{Collector, MetricsWriter};
+pub use crate::mmr::MmrWriter;
+#[cfg(feature = "multipart")]
+pub use crate::multipart::MultipartWriter;
+pub use crate::namespace::NamespacedWriter;
+#[cfg(feature = "nar")]
+pub use crate::nar::nar_hash;
+pub use crate::parallel::{ParallelHash, ParallelWriter};
+pub use crate::pool::{hash_files_parallel, PoolResult, PoolResults, WriterPool};
+pub use crate::positioned::PositionedWriter;
+pub use crate::prealloc::Preallocate;
+#[cfg(feature = "async-runtime-tokio")]
+pub use crate::progress::AsyncProgressWriter;
+pub use crate::progress::{Progress, ProgressWriter};
+pub use crate::proof::{leaf_digest, verify_proof, Proof, Side};
+pub use crate::record::{DigestSet, RecordWriter};
+pub use crate::report::DigestReport;
+#[cfg(feature = "async-runtime-tokio")]
+use crate::seal::Seal;
+use crate::seek::Tracking;
+pub use crate::segment::{Rotation, Segment, SegmentedLogWriter};
+#[cfg(feature = "librsync")]
+pub use crate::signature::SignatureWriter;
+pub use crate::sink::{ChunkLog, ChunkSink};
+#[cfg(feature = "async-runtime-tokio")]
+pub use crate::split::AsyncSplitWriter;
+pub use crate::split::{DigestHandle, SplitWriter};
+pub use crate::state::{AbortReport, FsStateStore, ResumableHash, State, StateStore};
+pub use crate::stats::WriteStats;
+pub use crate::temp::SaveOptions;
+#[cfg(feature = "async-runtime-tokio")]
+use crate::update::Pending;
+pub use crate::update::UpdateOrder;
+#[cfg(feature = "upload")]
+pub use crate::upload::ResumableUpload;
+pub use crate::uuid::Uuid;
+pub use crate::vec::VecWriter;
+pub use crate::verify::VerifyingWriter;
+#[cfg(feature = "manifest")]
+pub use crate::verify::{verify_manifest, VerifyResult};
+pub use crate::xof::ExtendableHash;
+#[cfg(feature = "zsync")]
+pub use crate::zsync::ZsyncWriter;
 
 /// Creates new [`Writer`].
 pub fn new<H>(inner: impl Write) -> Writer<impl Write, H>
@@ -79,6 +296,15 @@ where
     Writer::new(inner)
 }
 
+/// Creates new [`Builder`].
+#[must_use]
+pub fn builder<H>() -> Builder<H>
+where
+    H: Hash,
+{
+    Builder::new()
+}
+
 /// Creates new [`Writer`] with provided hash.
 pub fn with_hash<H>(inner: impl Write, hash: H) -> Writer<impl Write, H>
 where
@@ -87,6 +313,30 @@ where
     Writer::with_hash(inner, hash)
 }
 
+/// Writes all bytes to the writer and returns their hash digest.
+pub fn write_all_and_digest<H>(inner: impl Write, data: impl AsRef<[u8]>) -> io::Result<H::Digest>
+where
+    H: Hash,
+{
+    write_chunks_and_digest::<H>(inner, [data])
+}
+
+/// Writes all chunks to the writer and returns hash digest of their concatenation.
+pub fn write_chunks_and_digest<H>(
+    inner: impl Write,
+    chunks: impl IntoIterator<Item = impl AsRef<[u8]>>,
+) -> io::Result<H::Digest>
+where
+    H: Hash,
+{
+    let mut writer = Writer::<_, H>::new(inner);
+    for chunk in chunks {
+        writer.write_all(chunk.as_ref())?;
+    }
+    writer.flush()?;
+    Ok(writer.digest())
+}
+
 #[cfg(feature = "async-runtime-tokio")]
 /// Creates new [`AsyncWriter`].
 pub fn async_new<H>(inner: impl AsyncWrite) -> AsyncWriter<impl AsyncWrite, H>
@@ -105,15 +355,70 @@ where
     AsyncWriter::with_hash(inner, hash)
 }
 
+#[cfg(feature = "async-runtime-tokio")]
+/// Writes all bytes to the writer and returns their hash digest.
+pub async fn async_write_all_and_digest<H>(
+    inner: impl AsyncWrite + Unpin,
+    data: impl AsRef<[u8]>,
+) -> io::Result<H::Digest>
+where
+    H: Hash + Unpin,
+{
+    async_write_chunks_and_digest::<H>(inner, [data]).await
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+/// Writes all chunks to the writer and returns hash digest of their concatenation.
+pub async fn async_write_chunks_and_digest<H>(
+    inner: impl AsyncWrite + Unpin,
+    chunks: impl IntoIterator<Item = impl AsRef<[u8]>>,
+) -> io::Result<H::Digest>
+where
+    H: Hash + Unpin,
+{
+    let mut writer = AsyncWriter::<_, H>::new(inner);
+    for chunk in chunks {
+        writer.write_all(chunk.as_ref()).await?;
+    }
+    writer.flush().await?;
+    Ok(writer.digest())
+}
+
 /// Wraps a writer and calculates the hash digest on the fly.
+///
+/// # Lending
+///
+/// [`Write::by_ref`] borrows the writer as `&mut Writer`, which implements [`Write`] too, so the writer can be passed
+/// to functions taking `impl Write` by value and still be finalized afterwards.
+///
+/// # Type erasure
+///
+/// The underlying writer may be unsized, so a `Box<Writer<W, H>>` can be coerced to `Box<Writer<dyn Write, H>>` to
+/// erase the writer type while keeping access to the digest.
+///
+/// # Allocation
+///
+/// Creating the writer, writing and reading the digest do not allocate, unless the hash or the underlying writer do.
+/// Only a suffix configured with [`Builder`] is kept on the heap, and [`Writer::digest_array`] gives the digest as a
+/// fixed-size array.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Writer<W, H>
 where
-    W: Write,
+    W: Write + ?Sized,
     H: Hash,
 {
-    inner: W,
     hash: H,
+    count: u64,
+    started: Option<SystemTime>,
+    suffix: Vec<Suffix>,
+    stats: WriteStats,
+    tracking: Tracking,
+    context: bool,
+    order: UpdateOrder,
+    name: Option<String>,
+    expected: Option<u64>,
+    // must be last for `W` to be unsized
+    inner: W,
 }
 
 impl<W, H> Writer<W, H>
@@ -130,7 +435,45 @@ where
     /// Creates new [`Writer`] with provided hash.
     #[must_use]
     pub const fn with_hash(inner: W, hash: H) -> Self {
-        Self { inner, hash }
+        Self {
+            inner,
+            hash,
+            count: 0,
+            started: None,
+            suffix: Vec::new(),
+            stats: WriteStats::new(),
+            tracking: Tracking::Untracked,
+            context: false,
+            order: UpdateOrder::AfterWrite,
+            name: None,
+            expected: None,
+        }
+    }
+
+    /// Creates new [`Writer`] with hash created by the factory.
+    ///
+    /// Useful for hashes configured with constructor parameters, like the output length or personalization.
+    pub fn with_hash_fn(inner: W, factory: impl FnOnce() -> H) -> Self {
+        let hash = factory();
+        Self::with_hash(inner, hash)
+    }
+
+    /// Creates new [`Writer`] with hash in keyed mode.
+    pub fn new_keyed(inner: W, key: &H::Key) -> Self
+    where
+        H: KeyedHash,
+    {
+        let hash = H::new_keyed(key);
+        Self::with_hash(inner, hash)
+    }
+
+    /// Creates new [`Writer`] with hash in key derivation mode for the given context string.
+    pub fn new_derive_key(inner: W, context: &str) -> Self
+    where
+        H: DeriveKeyHash,
+    {
+        let hash = H::new_derive_key(context);
+        Self::with_hash(inner, hash)
     }
 
     /// Unwraps this [`Writer`], returning the underlying writer.
@@ -140,45 +483,350 @@ where
         inner
     }
 
+    /// Flushes the writer and returns the final hash digest, including the suffix configured with [`Builder`].
+    ///
+    /// If the length set with [`Writer::with_expected_len`] differs from [`Writer::count`], the error wraps
+    /// [`Error::LengthMismatch`], so a truncated stream is detected even without an expected digest.
+    pub fn finalize(mut self) -> io::Result<H::Digest> {
+        self.flush()?;
+        if let Some(expected) = self.expected.filter(|&expected| expected != self.count) {
+            let actual = self.count;
+            return Err(Error::LengthMismatch { expected, actual }.into());
+        }
+        builder::fold(&mut self.hash, &mut self.suffix, self.count);
+        Ok(self.hash.digest())
+    }
+
+    /// Splits this [`Writer`] into a writing half and a [`DigestHandle`].
+    ///
+    /// The handle can be cloned and sent to other threads to query the byte count and a digest snapshot while the
+    /// writing half keeps exclusive access to the underlying writer. The state of the writer carries over to the
+    /// writing half, see [`SplitWriter`].
+    #[must_use]
+    pub fn split(self) -> (SplitWriter<W, H>, DigestHandle<H>) {
+        SplitWriter::new(self)
+    }
+
+    /// Wraps this [`Writer`] in a [`DigestGuard`].
+    #[must_use]
+    pub const fn guard(self) -> DigestGuard<W, H> {
+        DigestGuard::new(self)
+    }
+
+    /// Converts this [`Writer`] into a [`FilterWriter`] hashing only the writes accepted by the predicate.
+    pub fn filter<P>(self, predicate: P) -> FilterWriter<W, H, P>
+    where
+        P: FnMut(u64, &[u8]) -> bool,
+    {
+        FilterWriter::from_writer(self, predicate)
+    }
+
+    /// Wraps errors of the underlying writer in [`Error::Write`], adding the stream offset of the failed write.
+    ///
+    /// The wrapping error keeps the [`io::ErrorKind`] of the original one, which is available as its source, so retry
+    /// logic inspecting the kind, like on [`io::ErrorKind::Interrupted`], keeps working.
+    #[must_use]
+    pub const fn with_error_context(mut self) -> Self {
+        self.context = true;
+        self
+    }
+
+    /// Sets whether the hash is updated before or after the write to the underlying writer, see [`UpdateOrder`].
+    #[must_use]
+    pub const fn with_update_order(mut self, order: UpdateOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets expected length of the whole stream, including the resumed bytes.
+    ///
+    /// The length is checked by [`Writer::finalize`] and used by [`Writer::preallocate`] to reserve space in the
+    /// underlying writer.
+    #[must_use]
+    pub const fn with_expected_len(mut self, length: u64) -> Self {
+        self.expected = Some(length);
+        self
+    }
+
+    /// Names this [`Writer`], so concurrent writers are distinguishable in its reports and summaries.
+    #[must_use]
+    pub fn instrument(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Converts this [`Writer`] into an [`ExcludeWriter`] hashing the stream without the ranges excluded by the hook.
+    pub fn exclude<F>(self, hook: F) -> ExcludeWriter<W, H, F>
+    where
+        F: FnMut(u64, &[u8], &mut Exclusions) -> io::Result<()>,
+    {
+        ExcludeWriter::from_writer(self, hook)
+    }
+
+    /// Wraps this [`Writer`] in an [`AuditWriter`] recording every write into the sink.
+    #[must_use]
+    pub const fn audit<S>(self, sink: S) -> AuditWriter<W, H, S>
+    where
+        H::Digest: AsRef<[u8]>,
+        S: AuditSink,
+    {
+        AuditWriter::new(self, sink)
+    }
+
+    /// Wraps this [`Writer`] in a [`ProgressWriter`] reporting written bytes to the progress.
+    #[must_use]
+    pub const fn with_progress<P>(self, progress: P) -> ProgressWriter<W, H, P>
+    where
+        P: Progress,
+    {
+        ProgressWriter::new(self, progress)
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl<W, H> Writer<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    /// Wraps this [`Writer`] in a [`MetricsWriter`] recording its telemetry in the collector.
+    #[must_use]
+    pub fn with_metrics(self, collector: &Collector) -> MetricsWriter<W, H> {
+        MetricsWriter::new(self, collector)
+    }
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: Hash,
+{
+    /// Updates the hash and the statistics with the written bytes.
+    fn record(&mut self, data: &[u8]) {
+        self.hash.update(data);
+        self.count += data.len() as u64;
+        self.stats.record(data.len());
+        self.started.get_or_insert_with(SystemTime::now);
+        self.tracking.advance(data.len());
+    }
+
+    /// Returns number of bytes written so far.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
     /// Returns calculated hash digest.
+    ///
+    /// The suffix configured with [`Builder`] is not included, use [`Writer::finalize`] for the final digest.
     #[must_use]
     pub fn digest(&self) -> H::Digest {
         self.hash.digest()
     }
+
+    /// Returns calculated hash digest as a fixed-size array, or [`None`] if the digest length is not `N`.
+    ///
+    /// The array converts with [`From`] into types of other ecosystems, like `GenericArray` used by RustCrypto.
+    #[must_use]
+    pub fn digest_array<const N: usize>(&self) -> Option<[u8; N]>
+    where
+        H::Digest: AsRef<[u8]>,
+    {
+        self.digest().as_ref().try_into().ok()
+    }
+
+    /// Returns statistics of write calls.
+    #[must_use]
+    pub const fn stats(&self) -> &WriteStats {
+        &self.stats
+    }
+
+    /// Returns structured summary of the hashing result.
+    ///
+    /// Like [`Writer::digest`], the suffix configured with [`Builder`] is not included.
+    #[must_use]
+    pub fn report(&self) -> DigestReport {
+        let report = DigestReport::new::<H>(self.hash.digest(), self.count, self.started);
+        DigestReport {
+            name: self.name.clone(),
+            ..report
+        }
+    }
+
+    /// Returns one-line summary of the digest, length and throughput, see [`DigestReport::summary`].
+    #[must_use]
+    pub fn summary(&self) -> String {
+        self.report().summary()
+    }
+
+    /// Returns `false` if some write did not land at the end of the hashed stream after seeking.
+    ///
+    /// In that case the digest no longer matches the contents of the underlying writer.
+    #[must_use]
+    pub const fn is_contiguous(&self) -> bool {
+        self.tracking.is_contiguous()
+    }
 }
 
-impl<W, H> Write for Writer<W, H>
+impl<W, H> Default for Writer<W, H>
+where
+    W: Write + Default,
+    H: Hash,
+{
+    fn default() -> Self {
+        Self::new(W::default())
+    }
+}
+
+impl<W, H> From<W> for Writer<W, H>
 where
     W: Write,
     H: Hash,
+{
+    fn from(inner: W) -> Self {
+        Self::new(inner)
+    }
+}
+
+impl<W, H> Write for Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: Hash,
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let n = self.inner.write(buf)?;
-        self.hash.update(&buf[..n]);
-        Ok(n)
+        let offset = self.count;
+        let result = match self.order {
+            UpdateOrder::AfterWrite => self.inner.write(buf),
+            UpdateOrder::BeforeWrite => {
+                self.record(buf);
+                self.inner.write_all(buf).map(|()| buf.len())
+            },
+        };
+        match result {
+            Ok(n) => {
+                if self.order == UpdateOrder::AfterWrite {
+                    self.record(&buf[..n]);
+                }
+                Ok(n)
+            },
+            Err(error) if self.context => Err(Error::write(offset, error).into()),
+            Err(error) => Err(error),
+        }
     }
 
     fn flush(&mut self) -> io::Result<()> {
         self.inner.flush()
     }
+
+    /// Writes formatted arguments through a stack buffer, so a `write!` call results in as few writes to the
+    /// underlying writer as possible and allocates nothing.
+    fn write_fmt(&mut self, arguments: fmt::Arguments<'_>) -> io::Result<()> {
+        format::write_fmt(self, arguments)
+    }
 }
 
-/// Wraps a reader and calculates the hash digest on the fly.
-#[cfg(feature = "async-runtime-tokio")]
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct AsyncWriter<W, H>
+/// Writes bytes from the iterator in batches.
+///
+/// # Panics
+///
+/// Panics if the underlying writer returns an error, since [`Extend`] provides no way to report it.
+impl<W, H> Extend<u8> for Writer<W, H>
 where
-    W: AsyncWriteExt,
+    W: Write + ?Sized,
     H: Hash,
 {
-    inner: W,
-    hash: H,
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = u8>,
+    {
+        const BATCH: usize = 512;
+
+        let mut iter = iter.into_iter();
+        let mut batch = [0; BATCH];
+        loop {
+            let length = batch.iter_mut().zip(&mut iter).fold(0, |length, (slot, byte)| {
+                *slot = byte;
+                length + 1
+            });
+            if length == 0 {
+                break;
+            }
+            self.write_all(&batch[..length])
+                .expect("writing to the underlying writer should succeed");
+            if length < BATCH {
+                break;
+            }
+        }
+    }
+}
+
+/// Writes bytes from the iterator in batches.
+///
+/// # Panics
+///
+/// Panics if the underlying writer returns an error, since [`Extend`] provides no way to report it.
+impl<'a, W, H> Extend<&'a u8> for Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: Hash,
+{
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = &'a u8>,
+    {
+        self.extend(iter.into_iter().copied());
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+pin_project! {
+    /// Wraps a reader and calculates the hash digest on the fly.
+    ///
+    /// # Cancel safety
+    ///
+    /// The hash is updated only with the bytes the underlying writer reported as accepted, within the same poll, so if
+    /// a future like [`AsyncWriteExt::write_all`] is dropped midway, the digest still reflects exactly the bytes
+    /// accepted by the underlying writer. Use [`AsyncWriter::count`] to find out how many there were.
+    ///
+    /// With [`UpdateOrder::BeforeWrite`], the digest runs ahead of the underlying writer by the bytes reported by
+    /// [`AsyncWriter::staged_len`] instead.
+    ///
+    /// # Lending
+    ///
+    /// Like any [`Unpin`] writer, `&mut AsyncWriter` and `Box<AsyncWriter>` implement [`AsyncWrite`] too, so the
+    /// writer can be lent to functions like [`tokio::io::copy`] and queried for the digest afterwards.
+    ///
+    /// # Pinning
+    ///
+    /// The underlying writer is structurally pinned, so it does not have to be [`Unpin`]. A writer wrapping a `!Unpin`
+    /// stream is used through [`Box::pin`] or [`std::pin::pin!`], and [`AsyncWriter::get_pin_mut`] reaches the pinned
+    /// stream.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct AsyncWriter<W, H>
+    where
+        W: AsyncWrite,
+        H: Hash,
+    {
+        #[pin]
+        inner: W,
+        hash: H,
+        count: u64,
+        started: Option<SystemTime>,
+        suffix: Vec<Suffix>,
+        stats: WriteStats,
+        tracking: Tracking,
+        context: bool,
+        order: UpdateOrder,
+        pending: Pending,
+        name: Option<String>,
+        seal: Seal,
+    }
 }
 
 #[cfg(feature = "async-runtime-tokio")]
 impl<W, H> AsyncWriter<W, H>
 where
-    W: AsyncWriteExt,
+    W: AsyncWrite,
     H: Hash,
 {
     /// Creates new [`AsyncWriter`].
@@ -190,7 +838,46 @@ where
     /// Creates new [`AsyncWriter`] with provided hash.
     #[must_use]
     pub const fn with_hash(inner: W, hash: H) -> Self {
-        Self { inner, hash }
+        Self {
+            inner,
+            hash,
+            count: 0,
+            started: None,
+            suffix: Vec::new(),
+            stats: WriteStats::new(),
+            tracking: Tracking::Untracked,
+            context: false,
+            order: UpdateOrder::AfterWrite,
+            pending: Pending::new(),
+            name: None,
+            seal: Seal::Open,
+        }
+    }
+
+    /// Creates new [`AsyncWriter`] with hash created by the factory.
+    ///
+    /// Useful for hashes configured with constructor parameters, like the output length or personalization.
+    pub fn with_hash_fn(inner: W, factory: impl FnOnce() -> H) -> Self {
+        let hash = factory();
+        Self::with_hash(inner, hash)
+    }
+
+    /// Creates new [`AsyncWriter`] with hash in keyed mode.
+    pub fn new_keyed(inner: W, key: &H::Key) -> Self
+    where
+        H: KeyedHash,
+    {
+        let hash = H::new_keyed(key);
+        Self::with_hash(inner, hash)
+    }
+
+    /// Creates new [`AsyncWriter`] with hash in key derivation mode for the given context string.
+    pub fn new_derive_key(inner: W, context: &str) -> Self
+    where
+        H: DeriveKeyHash,
+    {
+        let hash = H::new_derive_key(context);
+        Self::with_hash(inner, hash)
     }
 
     /// Unwraps this [`AsyncWriter`], returning the underlying writer.
@@ -200,37 +887,272 @@ where
         inner
     }
 
+    /// Returns reference to the underlying writer.
+    #[must_use]
+    pub const fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns mutable reference to the underlying writer.
+    ///
+    /// Writing through the reference bypasses the hash, so the digest no longer matches the written data.
+    #[must_use]
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Returns pinned mutable reference to the underlying writer.
+    ///
+    /// Like [`AsyncWriter::get_mut`], writing through the reference bypasses the hash.
+    #[must_use]
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut W> {
+        self.project().inner
+    }
+
+    /// Returns number of bytes written so far.
+    #[must_use]
+    pub const fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns number of bytes hashed but not accepted by the underlying writer yet.
+    ///
+    /// Only [`UpdateOrder::BeforeWrite`] hashes bytes ahead of the underlying writer, they are staged until a retried
+    /// write with the same data delivers them. With the default [`UpdateOrder::AfterWrite`] it is always zero.
+    #[must_use]
+    pub const fn staged_len(&self) -> u64 {
+        self.pending.get()
+    }
+
     /// Returns calculated hash digest.
+    ///
+    /// The suffix configured with [`Builder`] is only included once the writer is [sealed](AsyncWriter::sealed), use
+    /// [`AsyncWriter::finalize`] for the final digest without shutting down.
     #[must_use]
     pub fn digest(&self) -> H::Digest {
         self.hash.digest()
     }
+
+    /// Returns calculated hash digest as a fixed-size array, or [`None`] if the digest length is not `N`.
+    ///
+    /// The array converts with [`From`] into types of other ecosystems, like `GenericArray` used by RustCrypto.
+    #[must_use]
+    pub fn digest_array<const N: usize>(&self) -> Option<[u8; N]>
+    where
+        H::Digest: AsRef<[u8]>,
+    {
+        self.digest().as_ref().try_into().ok()
+    }
+
+    /// Returns statistics of write calls.
+    #[must_use]
+    pub const fn stats(&self) -> &WriteStats {
+        &self.stats
+    }
+
+    /// Returns `true` once the writer was shut down and the digest is final.
+    ///
+    /// After sealing, [`AsyncWriter::digest`] includes the suffix configured with [`Builder`] and never changes.
+    #[must_use]
+    pub const fn sealed(&self) -> bool {
+        matches!(self.seal, Seal::Sealed)
+    }
+
+    /// Returns structured summary of the hashing result.
+    ///
+    /// Like [`AsyncWriter::digest`], the suffix configured with [`Builder`] is only included once the writer is
+    /// [sealed](AsyncWriter::sealed).
+    #[must_use]
+    pub fn report(&self) -> DigestReport {
+        let report = DigestReport::new::<H>(self.hash.digest(), self.count, self.started);
+        DigestReport {
+            name: self.name.clone(),
+            ..report
+        }
+    }
+
+    /// Returns one-line summary of the digest, length and throughput, see [`DigestReport::summary`].
+    #[must_use]
+    pub fn summary(&self) -> String {
+        self.report().summary()
+    }
+
+    /// Flushes the writer and returns the final hash digest, including the suffix configured with [`Builder`].
+    pub async fn finalize(mut self) -> io::Result<H::Digest>
+    where
+        W: Unpin,
+        H: Unpin,
+    {
+        self.flush().await?;
+        self.fold_suffix();
+        Ok(self.hash.digest())
+    }
+
+    /// Folds the suffix configured with [`Builder`] into the hash.
+    pub(crate) fn fold_suffix(&mut self) {
+        builder::fold(&mut self.hash, &mut self.suffix, self.count);
+    }
+
+    /// Returns `false` if some write did not land at the end of the hashed stream after seeking.
+    ///
+    /// In that case the digest no longer matches the contents of the underlying writer.
+    #[must_use]
+    pub const fn is_contiguous(&self) -> bool {
+        self.tracking.is_contiguous()
+    }
+
+    /// Splits this [`AsyncWriter`] into a writing half and a [`DigestHandle`].
+    ///
+    /// The writing half can be lent to [`tokio::io::copy`] while the handle, possibly in another task, queries the
+    /// byte count and a digest snapshot mid-transfer. The state of the writer carries over to the writing half, so a
+    /// sealed writer stays sealed, see [`AsyncSplitWriter`].
+    #[must_use]
+    pub fn split(self) -> (AsyncSplitWriter<W, H>, DigestHandle<H>) {
+        AsyncSplitWriter::new(self)
+    }
+
+    /// Wraps this [`AsyncWriter`] in an [`AsyncDigestGuard`].
+    #[must_use]
+    pub const fn guard(self) -> AsyncDigestGuard<W, H> {
+        AsyncDigestGuard::new(self)
+    }
+
+    /// Wraps errors of the underlying writer in [`Error::Write`], adding the stream offset of the failed write.
+    ///
+    /// See [`Writer::with_error_context`] for details.
+    #[must_use]
+    pub const fn with_error_context(mut self) -> Self {
+        self.context = true;
+        self
+    }
+
+    /// Sets whether the hash is updated before or after the write to the underlying writer, see [`UpdateOrder`].
+    #[must_use]
+    pub const fn with_update_order(mut self, order: UpdateOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Names this [`AsyncWriter`], so concurrent writers are distinguishable in its reports and summaries.
+    #[must_use]
+    pub fn instrument(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Converts this [`AsyncWriter`] into an [`AsyncFilterWriter`] hashing only the writes accepted by the predicate.
+    pub fn filter<P>(self, predicate: P) -> AsyncFilterWriter<W, H, P>
+    where
+        P: FnMut(u64, &[u8]) -> bool,
+    {
+        AsyncFilterWriter::from_writer(self, predicate)
+    }
+
+    /// Wraps this [`AsyncWriter`] in an [`AsyncAuditWriter`] recording every write into the sink.
+    #[must_use]
+    pub const fn audit<S>(self, sink: S) -> AsyncAuditWriter<W, H, S>
+    where
+        H::Digest: AsRef<[u8]>,
+        S: AuditSink,
+    {
+        AsyncAuditWriter::new(self, sink)
+    }
+
+    /// Wraps this [`AsyncWriter`] in an [`AsyncProgressWriter`] reporting written bytes to the progress.
+    #[must_use]
+    pub const fn with_progress<P>(self, progress: P) -> AsyncProgressWriter<W, H, P>
+    where
+        P: Progress,
+    {
+        AsyncProgressWriter::new(self, progress)
+    }
+
+    /// Wraps this [`AsyncWriter`] in an [`AsyncEventWriter`] publishing the digest once the writer is shut down.
+    #[must_use]
+    pub const fn with_events(self, sender: broadcast::Sender<DigestEvent<H::Digest>>) -> AsyncEventWriter<W, H> {
+        AsyncEventWriter::new(self, sender)
+    }
+}
+
+#[cfg(all(feature = "async-runtime-tokio", feature = "metrics"))]
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Algorithm,
+{
+    /// Wraps this [`AsyncWriter`] in an [`AsyncMetricsWriter`] recording its telemetry in the collector.
+    #[must_use]
+    pub fn with_metrics(self, collector: &Collector) -> AsyncMetricsWriter<W, H> {
+        AsyncMetricsWriter::new(self, collector)
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> Default for AsyncWriter<W, H>
+where
+    W: AsyncWrite + Default,
+    H: Hash,
+{
+    fn default() -> Self {
+        Self::new(W::default())
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> From<W> for AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    fn from(inner: W) -> Self {
+        Self::new(inner)
+    }
 }
 
 #[cfg(feature = "async-runtime-tokio")]
 impl<W, H> AsyncWrite for AsyncWriter<W, H>
 where
-    W: AsyncWrite + Unpin,
-    H: Hash + Unpin,
+    W: AsyncWrite,
+    H: Hash,
 {
     fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
-        let Self { inner, hash } = self.get_mut();
-        match pin!(inner).poll_write(cx, buf) {
-            Poll::Ready(Ok(n)) => {
-                hash.update(&buf[..n]);
-                Poll::Ready(Ok(n))
-            },
+        let this = self.project();
+        if *this.seal != Seal::Open {
+            let error = io::Error::from(Error::Finalized);
+            return Poll::Ready(Err(error));
+        }
+        let offset = *this.count - this.pending.get();
+        let poll = this.inner.poll_write(cx, buf);
+        if let Some(data) = this.order.hashed(this.pending, buf, &poll) {
+            this.hash.update(data);
+            *this.count += data.len() as u64;
+            this.stats.record(data.len());
+            this.started.get_or_insert_with(SystemTime::now);
+            this.tracking.advance(data.len());
+        }
+        match poll {
+            Poll::Ready(Err(error)) if *this.context => Poll::Ready(Err(Error::write(offset, error).into())),
             poll => poll,
         }
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        let Self { inner, .. } = self.get_mut();
-        pin!(inner).poll_flush(cx)
+        self.project().inner.poll_flush(cx)
     }
 
+    /// Shuts down the underlying writer and seals the digest, folding in the suffix configured with [`Builder`].
+    ///
+    /// Writes are rejected once shutdown started, and shutting down a sealed writer does nothing.
     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        let Self { inner, .. } = self.get_mut();
-        pin!(inner).poll_shutdown(cx)
+        let this = self.project();
+        match this.seal {
+            Seal::Sealed => return Poll::Ready(Ok(())),
+            Seal::Open => *this.seal = Seal::Sealing,
+            Seal::Sealing => {},
+        }
+        ready!(this.inner.poll_shutdown(cx))?;
+        builder::fold(this.hash, this.suffix, *this.count);
+        *this.seal = Seal::Sealed;
+        Poll::Ready(Ok(()))
     }
 }
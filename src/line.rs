@@ -0,0 +1,89 @@
+use std::io::{self, LineWriter, Write};
+
+use chksum_core::Hash;
+
+use crate::error::IntoInnerError;
+
+/// Wraps a writer with [`LineWriter`] semantics, delivering complete lines, and calculates the hash digest of the
+/// whole stream on the fly.
+///
+/// Data up to the last newline of every write is passed to the underlying writer right away, while the rest stays
+/// buffered until the next newline, a flush or [`LineDigestWriter::finish`]. The digest covers all written bytes,
+/// including a buffered tail without newline.
+#[derive(Debug)]
+pub struct LineDigestWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: LineWriter<W>,
+    hash: H,
+}
+
+impl<W, H> LineDigestWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`LineDigestWriter`].
+    pub fn new(inner: W) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash)
+    }
+
+    /// Creates new [`LineDigestWriter`] with provided hash.
+    pub fn with_hash(inner: W, hash: H) -> Self {
+        let inner = LineWriter::new(inner);
+        Self { inner, hash }
+    }
+
+    /// Creates new [`LineDigestWriter`] with at least the specified buffer capacity.
+    pub fn with_capacity(inner: W, capacity: usize) -> Self {
+        let inner = LineWriter::with_capacity(capacity, inner);
+        let hash = H::default();
+        Self { inner, hash }
+    }
+
+    /// Returns reference to the underlying writer.
+    #[must_use]
+    pub fn get_ref(&self) -> &W {
+        self.inner.get_ref()
+    }
+
+    /// Returns calculated hash digest, including bytes not yet passed to the underlying writer.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+
+    /// Writes the buffered tail and returns the underlying writer with calculated hash digest.
+    ///
+    /// On failure the writer is returned within the error, with unwritten data still buffered, so finishing can be
+    /// retried.
+    pub fn finish(self) -> Result<(W, H::Digest), IntoInnerError<Self>> {
+        let Self { inner, hash } = self;
+        match inner.into_inner() {
+            Ok(inner) => Ok((inner, hash.digest())),
+            Err(error) => {
+                let (error, inner) = error.into_parts();
+                Err(IntoInnerError::new(Self { inner, hash }, error))
+            },
+        }
+    }
+}
+
+impl<W, H> Write for LineDigestWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hash.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
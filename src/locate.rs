@@ -0,0 +1,283 @@
+use std::io::{self, Read, Write};
+use std::num::NonZeroU64;
+use std::ops::Range;
+
+use chksum_core::Hash;
+
+use crate::HashListWriter;
+
+/// Magic bytes starting a binary block map.
+const MAGIC: &[u8; 8] = b"CHKSBMAP";
+/// Version of the binary block map format.
+const VERSION: u16 = 1;
+
+/// Chunk digests of a stream together with their layout, used to locate corrupted byte ranges.
+///
+/// Created by [`HashListWriter::block_manifest`], and exported to and imported from a binary block map with
+/// [`BlockManifest::write_to`] and [`BlockManifest::read_from`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockManifest<D> {
+    /// Size of the chunks.
+    pub chunk_size: NonZeroU64,
+    /// Length of the first chunk, shorter than the chunk size if the chunks are aligned to storage blocks.
+    pub first: u64,
+    /// Number of bytes of the stream.
+    pub length: u64,
+    /// Digests of all chunks.
+    pub digests: Vec<D>,
+}
+
+impl<D> BlockManifest<D>
+where
+    D: PartialEq,
+{
+    /// Returns byte range of the chunk with the given index.
+    ///
+    /// Chunks past the end of the stream, including those whose offset does not fit in 64 bits, have an empty range at
+    /// the end of the stream.
+    #[must_use]
+    pub fn range(&self, index: usize) -> Range<u64> {
+        match self.bounds(index as u64) {
+            Some(range) => range.start.min(self.length)..range.end.min(self.length),
+            None => self.length..self.length,
+        }
+    }
+
+    /// Returns unclamped byte range of the chunk with the given index, or `None` if it does not fit in 64 bits.
+    fn bounds(&self, index: u64) -> Option<Range<u64>> {
+        if index == 0 {
+            return Some(0..self.first);
+        }
+        let start = (index - 1)
+            .checked_mul(self.chunk_size.get())?
+            .checked_add(self.first)?;
+        let end = start.checked_add(self.chunk_size.get())?;
+        Some(start..end)
+    }
+
+    /// Compares this reference manifest with the manifest of the actual data, returning corrupted byte ranges.
+    ///
+    /// Adjacent corrupted chunks are merged into a single range, and length difference is reported as corrupted as
+    /// well. Returns [`None`] if the chunk layouts differ, so the manifests cannot be compared.
+    #[must_use]
+    pub fn locate(&self, actual: &Self) -> Option<Vec<Range<u64>>> {
+        if self.chunk_size != actual.chunk_size || self.first != actual.first {
+            return None;
+        }
+        let mut corrupted: Vec<Range<u64>> = Vec::new();
+        let chunks = self.digests.len().max(actual.digests.len());
+        for index in 0..chunks {
+            if self.digests.get(index) == actual.digests.get(index) {
+                continue;
+            }
+            let range = self.range(index);
+            let other = actual.range(index);
+            let range = range.start.min(other.start)..range.end.max(other.end);
+            match corrupted.last_mut() {
+                Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+                _ => corrupted.push(range),
+            }
+        }
+        Some(corrupted)
+    }
+}
+
+impl<D> BlockManifest<D>
+where
+    D: AsRef<[u8]> + PartialEq,
+{
+    /// Writes this manifest as a binary block map.
+    ///
+    /// The map starts with a header of the magic bytes `CHKSBMAP`, the format version, the digest size, the chunk size,
+    /// the length of the first chunk, the length of the stream and the number of chunks. The header is followed by an
+    /// entry of the offset, the size and the digest for every chunk. Integers are big-endian, the version and the
+    /// digest size take 2 bytes and the rest 8 bytes.
+    ///
+    /// Digests of different sizes result in an error of kind [`io::ErrorKind::InvalidInput`].
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        let digest_size = self.digests.first().map_or(0, |digest| digest.as_ref().len());
+        if self.digests.iter().any(|digest| digest.as_ref().len() != digest_size) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "digests differ in size"));
+        }
+        let digest_size = u16::try_from(digest_size)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "digest too large for block map"))?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_be_bytes())?;
+        writer.write_all(&digest_size.to_be_bytes())?;
+        writer.write_all(&self.chunk_size.get().to_be_bytes())?;
+        writer.write_all(&self.first.to_be_bytes())?;
+        writer.write_all(&self.length.to_be_bytes())?;
+        writer.write_all(&(self.digests.len() as u64).to_be_bytes())?;
+        for (index, digest) in self.digests.iter().enumerate() {
+            let range = self.range(index);
+            writer.write_all(&range.start.to_be_bytes())?;
+            writer.write_all(&(range.end - range.start).to_be_bytes())?;
+            writer.write_all(digest.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Hashes the data read from the reader and compares it with this manifest, returning corrupted byte ranges.
+    ///
+    /// See [`BlockManifest::locate`] for details.
+    pub fn verify<H>(&self, mut reader: impl Read) -> io::Result<Vec<Range<u64>>>
+    where
+        H: Hash,
+        H::Digest: AsRef<[u8]> + Clone,
+    {
+        let mut writer = HashListWriter::<_, H>::with_layout(io::sink(), self.chunk_size, self.first);
+        io::copy(&mut reader, &mut writer)?;
+        let actual = writer.block_manifest();
+        let actual = BlockManifest {
+            chunk_size: actual.chunk_size,
+            first: actual.first,
+            length: actual.length,
+            digests: actual.digests.iter().map(|digest| digest.as_ref().to_vec()).collect(),
+        };
+        let expected = BlockManifest {
+            chunk_size: self.chunk_size,
+            first: self.first,
+            length: self.length,
+            digests: self.digests.iter().map(|digest| digest.as_ref().to_vec()).collect(),
+        };
+        Ok(expected.locate(&actual).unwrap_or_default())
+    }
+}
+
+impl BlockManifest<Vec<u8>> {
+    /// Reads manifest from a binary block map, see [`BlockManifest::write_to`] for the format.
+    ///
+    /// Unknown magic bytes or version and inconsistent entries result in an error of kind
+    /// [`io::ErrorKind::InvalidData`].
+    pub fn read_from(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a block map"));
+        }
+        if read_u16(&mut reader)? != VERSION {
+            return Err(invalid("unsupported block map version"));
+        }
+        let digest_size = read_u16(&mut reader)?;
+        let chunk_size = NonZeroU64::new(read_u64(&mut reader)?).ok_or_else(|| invalid("zero chunk size"))?;
+        let first = read_u64(&mut reader)?;
+        let length = read_u64(&mut reader)?;
+        let count = read_u64(&mut reader)?;
+        let mut manifest = Self {
+            chunk_size,
+            first,
+            length,
+            digests: Vec::new(),
+        };
+        let mut end = 0;
+        for index in 0..count {
+            let range = manifest
+                .bounds(index)
+                .ok_or_else(|| invalid("block map entry out of range"))?;
+            let range = range.start.min(length)..range.end.min(length);
+            let (offset, size) = (read_u64(&mut reader)?, read_u64(&mut reader)?);
+            if offset != range.start || size != range.end - range.start || size == 0 {
+                return Err(invalid("block map entry does not match layout"));
+            }
+            let mut digest = vec![0; usize::from(digest_size)];
+            reader.read_exact(&mut digest)?;
+            manifest.digests.push(digest);
+            end = range.end;
+        }
+        if end != length {
+            return Err(invalid("block map entries do not cover the stream"));
+        }
+        Ok(manifest)
+    }
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_be_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::num::NonZeroU64;
+    use std::ops::Range;
+
+    use chksum_core::Hash;
+
+    use super::{BlockManifest, MAGIC, VERSION};
+    use crate::testing::Sha256;
+    use crate::HashListWriter;
+
+    const DATA: &[u8] = b"abcdefghij";
+
+    fn manifest() -> BlockManifest<Vec<u8>> {
+        let mut writer = HashListWriter::<_, Sha256>::new(io::sink(), NonZeroU64::new(4).unwrap());
+        writer.write_all(DATA).unwrap();
+        let manifest = writer.block_manifest();
+        BlockManifest {
+            chunk_size: manifest.chunk_size,
+            first: manifest.first,
+            length: manifest.length,
+            digests: manifest.digests.iter().map(|digest| digest.as_ref().to_vec()).collect(),
+        }
+    }
+
+    #[test]
+    fn chunk_digests() {
+        let manifest = manifest();
+        assert_eq!(manifest.length, DATA.len() as u64);
+        let expected: Vec<_> = DATA
+            .chunks(4)
+            .map(|chunk| Sha256::hash(chunk).as_ref().to_vec())
+            .collect();
+        assert_eq!(manifest.digests, expected);
+        let ranges: Vec<_> = (0..3).map(|index| manifest.range(index)).collect();
+        assert_eq!(ranges, [0..4, 4..8, 8..10]);
+        assert_eq!(manifest.range(usize::MAX), 10..10);
+    }
+
+    #[test]
+    fn block_map_round_trip() {
+        let manifest = manifest();
+        let mut map = Vec::new();
+        manifest.write_to(&mut map).unwrap();
+        assert_eq!(&map[..8], MAGIC);
+        assert_eq!(map.len(), 8 + 2 + 2 + 4 * 8 + 3 * (8 + 8 + 32));
+        assert_eq!(BlockManifest::read_from(&map[..]).unwrap(), manifest);
+    }
+
+    #[test]
+    fn locates_corrupted_chunks() {
+        let manifest = manifest();
+        assert_eq!(manifest.verify::<Sha256>(DATA).unwrap(), []);
+        assert_eq!(
+            manifest.verify::<Sha256>(&b"abcdXfghij"[..]).unwrap(),
+            [Range { start: 4, end: 8 }]
+        );
+        assert_eq!(manifest.verify::<Sha256>(&b"Xbcdefghi"[..]).unwrap(), [0..4, 8..10]);
+    }
+
+    #[test]
+    fn rejects_entries_out_of_range() {
+        let mut map = MAGIC.to_vec();
+        map.extend_from_slice(&VERSION.to_be_bytes());
+        map.extend_from_slice(&0_u16.to_be_bytes());
+        for value in [u64::MAX, 1, u64::MAX, 3] {
+            map.extend_from_slice(&value.to_be_bytes());
+        }
+        // the first chunk is a single byte, the end of the second one overflows
+        map.extend_from_slice(&0_u64.to_be_bytes());
+        map.extend_from_slice(&1_u64.to_be_bytes());
+        map.extend_from_slice(&[0; 16]);
+        let error = BlockManifest::read_from(&map[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,289 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::escape::{escape, unescape};
+
+/// Line format of a checksum manifest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// GNU coreutils format, like `sha256sum`, e.g. `<digest>  <path>`.
+    Gnu,
+    /// BSD format, like `sha256sum --tag`, e.g. `SHA256 (<path>) = <digest>`.
+    Bsd,
+    /// Simple File Verification format with CRC32 digests, e.g. `<path> <digest>`.
+    Sfv,
+}
+
+/// Single entry of a checksum manifest.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Path of the file.
+    pub path: PathBuf,
+    /// Hash digest as hexadecimal string.
+    pub digest: String,
+    /// Name of the hash algorithm, if known.
+    pub algorithm: Option<String>,
+}
+
+impl ManifestEntry {
+    /// Creates new [`ManifestEntry`].
+    pub fn new(path: impl Into<PathBuf>, digest: impl Display) -> Self {
+        Self {
+            path: path.into(),
+            digest: digest.to_string(),
+            algorithm: None,
+        }
+    }
+
+    /// Sets name of the hash algorithm.
+    #[must_use]
+    pub fn with_algorithm(mut self, algorithm: impl Into<String>) -> Self {
+        self.algorithm = Some(algorithm.into());
+        self
+    }
+
+    /// Writes the entry as a single line in the given format.
+    ///
+    /// The [`Format::Bsd`] format requires the algorithm to be set, otherwise an error of kind
+    /// [`io::ErrorKind::InvalidInput`] is returned.
+    ///
+    /// The [`Format::Sfv`] format does not support escaping, paths with line breaks result in an error of kind
+    /// [`io::ErrorKind::InvalidInput`].
+    pub fn write_to(&self, mut writer: impl Write, format: Format) -> io::Result<()> {
+        let path = self.path.to_string_lossy();
+        match format {
+            Format::Gnu => {
+                let (escape, path) = escape(&path);
+                writeln!(writer, "{escape}{}  {path}", self.digest)
+            },
+            Format::Bsd => {
+                let algorithm = self
+                    .algorithm
+                    .as_deref()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "BSD format requires algorithm name"))?;
+                let (escape, path) = escape(&path);
+                writeln!(writer, "{escape}{algorithm} ({path}) = {}", self.digest)
+            },
+            Format::Sfv => {
+                if path.contains(['\n', '\r']) {
+                    let error = io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "SFV format does not support line breaks in paths",
+                    );
+                    return Err(error);
+                }
+                writeln!(writer, "{path} {}", self.digest.to_ascii_uppercase())
+            },
+        }
+    }
+}
+
+/// Parses checksum manifest in GNU or BSD format, which may be mixed.
+///
+/// Empty lines and lines starting with `#` are skipped. Malformed lines result in an error of kind
+/// [`io::ErrorKind::InvalidData`].
+pub fn parse_manifest(reader: impl BufRead) -> io::Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = parse_line(line).ok_or_else(|| {
+            let message = format!("malformed manifest line {}", number + 1);
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Parses manifest in Simple File Verification format.
+///
+/// Empty lines and comment lines starting with `;` are skipped. Malformed lines result in an error of kind
+/// [`io::ErrorKind::InvalidData`].
+pub fn parse_sfv(reader: impl BufRead) -> io::Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if line.trim().is_empty() || line.starts_with(';') {
+            continue;
+        }
+        let entry = parse_sfv_line(line).ok_or_else(|| {
+            let message = format!("malformed SFV line {}", number + 1);
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        })?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn parse_sfv_line(line: &str) -> Option<ManifestEntry> {
+    let (path, digest) = line.trim_end().rsplit_once(' ')?;
+    let path = path.trim_end();
+    (digest.len() == 8 && is_hex(digest) && !path.is_empty())
+        .then(|| ManifestEntry::new(path, digest).with_algorithm("CRC32"))
+}
+
+/// Writes comment line, like a header, in [`Format::Sfv`] format.
+pub fn write_sfv_comment(mut writer: impl Write, comment: &str) -> io::Result<()> {
+    for line in comment.lines() {
+        writeln!(writer, "; {line}")?;
+    }
+    Ok(())
+}
+
+fn parse_line(line: &str) -> Option<ManifestEntry> {
+    let (line, escaped) = match line.strip_prefix('\\') {
+        Some(line) => (line, true),
+        None => (line, false),
+    };
+    let (path, digest, algorithm) = parse_bsd(line).or_else(|| parse_gnu(line))?;
+    let path = if escaped { unescape(path)? } else { path.to_owned() };
+    Some(ManifestEntry {
+        path: PathBuf::from(path),
+        digest: digest.to_owned(),
+        algorithm: algorithm.map(ToOwned::to_owned),
+    })
+}
+
+fn parse_gnu(line: &str) -> Option<(&str, &str, Option<&str>)> {
+    let (digest, path) = line.split_once(' ')?;
+    // the second separator is a space in text mode and an asterisk in binary mode
+    let path = path.strip_prefix([' ', '*']).unwrap_or(path);
+    (is_hex(digest) && !path.is_empty()).then_some((path, digest, None))
+}
+
+fn parse_bsd(line: &str) -> Option<(&str, &str, Option<&str>)> {
+    let (algorithm, rest) = line.split_once(" (")?;
+    let (path, digest) = rest.rsplit_once(") = ")?;
+    (is_hex(digest) && !algorithm.is_empty() && !path.is_empty()).then_some((path, digest, Some(algorithm)))
+}
+
+fn is_hex(digest: &str) -> bool {
+    !digest.is_empty() && digest.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// Writes checksum manifest in the given format.
+pub fn write_manifest<'a>(
+    mut writer: impl Write,
+    entries: impl IntoIterator<Item = &'a ManifestEntry>,
+    format: Format,
+) -> io::Result<()> {
+    for entry in entries {
+        entry.write_to(&mut writer, format)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor};
+
+    use super::{parse_manifest, parse_sfv, write_manifest, write_sfv_comment, Format, ManifestEntry};
+
+    const DIGEST: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn entries() -> Vec<ManifestEntry> {
+        ["empty", "with space", "back\\slash", "line\nbreak"]
+            .into_iter()
+            .map(|path| ManifestEntry::new(path, DIGEST).with_algorithm("SHA256"))
+            .collect()
+    }
+
+    fn written(entries: &[ManifestEntry], format: Format) -> String {
+        let mut manifest = Vec::new();
+        write_manifest(&mut manifest, entries, format).unwrap();
+        String::from_utf8(manifest).unwrap()
+    }
+
+    #[test]
+    fn coreutils_lines() {
+        let gnu = written(&entries(), Format::Gnu);
+        assert_eq!(
+            gnu.lines().collect::<Vec<_>>(),
+            [
+                format!("{DIGEST}  empty"),
+                format!("{DIGEST}  with space"),
+                format!("\\{DIGEST}  back\\\\slash"),
+                format!("\\{DIGEST}  line\\nbreak"),
+            ]
+        );
+        let bsd = written(&entries()[..1], Format::Bsd);
+        assert_eq!(bsd, format!("SHA256 (empty) = {DIGEST}\n"));
+        let error = ManifestEntry::new("empty", DIGEST)
+            .write_to(io::sink(), Format::Bsd)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn round_trip() {
+        let gnu = parse_manifest(Cursor::new(written(&entries(), Format::Gnu))).unwrap();
+        let without_algorithm: Vec<_> = entries()
+            .into_iter()
+            .map(|entry| ManifestEntry::new(entry.path, entry.digest))
+            .collect();
+        assert_eq!(gnu, without_algorithm);
+        let bsd = parse_manifest(Cursor::new(written(&entries(), Format::Bsd))).unwrap();
+        assert_eq!(bsd, entries());
+    }
+
+    #[test]
+    fn mixed_and_binary_lines() {
+        let manifest = format!("# comment\r\n\n{DIGEST} *binary\r\nSHA256 (a ) = b) = {DIGEST}\n");
+        let entries = parse_manifest(Cursor::new(manifest)).unwrap();
+        assert_eq!(
+            entries,
+            [
+                ManifestEntry::new("binary", DIGEST),
+                ManifestEntry::new("a ) = b", DIGEST).with_algorithm("SHA256"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_line() {
+        for manifest in [
+            "digest  path\n",
+            &format!("{DIGEST}\n"),
+            &format!("ok\n\\{DIGEST}  bad\\escape\n"),
+        ] {
+            let error = parse_manifest(Cursor::new(manifest)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{manifest:?}");
+        }
+        let error = parse_manifest(Cursor::new(format!("{DIGEST}  a\n\nx\n"))).unwrap_err();
+        assert_eq!(error.to_string(), "malformed manifest line 3");
+    }
+
+    #[test]
+    fn sfv() {
+        let entries = [
+            ManifestEntry::new("file one.txt", "cbf43926"),
+            ManifestEntry::new("two", "00000000"),
+        ];
+        let mut manifest = Vec::new();
+        write_sfv_comment(&mut manifest, "generated\nby test").unwrap();
+        write_manifest(&mut manifest, &entries, Format::Sfv).unwrap();
+        assert_eq!(
+            String::from_utf8(manifest.clone()).unwrap(),
+            "; generated\n; by test\nfile one.txt CBF43926\ntwo 00000000\n"
+        );
+        let parsed = parse_sfv(Cursor::new(manifest)).unwrap();
+        assert_eq!(
+            parsed[0],
+            ManifestEntry::new("file one.txt", "CBF43926").with_algorithm("CRC32")
+        );
+        assert_eq!(parsed[1].path.to_str(), Some("two"));
+
+        let error = ManifestEntry::new("a\nb", "00000000")
+            .write_to(io::sink(), Format::Sfv)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = parse_sfv(Cursor::new("file 1234\n")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{Algorithm, Writer};
+
+/// Counters shared by all writers of a single algorithm.
+#[derive(Debug, Default)]
+struct Counters {
+    active: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Counters {
+    fn record(&self, result: &io::Result<usize>) {
+        match result {
+            Ok(n) => self.bytes.fetch_add(*n as u64, Ordering::Relaxed),
+            Err(_) => self.errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+}
+
+/// Counts the writer as active until dropped.
+#[derive(Debug)]
+struct Active(Arc<Counters>);
+
+impl Active {
+    fn new(counters: Arc<Counters>) -> Self {
+        counters.active.fetch_add(1, Ordering::Relaxed);
+        Self(counters)
+    }
+}
+
+impl Drop for Active {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counters of an algorithm, with the byte count at the previous scrape for the throughput.
+#[derive(Debug)]
+struct Entry {
+    counters: Arc<Counters>,
+    scraped: (Instant, u64),
+}
+
+/// Collector of aggregate hashing telemetry of many writers, per algorithm, in the Prometheus text format.
+///
+/// Writers are registered with [`Writer::with_metrics`] and [`AsyncWriter::with_metrics`], and labeled with
+/// [`Algorithm::NAME`]. The collector is cheap to clone, clones share the counters. [`Collector::encode`] exposes:
+///
+/// * `chksum_writer_active_writers`, gauge of the writers alive,
+/// * `chksum_writer_bytes_total`, counter of the hashed bytes,
+/// * `chksum_writer_errors_total`, counter of the failed writes,
+/// * `chksum_writer_throughput_bytes_per_second`, gauge of the hashed bytes per second since the previous encoding.
+#[derive(Clone, Debug, Default)]
+pub struct Collector {
+    entries: Arc<Mutex<BTreeMap<&'static str, Entry>>>,
+}
+
+impl Collector {
+    /// Creates new [`Collector`] without any writers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn counters(&self, algorithm: &'static str) -> Arc<Counters> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = entries.entry(algorithm).or_insert_with(|| {
+            Entry {
+                counters: Arc::default(),
+                scraped: (Instant::now(), 0),
+            }
+        });
+        Arc::clone(&entry.counters)
+    }
+
+    /// Returns metrics of all algorithms in the Prometheus text exposition format.
+    #[must_use]
+    pub fn encode(&self) -> String {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let samples: Vec<_> = entries
+            .iter_mut()
+            .map(|(algorithm, entry)| {
+                let bytes = entry.counters.bytes.load(Ordering::Relaxed);
+                let (scraped, previous) = entry.scraped;
+                let elapsed = now.duration_since(scraped).as_secs_f64();
+                let throughput = if elapsed > 0.0 {
+                    (bytes - previous) as f64 / elapsed
+                } else {
+                    0.0
+                };
+                entry.scraped = (now, bytes);
+                let active = entry.counters.active.load(Ordering::Relaxed);
+                let errors = entry.counters.errors.load(Ordering::Relaxed);
+                (
+                    *algorithm,
+                    [
+                        active.to_string(),
+                        bytes.to_string(),
+                        errors.to_string(),
+                        throughput.to_string(),
+                    ],
+                )
+            })
+            .collect();
+        let metrics = [
+            ("active_writers", "gauge", "Number of active writers."),
+            ("bytes_total", "counter", "Number of hashed bytes."),
+            ("errors_total", "counter", "Number of failed writes."),
+            (
+                "throughput_bytes_per_second",
+                "gauge",
+                "Hashed bytes per second since the previous scrape.",
+            ),
+        ];
+        let mut output = String::new();
+        for (index, (name, kind, help)) in metrics.into_iter().enumerate() {
+            let _ = writeln!(output, "# HELP chksum_writer_{name} {help}");
+            let _ = writeln!(output, "# TYPE chksum_writer_{name} {kind}");
+            for (algorithm, values) in &samples {
+                let value = &values[index];
+                let _ = writeln!(output, "chksum_writer_{name}{{algorithm=\"{algorithm}\"}} {value}");
+            }
+        }
+        output
+    }
+}
+
+/// Wraps a [`Writer`] and records its written bytes and failed writes in a [`Collector`].
+///
+/// The writer counts as active until dropped or unwrapped.
+#[derive(Debug)]
+pub struct MetricsWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    writer: Writer<W, H>,
+    active: Active,
+}
+
+impl<W, H> MetricsWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    /// Creates new [`MetricsWriter`] registered in the collector.
+    #[must_use]
+    pub fn new(writer: Writer<W, H>, collector: &Collector) -> Self {
+        let active = Active::new(collector.counters(H::NAME));
+        Self { writer, active }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Unwraps this [`MetricsWriter`], returning the wrapped writer.
+    #[must_use]
+    pub fn into_inner(self) -> Writer<W, H> {
+        let Self { writer, .. } = self;
+        writer
+    }
+}
+
+impl<W, H> Write for MetricsWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result = self.writer.write(buf);
+        self.active.0.record(&result);
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Wraps an [`AsyncWriter`] and records its written bytes and failed writes in a [`Collector`].
+///
+/// The writer counts as active until dropped or unwrapped.
+#[cfg(feature = "async-runtime-tokio")]
+#[derive(Debug)]
+pub struct AsyncMetricsWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Algorithm,
+{
+    writer: AsyncWriter<W, H>,
+    active: Active,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncMetricsWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Algorithm,
+{
+    /// Creates new [`AsyncMetricsWriter`] registered in the collector.
+    #[must_use]
+    pub fn new(writer: AsyncWriter<W, H>, collector: &Collector) -> Self {
+        let active = Active::new(collector.counters(H::NAME));
+        Self { writer, active }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Unwraps this [`AsyncMetricsWriter`], returning the wrapped writer.
+    #[must_use]
+    pub fn into_inner(self) -> AsyncWriter<W, H> {
+        let Self { writer, .. } = self;
+        writer
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWrite for AsyncMetricsWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Algorithm + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let Self { writer, active } = self.get_mut();
+        match pin!(writer).poll_write(cx, buf) {
+            Poll::Ready(result) => {
+                active.0.record(&result);
+                Poll::Ready(result)
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_shutdown(cx)
+    }
+}
@@ -0,0 +1,151 @@
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+/// Prefix of hashed leaves, separating them from internal nodes.
+pub(crate) const LEAF_PREFIX: [u8; 1] = [0x00];
+/// Prefix of hashed internal nodes, separating them from leaves.
+pub(crate) const NODE_PREFIX: [u8; 1] = [0x01];
+
+/// Calculates hash digest of a leaf.
+pub(crate) fn hash_leaf<H>(data: &[u8]) -> H::Digest
+where
+    H: Hash,
+{
+    let mut hash = H::default();
+    hash.update(LEAF_PREFIX);
+    hash.update(data);
+    hash.digest()
+}
+
+/// Calculates hash digest of an internal node.
+pub(crate) fn hash_node<H>(left: &H::Digest, right: &H::Digest) -> H::Digest
+where
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+{
+    let mut hash = H::default();
+    hash.update(NODE_PREFIX);
+    hash.update(left);
+    hash.update(right);
+    hash.digest()
+}
+
+/// Peak of a mountain, identified by the position of its top node and its height.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Peak {
+    pub(crate) position: usize,
+    pub(crate) height: u32,
+}
+
+/// Writes records to the underlying writer while accumulating them in a Merkle Mountain Range.
+///
+/// Every record becomes a leaf hashed as `H(0x00 || record)`, internal nodes are hashed as `H(0x01 || left || right)`.
+/// Appending is amortized constant time and the root is available at any time, without fixing the number of leaves
+/// up front.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MmrWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]> + Clone,
+{
+    inner: W,
+    pub(crate) nodes: Vec<H::Digest>,
+    pub(crate) peaks: Vec<Peak>,
+    pub(crate) leaves: u64,
+}
+
+impl<W, H> MmrWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]> + Clone,
+{
+    /// Creates new [`MmrWriter`].
+    #[must_use]
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            nodes: Vec::new(),
+            peaks: Vec::new(),
+            leaves: 0,
+        }
+    }
+
+    /// Writes the record and appends it as a new leaf, returning its index.
+    pub fn append(&mut self, record: &[u8]) -> io::Result<u64> {
+        self.inner.write_all(record)?;
+        let leaf = hash_leaf::<H>(record);
+        Ok(self.push(leaf))
+    }
+
+    fn push(&mut self, leaf: H::Digest) -> u64 {
+        self.nodes.push(leaf);
+        self.peaks.push(Peak {
+            position: self.nodes.len() - 1,
+            height: 0,
+        });
+        while let [.., left, right] = self.peaks[..] {
+            if left.height != right.height {
+                break;
+            }
+            let node = hash_node::<H>(&self.nodes[left.position], &self.nodes[right.position]);
+            self.nodes.push(node);
+            self.peaks.truncate(self.peaks.len() - 2);
+            self.peaks.push(Peak {
+                position: self.nodes.len() - 1,
+                height: left.height + 1,
+            });
+        }
+        let index = self.leaves;
+        self.leaves += 1;
+        index
+    }
+
+    /// Returns number of appended leaves.
+    #[must_use]
+    pub const fn leaves(&self) -> u64 {
+        self.leaves
+    }
+
+    /// Returns digests of the current peaks, from the highest (leftmost) to the lowest.
+    pub fn peaks(&self) -> impl DoubleEndedIterator<Item = &H::Digest> {
+        self.peaks.iter().map(|peak| &self.nodes[peak.position])
+    }
+
+    /// Returns root of the accumulator, or `None` if no leaf was appended yet.
+    ///
+    /// The root is calculated by bagging the peaks from right to left, starting with the lowest peak and folding every
+    /// preceding peak as `H(0x01 || peak || accumulator)`.
+    #[must_use]
+    pub fn root(&self) -> Option<H::Digest> {
+        bag::<H>(self.peaks())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Unwraps this [`MmrWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+}
+
+/// Bags the peaks from right to left into a single root.
+pub(crate) fn bag<'a, H>(peaks: impl DoubleEndedIterator<Item = &'a H::Digest>) -> Option<H::Digest>
+where
+    H: Hash,
+    H::Digest: AsRef<[u8]> + Clone + 'a,
+{
+    let mut peaks = peaks.rev();
+    let mut root = peaks.next()?.clone();
+    for peak in peaks {
+        root = hash_node::<H>(peak, &root);
+    }
+    Some(root)
+}
@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+/// Writes a `multipart/form-data` body, calculating the hash digest of every part on the fly.
+///
+/// A part is started with [`MultipartWriter::part`] and its body is written through the [`Write`] implementation.
+/// The body can be passed to any request builder accepting raw bytes or a reader, together with the header value
+/// returned by [`MultipartWriter::content_type`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultipartWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    boundary: String,
+    current: Option<(String, H)>,
+    digests: BTreeMap<String, H::Digest>,
+}
+
+impl<W, H> MultipartWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`MultipartWriter`] separating the parts with the boundary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the boundary is empty, longer than 70 characters or contains characters other than ASCII letters,
+    /// digits and `'()+_,-./:=?`, as required by RFC 2046.
+    pub fn new(inner: W, boundary: impl Into<String>) -> Self {
+        let boundary = boundary.into();
+        let valid = |char: char| char.is_ascii_alphanumeric() || "'()+_,-./:=?".contains(char);
+        assert!(
+            (1..=70).contains(&boundary.len()) && boundary.chars().all(valid),
+            "invalid multipart boundary"
+        );
+        Self {
+            inner,
+            boundary,
+            current: None,
+            digests: BTreeMap::new(),
+        }
+    }
+
+    /// Returns value of the `Content-Type` header of the body.
+    #[must_use]
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Ends the current part, if any, and starts a new one for the field.
+    ///
+    /// The file name and the content type are added to the part headers when given. Quotes and line breaks in the
+    /// names are percent-encoded, like browsers do. A content type containing a line break results in an error of kind
+    /// [`io::ErrorKind::InvalidInput`], before the current part is ended.
+    pub fn part(&mut self, name: &str, filename: Option<&str>, content_type: Option<&str>) -> io::Result<()> {
+        if content_type.is_some_and(|content_type| content_type.contains(['\r', '\n'])) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line break in multipart content type",
+            ));
+        }
+        self.end_part()?;
+        write!(
+            self.inner,
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            encode(name)
+        )?;
+        if let Some(filename) = filename {
+            write!(self.inner, "; filename=\"{}\"", encode(filename))?;
+        }
+        if let Some(content_type) = content_type {
+            write!(self.inner, "\r\nContent-Type: {content_type}")?;
+        }
+        self.inner.write_all(b"\r\n\r\n")?;
+        self.current = Some((name.to_owned(), H::default()));
+        Ok(())
+    }
+
+    /// Terminates body of the current part, if any, and records its digest.
+    fn end_part(&mut self) -> io::Result<()> {
+        if let Some((name, hash)) = self.current.take() {
+            self.inner.write_all(b"\r\n")?;
+            self.digests.insert(name, hash.digest());
+        }
+        Ok(())
+    }
+
+    /// Returns digests of the bodies of the finished parts by field name, the last part wins for repeated names.
+    #[must_use]
+    pub const fn digests(&self) -> &BTreeMap<String, H::Digest> {
+        &self.digests
+    }
+
+    /// Ends the last part, writes the closing boundary and flushes the writer, returning the underlying writer with
+    /// digests of the bodies of all parts by field name.
+    pub fn finish(mut self) -> io::Result<(W, BTreeMap<String, H::Digest>)> {
+        self.end_part()?;
+        write!(self.inner, "--{}--\r\n", self.boundary)?;
+        self.inner.flush()?;
+        let Self { inner, digests, .. } = self;
+        Ok((inner, digests))
+    }
+}
+
+impl<W, H> Write for MultipartWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Writes body of the current part.
+    ///
+    /// Writing before the first part results in an error of kind [`io::ErrorKind::InvalidInput`].
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some((_, hash)) = &mut self.current else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no multipart part started"));
+        };
+        let n = self.inner.write(buf)?;
+        hash.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Percent-encodes quotes and line breaks of a name in the part headers.
+fn encode(name: &str) -> String {
+    name.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+
+    use chksum_core::Hash;
+
+    use super::MultipartWriter;
+    use crate::testing::Sha256;
+
+    #[test]
+    fn writes_parts_with_digests() {
+        let mut writer = MultipartWriter::<_, Sha256>::new(Vec::new(), "XyZ");
+        assert_eq!(writer.content_type(), "multipart/form-data; boundary=XyZ");
+        writer.part("title", None, None).unwrap();
+        writer.write_all(b"hello").unwrap();
+        writer.part("file", Some("a \"b\".txt"), Some("text/plain")).unwrap();
+        writer.write_all(b"world").unwrap();
+        let (body, digests) = writer.finish().unwrap();
+        let expected = "--XyZ\r\nContent-Disposition: form-data; \
+                        name=\"title\"\r\n\r\nhello\r\n--XyZ\r\nContent-Disposition: form-data; name=\"file\"; \
+                        filename=\"a %22b%22.txt\"\r\nContent-Type: text/plain\r\n\r\nworld\r\n--XyZ--\r\n";
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+        assert_eq!(digests["title"], Sha256::hash(b"hello"));
+        assert_eq!(digests["file"], Sha256::hash(b"world"));
+    }
+
+    #[test]
+    fn rejects_line_breaks_in_content_type() {
+        let mut writer = MultipartWriter::<_, Sha256>::new(Vec::new(), "XyZ");
+        writer.part("a", None, None).unwrap();
+        for content_type in ["text/plain\r\nX-Injected: 1", "text/plain\n"] {
+            let error = writer.part("b", None, Some(content_type)).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        // the current part is still open
+        writer.write_all(b"body").unwrap();
+        assert!(writer.digests().is_empty());
+    }
+
+    #[test]
+    fn rejects_body_before_first_part() {
+        let mut writer = MultipartWriter::<_, Sha256>::new(Vec::new(), "XyZ");
+        let error = writer.write(b"body").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+use crate::KeyedHash;
+
+/// Wraps a writer and feeds the same bytes into multiple named hash contexts, like tenant-specific keyed hashes.
+///
+/// Cheaper than stacking a writer per context, the data is written once and only hashed repeatedly. Contexts should be
+/// registered before writing, as a context registered later misses the bytes written before.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamespacedWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    contexts: BTreeMap<String, H>,
+}
+
+impl<W, H> NamespacedWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`NamespacedWriter`] without any context.
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            contexts: BTreeMap::new(),
+        }
+    }
+
+    /// Registers context with provided hash, replacing a context of the same name.
+    #[must_use]
+    pub fn with_namespace(mut self, name: impl Into<String>, hash: H) -> Self {
+        self.contexts.insert(name.into(), hash);
+        self
+    }
+
+    /// Registers context with hash in keyed mode, replacing a context of the same name.
+    #[must_use]
+    pub fn with_keyed(self, name: impl Into<String>, key: &H::Key) -> Self
+    where
+        H: KeyedHash,
+    {
+        self.with_namespace(name, H::new_keyed(key))
+    }
+
+    /// Registers context with a domain tag, replacing a context of the same name.
+    ///
+    /// The digest is `H(len(tag) || tag || data)`, with the length of the tag as 64-bit big-endian integer, so
+    /// different tags never produce the same hash input.
+    #[must_use]
+    pub fn with_tag(self, name: impl Into<String>, tag: &[u8]) -> Self {
+        let mut hash = H::default();
+        hash.update((tag.len() as u64).to_be_bytes());
+        hash.update(tag);
+        self.with_namespace(name, hash)
+    }
+
+    /// Returns calculated hash digests by the names of the contexts.
+    #[must_use]
+    pub fn digests(&self) -> BTreeMap<String, H::Digest> {
+        self.contexts
+            .iter()
+            .map(|(name, hash)| (name.clone(), hash.digest()))
+            .collect()
+    }
+
+    /// Flushes the underlying writer and returns the final hash digests by the names of the contexts.
+    pub fn finalize(mut self) -> io::Result<BTreeMap<String, H::Digest>> {
+        self.inner.flush()?;
+        Ok(self.digests())
+    }
+
+    /// Unwraps this [`NamespacedWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+}
+
+impl<W, H> Write for NamespacedWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        for hash in self.contexts.values_mut() {
+            hash.update(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
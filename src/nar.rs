@@ -0,0 +1,176 @@
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use chksum_core::Hash;
+
+use crate::Writer;
+
+/// Writes string in NAR encoding, prefixed with its length and padded with zeros to a multiple of 8 bytes.
+fn write_str(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)?;
+    write_padding(writer, bytes.len() as u64)
+}
+
+fn write_padding(writer: &mut impl Write, length: u64) -> io::Result<()> {
+    let padding = (8 - length % 8) % 8;
+    writer.write_all(&[0; 8][..padding as usize])
+}
+
+fn os_bytes(name: &OsStr) -> Vec<u8> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+
+        name.as_bytes().to_vec()
+    }
+    #[cfg(not(unix))]
+    {
+        name.to_string_lossy().into_owned().into_bytes()
+    }
+}
+
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+fn write_node(writer: &mut impl Write, path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    write_str(writer, b"(")?;
+    write_str(writer, b"type")?;
+    if metadata.file_type().is_symlink() {
+        write_str(writer, b"symlink")?;
+        write_str(writer, b"target")?;
+        write_str(writer, &os_bytes(fs::read_link(path)?.as_os_str()))?;
+    } else if metadata.is_dir() {
+        write_str(writer, b"directory")?;
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| (os_bytes(&entry.file_name()), entry.path())))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by(|(name, _), (other, _)| name.cmp(other));
+        for (name, path) in entries {
+            write_str(writer, b"entry")?;
+            write_str(writer, b"(")?;
+            write_str(writer, b"name")?;
+            write_str(writer, &name)?;
+            write_str(writer, b"node")?;
+            write_node(writer, &path)?;
+            write_str(writer, b")")?;
+        }
+    } else if metadata.is_file() {
+        write_str(writer, b"regular")?;
+        if is_executable(&metadata) {
+            write_str(writer, b"executable")?;
+            write_str(writer, b"")?;
+        }
+        write_str(writer, b"contents")?;
+        let length = metadata.len();
+        writer.write_all(&length.to_le_bytes())?;
+        let copied = io::copy(&mut File::open(path)?.take(length), writer)?;
+        if copied != length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "file changed while serializing",
+            ));
+        }
+        write_padding(writer, length)?;
+    } else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "unsupported file type"));
+    }
+    write_str(writer, b")")
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Serializes the file, directory or symbolic link at the path in the Nix archive (NAR) format.
+    ///
+    /// The digest of a writer which received nothing else is the NAR hash, as used by Nix for content addressing.
+    /// Directory entries are ordered by the bytes of their names, and only the executable bit of permissions is
+    /// kept. Other file types, like sockets, result in an error of kind [`io::ErrorKind::InvalidInput`].
+    pub fn write_nar(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_str(self, b"nix-archive-1")?;
+        write_node(self, path.as_ref())
+    }
+}
+
+/// Calculates hash digest of the path serialized in the Nix archive (NAR) format.
+///
+/// See [`Writer::write_nar`] for details.
+pub fn nar_hash<H>(path: impl AsRef<Path>) -> io::Result<H::Digest>
+where
+    H: Hash,
+{
+    let mut writer = Writer::<_, H>::new(io::sink());
+    writer.write_nar(path)?;
+    Ok(writer.digest())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::{self, Write};
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::path::PathBuf;
+
+    use super::nar_hash;
+    use crate::testing::Sha256;
+
+    fn scratch(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chksum-writer-nar-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn regular_file() {
+        let path = scratch("file");
+        fs::write(&path, "hello\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        let digest = nar_hash::<Sha256>(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(
+            digest.to_string(),
+            "1c37d01af40be2e80691de3cc3df44377a699afbb17c68f080964b2fd071fc13"
+        );
+    }
+
+    #[test]
+    fn directory() {
+        let path = scratch("directory");
+        fs::create_dir(&path).unwrap();
+        // created in reverse to check the ordering of entries
+        fs::create_dir(path.join("c")).unwrap();
+        symlink("a.sh", path.join("b")).unwrap();
+        let mut script = File::create(path.join("a.sh")).unwrap();
+        script.write_all(b"#!/bin/sh\n").unwrap();
+        script.set_permissions(fs::Permissions::from_mode(0o755)).unwrap();
+        let digest = nar_hash::<Sha256>(&path);
+        fs::remove_dir_all(&path).unwrap();
+        assert_eq!(
+            digest.unwrap().to_string(),
+            "023637c0b39c575a81a0adf84523e959c30f204a5619f48ba7cd6848aef63d1c"
+        );
+    }
+
+    #[test]
+    fn missing_path() {
+        let error = nar_hash::<Sha256>(scratch("missing")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+}
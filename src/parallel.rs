@@ -0,0 +1,96 @@
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+/// A trait for hashes able to update their state using multiple threads, like BLAKE3 with `rayon`.
+pub trait ParallelHash: Hash {
+    /// Updates the hash state with an input data, possibly using multiple threads.
+    fn update_parallel(&mut self, data: &[u8]);
+}
+
+/// Wraps a writer and calculates the hash digest on the fly, hashing large writes in parallel.
+///
+/// Writes of at least the threshold length are passed to [`ParallelHash::update_parallel`], smaller ones use the
+/// regular serial update, which is faster for them due to the threading overhead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParallelWriter<W, H>
+where
+    W: Write,
+    H: ParallelHash,
+{
+    inner: W,
+    hash: H,
+    threshold: usize,
+}
+
+impl<W, H> ParallelWriter<W, H>
+where
+    W: Write,
+    H: ParallelHash,
+{
+    /// Default minimal length of writes hashed in parallel.
+    pub const DEFAULT_THRESHOLD: usize = 128 * 1024;
+
+    /// Creates new [`ParallelWriter`].
+    pub fn new(inner: W) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash)
+    }
+
+    /// Creates new [`ParallelWriter`] with provided hash.
+    #[must_use]
+    pub const fn with_hash(inner: W, hash: H) -> Self {
+        Self {
+            inner,
+            hash,
+            threshold: Self::DEFAULT_THRESHOLD,
+        }
+    }
+
+    /// Sets minimal length of writes hashed in parallel.
+    #[must_use]
+    pub const fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Returns minimal length of writes hashed in parallel.
+    #[must_use]
+    pub const fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Unwraps this [`ParallelWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+}
+
+impl<W, H> Write for ParallelWriter<W, H>
+where
+    W: Write,
+    H: ParallelHash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        let buf = &buf[..n];
+        if n >= self.threshold {
+            self.hash.update_parallel(buf);
+        } else {
+            self.hash.update(buf);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+use chksum_core::Hash;
+
+use crate::Writer;
+
+/// Outcome of processing a single file by a [`WriterPool`].
+pub type PoolResult<D> = (PathBuf, io::Result<(D, u64)>);
+
+/// Copies and hashes many files concurrently using [`Writer`] internally.
+///
+/// Workers run on threads named `chksum-writer-pool-<index>`, identifiable in debuggers and profilers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriterPool {
+    concurrency: NonZeroUsize,
+}
+
+impl WriterPool {
+    /// Creates new [`WriterPool`] running at most `concurrency` files at once.
+    #[must_use]
+    pub const fn new(concurrency: NonZeroUsize) -> Self {
+        Self { concurrency }
+    }
+
+    /// Returns maximum number of files processed at once.
+    #[must_use]
+    pub const fn concurrency(&self) -> NonZeroUsize {
+        self.concurrency
+    }
+
+    /// Hashes the given files, returning an iterator over results in completion order.
+    pub fn hash_files<H>(&self, paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> PoolResults<H::Digest>
+    where
+        H: Hash + 'static,
+        H::Digest: Send + 'static,
+    {
+        self.copy_files::<H, _, _>(paths, |_| Ok(io::sink()))
+    }
+
+    /// Copies the given files to writers created by `destination` while hashing them, returning an iterator over
+    /// results in completion order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a worker thread cannot be spawned, like [`thread::spawn`].
+    pub fn copy_files<H, F, W>(
+        &self,
+        paths: impl IntoIterator<Item = impl Into<PathBuf>>,
+        destination: F,
+    ) -> PoolResults<H::Digest>
+    where
+        H: Hash + 'static,
+        H::Digest: Send + 'static,
+        F: Fn(&Path) -> io::Result<W> + Send + Sync + 'static,
+        W: Write,
+    {
+        let paths: Vec<PathBuf> = paths.into_iter().map(Into::into).collect();
+        let workers = self.concurrency.get().min(paths.len());
+        let queue = Arc::new(Mutex::new(paths.into_iter()));
+        let destination = Arc::new(destination);
+        let (sender, receiver) = mpsc::channel();
+        for index in 0..workers {
+            let queue = Arc::clone(&queue);
+            let destination = Arc::clone(&destination);
+            let sender = sender.clone();
+            let worker = thread::Builder::new().name(format!("chksum-writer-pool-{index}"));
+            let spawned = worker.spawn(move || {
+                loop {
+                    let path = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                    let Some(path) = path else {
+                        break;
+                    };
+                    let result = copy::<H, _>(&path, &*destination);
+                    if sender.send((path, result)).is_err() {
+                        // results are no longer awaited
+                        break;
+                    }
+                }
+            });
+            spawned.expect("failed to spawn thread");
+        }
+        PoolResults { receiver }
+    }
+}
+
+fn copy<H, W>(path: &Path, destination: impl Fn(&Path) -> io::Result<W>) -> io::Result<(H::Digest, u64)>
+where
+    H: Hash,
+    W: Write,
+{
+    let mut source = File::open(path)?;
+    let mut writer = Writer::<_, H>::new(destination(path)?);
+    let length = io::copy(&mut source, &mut writer)?;
+    writer.flush()?;
+    Ok((writer.digest(), length))
+}
+
+/// Iterator over results produced by a [`WriterPool`].
+///
+/// Dropping it stops the workers after they finish their current files.
+#[derive(Debug)]
+pub struct PoolResults<D> {
+    receiver: Receiver<PoolResult<D>>,
+}
+
+impl<D> Iterator for PoolResults<D> {
+    type Item = PoolResult<D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// Hashes the given files using up to `concurrency` threads, returning an iterator over results in completion order.
+pub fn hash_files_parallel<H>(
+    paths: impl IntoIterator<Item = impl Into<PathBuf>>,
+    concurrency: NonZeroUsize,
+) -> PoolResults<H::Digest>
+where
+    H: Hash + 'static,
+    H::Digest: Send + 'static,
+{
+    WriterPool::new(concurrency).hash_files::<H>(paths)
+}
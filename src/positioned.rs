@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use chksum_core::Hash;
+
+/// Accepts positioned writes in any order and calculates the hash digest of the assembled stream.
+///
+/// Data is written to the underlying writer right away. Ranges which cannot be hashed yet, because some preceding
+/// range is still missing, are buffered until the gap is filled.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionedWriter<W, H>
+where
+    W: Write + Seek,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    hashed: u64,
+    pending: BTreeMap<u64, Vec<u8>>,
+}
+
+impl<W, H> PositionedWriter<W, H>
+where
+    W: Write + Seek,
+    H: Hash,
+{
+    /// Creates new [`PositionedWriter`].
+    pub fn new(inner: W) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash)
+    }
+
+    /// Creates new [`PositionedWriter`] with provided hash.
+    #[must_use]
+    pub const fn with_hash(inner: W, hash: H) -> Self {
+        Self {
+            inner,
+            hash,
+            hashed: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Writes data at the given offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::InvalidInput`] if the range overlaps with any previously written range or ends past
+    /// the largest 64-bit offset, or any error returned by the underlying writer.
+    pub fn write_at(&mut self, offset: u64, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let end = offset
+            .checked_add(data.len() as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "range ends past the largest offset"))?;
+        if self.overlaps(offset, end) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "range overlaps with previously written data",
+            ));
+        }
+        self.inner.seek(SeekFrom::Start(offset))?;
+        self.inner.write_all(data)?;
+        if offset == self.hashed {
+            self.hash.update(data);
+            self.hashed = end;
+            self.drain();
+        } else {
+            self.pending.insert(offset, data.to_vec());
+        }
+        Ok(())
+    }
+
+    fn overlaps(&self, start: u64, end: u64) -> bool {
+        if start < self.hashed {
+            return true;
+        }
+        // pending ranges were checked not to overflow when written
+        self.pending
+            .range(..end)
+            .next_back()
+            .is_some_and(|(offset, data)| offset + data.len() as u64 > start)
+    }
+
+    fn drain(&mut self) {
+        while let Some(data) = self.pending.remove(&self.hashed) {
+            self.hash.update(&data);
+            self.hashed += data.len() as u64;
+        }
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns length of the contiguous range starting at offset zero which is already hashed.
+    #[must_use]
+    pub const fn hashed_len(&self) -> u64 {
+        self.hashed
+    }
+
+    /// Returns number of bytes buffered until preceding gaps are filled.
+    #[must_use]
+    pub fn buffered_len(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    /// Returns `true` when every written range is hashed, meaning there are no gaps.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Unwraps this [`PositionedWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns calculated hash digest of the assembled stream, or `None` while gaps remain.
+    #[must_use]
+    pub fn digest(&self) -> Option<H::Digest> {
+        self.is_complete().then(|| self.hash.digest())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Cursor};
+
+    use chksum_core::Hash;
+
+    use super::PositionedWriter;
+    use crate::testing::Sha256;
+
+    #[test]
+    fn assembles_out_of_order_writes() {
+        let mut writer = PositionedWriter::<_, Sha256>::new(Cursor::new(Vec::new()));
+        writer.write_at(6, b"world").unwrap();
+        writer.write_at(0, b"hel").unwrap();
+        assert_eq!(writer.hashed_len(), 3);
+        assert_eq!(writer.buffered_len(), 5);
+        assert_eq!(writer.digest(), None);
+        writer.write_at(3, b"lo ").unwrap();
+        assert_eq!(writer.hashed_len(), 11);
+        assert_eq!(writer.digest(), Some(Sha256::hash(b"hello world")));
+        assert_eq!(writer.into_inner().into_inner(), b"hello world");
+    }
+
+    #[test]
+    fn rejects_overlapping_writes() {
+        let mut writer = PositionedWriter::<_, Sha256>::new(Cursor::new(Vec::new()));
+        writer.write_at(4, b"abcd").unwrap();
+        let error = writer.write_at(2, b"xyz").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = writer.write_at(7, b"xyz").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn rejects_range_past_largest_offset() {
+        let mut writer = PositionedWriter::<_, Sha256>::new(Cursor::new(Vec::new()));
+        let error = writer.write_at(u64::MAX - 1, b"abc").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(writer.is_complete());
+    }
+}
@@ -0,0 +1,73 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use chksum_core::Hash;
+
+use crate::Writer;
+
+/// A trait for writers able to reserve space for data of a known length up front, like [`File::set_len`].
+pub trait Preallocate {
+    /// Reserves space for the given total length of the written data.
+    fn preallocate(&mut self, length: u64) -> io::Result<()>;
+}
+
+impl Preallocate for File {
+    /// Extends the file to the length with [`File::set_len`], unless it is longer already.
+    fn preallocate(&mut self, length: u64) -> io::Result<()> {
+        (&*self).preallocate(length)
+    }
+}
+
+impl Preallocate for &File {
+    /// Extends the file to the length with [`File::set_len`], unless it is longer already.
+    fn preallocate(&mut self, length: u64) -> io::Result<()> {
+        if self.metadata()?.len() < length {
+            self.set_len(length)?;
+        }
+        Ok(())
+    }
+}
+
+impl Preallocate for Vec<u8> {
+    /// Reserves capacity for the length, failing with an error of kind [`io::ErrorKind::OutOfMemory`].
+    fn preallocate(&mut self, length: u64) -> io::Result<()> {
+        let length = usize::try_from(length).unwrap_or(usize::MAX);
+        self.try_reserve(length.saturating_sub(self.len()))
+            .map_err(|error| io::Error::new(io::ErrorKind::OutOfMemory, error))
+    }
+}
+
+impl<W> Preallocate for BufWriter<W>
+where
+    W: Write + Preallocate,
+{
+    fn preallocate(&mut self, length: u64) -> io::Result<()> {
+        self.get_mut().preallocate(length)
+    }
+}
+
+impl<T> Preallocate for &mut T
+where
+    T: Preallocate + ?Sized,
+{
+    fn preallocate(&mut self, length: u64) -> io::Result<()> {
+        (**self).preallocate(length)
+    }
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write + Preallocate,
+    H: Hash,
+{
+    /// Preallocates the underlying writer for the length set with [`Writer::with_expected_len`], if any.
+    ///
+    /// Reserving the space up front reduces fragmentation and makes some writers fail early if there is not enough
+    /// space, instead of midway through the stream.
+    pub fn preallocate(&mut self) -> io::Result<()> {
+        match self.expected {
+            Some(length) => self.inner.preallocate(length),
+            None => Ok(()),
+        }
+    }
+}
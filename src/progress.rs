@@ -0,0 +1,196 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{Context, Poll};
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::Writer;
+
+/// Receiver of progress updates, like a progress bar.
+///
+/// Rendering, including throughput, is left to the implementation. For example, `indicatif::ProgressBar` can be
+/// adapted by forwarding to its `inc` and `finish_with_message` methods.
+pub trait Progress {
+    /// Advances progress by the given number of bytes.
+    fn inc(&mut self, delta: u64);
+
+    /// Finishes progress with a message containing the digest.
+    fn finish(&mut self, message: String);
+}
+
+impl<P> Progress for &mut P
+where
+    P: Progress + ?Sized,
+{
+    fn inc(&mut self, delta: u64) {
+        (**self).inc(delta);
+    }
+
+    fn finish(&mut self, message: String) {
+        (**self).finish(message);
+    }
+}
+
+fn message(digest: &impl Display, count: u64) -> String {
+    format!("{digest} ({count} bytes)")
+}
+
+/// Wraps a [`Writer`] and reports number of written bytes to a [`Progress`].
+#[derive(Clone, Debug)]
+pub struct ProgressWriter<W, H, P>
+where
+    W: Write,
+    H: Hash,
+    P: Progress,
+{
+    writer: Writer<W, H>,
+    progress: P,
+}
+
+impl<W, H, P> ProgressWriter<W, H, P>
+where
+    W: Write,
+    H: Hash,
+    P: Progress,
+{
+    /// Creates new [`ProgressWriter`].
+    #[must_use]
+    pub const fn new(writer: Writer<W, H>, progress: P) -> Self {
+        Self { writer, progress }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Finalizes the writer, finishes the progress with the digest and returns the final hash digest.
+    pub fn finish(self) -> io::Result<H::Digest> {
+        let Self { writer, mut progress } = self;
+        let count = writer.count();
+        let digest = writer.finalize()?;
+        progress.finish(message(&digest, count));
+        Ok(digest)
+    }
+
+    /// Unwraps this [`ProgressWriter`], returning the wrapped writer and the progress without finishing it.
+    #[must_use]
+    pub fn into_parts(self) -> (Writer<W, H>, P) {
+        let Self { writer, progress } = self;
+        (writer, progress)
+    }
+}
+
+impl<W, H, P> Write for ProgressWriter<W, H, P>
+where
+    W: Write,
+    H: Hash,
+    P: Progress,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        self.progress.inc(n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Wraps an [`AsyncWriter`] and reports number of written bytes to a [`Progress`].
+///
+/// The progress is finished with the digest on a successful shutdown.
+#[cfg(feature = "async-runtime-tokio")]
+#[derive(Clone, Debug)]
+pub struct AsyncProgressWriter<W, H, P>
+where
+    W: AsyncWrite,
+    H: Hash,
+    P: Progress,
+{
+    writer: AsyncWriter<W, H>,
+    progress: P,
+    finished: bool,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H, P> AsyncProgressWriter<W, H, P>
+where
+    W: AsyncWrite,
+    H: Hash,
+    P: Progress,
+{
+    /// Creates new [`AsyncProgressWriter`].
+    #[must_use]
+    pub const fn new(writer: AsyncWriter<W, H>, progress: P) -> Self {
+        Self {
+            writer,
+            progress,
+            finished: false,
+        }
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Unwraps this [`AsyncProgressWriter`], returning the wrapped writer and the progress.
+    #[must_use]
+    pub fn into_parts(self) -> (AsyncWriter<W, H>, P) {
+        let Self { writer, progress, .. } = self;
+        (writer, progress)
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H, P> AsyncWrite for AsyncProgressWriter<W, H, P>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+    P: Progress + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let Self { writer, progress, .. } = self.get_mut();
+        match pin!(writer).poll_write(cx, buf) {
+            Poll::Ready(Ok(n)) => {
+                progress.inc(n as u64);
+                Poll::Ready(Ok(n))
+            },
+            poll => poll,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { writer, .. } = self.get_mut();
+        pin!(writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self {
+            writer,
+            progress,
+            finished,
+        } = self.get_mut();
+        match pin!(&mut *writer).poll_shutdown(cx) {
+            Poll::Ready(Ok(())) => {
+                if !*finished {
+                    *finished = true;
+                    progress.finish(message(&writer.digest(), writer.count()));
+                }
+                Poll::Ready(Ok(()))
+            },
+            poll => poll,
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::io::Write;
+use std::ops::Range;
+
+use chksum_core::Hash;
+
+use crate::mmr::{bag, hash_leaf, hash_node, MmrWriter};
+
+/// Side on which a sibling node lies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Sibling is the left child, the proven node is the right one.
+    Left,
+    /// Sibling is the right child, the proven node is the left one.
+    Right,
+}
+
+/// Inclusion proof of a single leaf.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proof<D> {
+    /// Index of the proven leaf.
+    pub index: u64,
+    /// Siblings on the path from the leaf up to its peak, starting at the leaf.
+    pub siblings: Vec<(Side, D)>,
+    /// All other peaks, from the leftmost to the rightmost.
+    pub peaks: Vec<D>,
+    /// Position among all peaks of the peak containing the leaf.
+    pub peak: usize,
+}
+
+/// Calculates hash digest of a leaf, as used by [`MmrWriter`] and expected by [`verify_proof`].
+#[must_use]
+pub fn leaf_digest<H>(record: &[u8]) -> H::Digest
+where
+    H: Hash,
+{
+    hash_leaf::<H>(record)
+}
+
+/// Verifies that the leaf with given digest belongs to the accumulator with given root.
+#[must_use]
+pub fn verify_proof<H>(root: &H::Digest, proof: &Proof<H::Digest>, leaf_digest: &H::Digest) -> bool
+where
+    H: Hash,
+    H::Digest: AsRef<[u8]> + Clone + PartialEq,
+{
+    if proof.peak > proof.peaks.len() {
+        return false;
+    }
+    let peak = proof
+        .siblings
+        .iter()
+        .fold(leaf_digest.clone(), |node, (side, sibling)| {
+            match side {
+                Side::Left => hash_node::<H>(sibling, &node),
+                Side::Right => hash_node::<H>(&node, sibling),
+            }
+        });
+    let (left, right) = proof.peaks.split_at(proof.peak);
+    let peaks = left.iter().chain([&peak]).chain(right);
+    bag::<H>(peaks).as_ref() == Some(root)
+}
+
+impl<W, H> MmrWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]> + Clone,
+{
+    /// Produces inclusion proof of the leaf with given index, or `None` if there is no such leaf.
+    #[must_use]
+    pub fn prove(&self, index: u64) -> Option<Proof<H::Digest>> {
+        if index >= self.leaves {
+            return None;
+        }
+        let mut first = 0;
+        let (peak, mut position, mut height, mut offset) = self.peaks.iter().enumerate().find_map(|(i, peak)| {
+            let leaves = 1 << peak.height;
+            if index < first + leaves {
+                Some((i, peak.position, peak.height, index - first))
+            } else {
+                first += leaves;
+                None
+            }
+        })?;
+        let mut siblings = Vec::with_capacity(height as usize);
+        while height > 0 {
+            let half = 1 << (height - 1);
+            let right = position - 1;
+            let left = position - (1 << height);
+            if offset < half {
+                siblings.push((Side::Right, self.nodes[right].clone()));
+                position = left;
+            } else {
+                siblings.push((Side::Left, self.nodes[left].clone()));
+                position = right;
+                offset -= half;
+            }
+            height -= 1;
+        }
+        siblings.reverse();
+        let peaks = self
+            .peaks
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != peak)
+            .map(|(_, peak)| self.nodes[peak.position].clone())
+            .collect();
+        Some(Proof {
+            index,
+            siblings,
+            peaks,
+            peak,
+        })
+    }
+
+    /// Produces inclusion proofs of all leaves in the given range, or `None` if the range exceeds the leaves.
+    #[must_use]
+    pub fn prove_range(&self, range: Range<u64>) -> Option<Vec<Proof<H::Digest>>> {
+        range.map(|index| self.prove(index)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{leaf_digest, verify_proof, Side};
+    use crate::testing::Sha256;
+    use crate::MmrWriter;
+
+    fn accumulator(leaves: u8) -> MmrWriter<io::Sink, Sha256> {
+        let mut writer = MmrWriter::new(io::sink());
+        for leaf in 0..leaves {
+            assert_eq!(writer.append(&[leaf]).unwrap(), u64::from(leaf));
+        }
+        writer
+    }
+
+    #[test]
+    fn root_known_answers() {
+        let mut writer = MmrWriter::<_, Sha256>::new(io::sink());
+        assert_eq!(writer.root(), None);
+        for record in [b"a", b"b", b"c"] {
+            writer.append(record).unwrap();
+        }
+        assert_eq!(writer.peaks().count(), 2);
+        assert_eq!(
+            writer.root().unwrap().to_string(),
+            "36642e73c2540ab121e3a6bf9545b0a24982cd830eb13d3cd19de3ce6c021ec1"
+        );
+        let writer = accumulator(7);
+        assert_eq!(writer.peaks().count(), 3);
+        assert_eq!(
+            writer.root().unwrap().to_string(),
+            "3560191803028444b232018ac047fdb561c09c23a7a6876c85e08b5e4d48e9f3"
+        );
+    }
+
+    #[test]
+    fn proves_every_leaf() {
+        for leaves in 1..=20 {
+            let writer = accumulator(leaves);
+            let root = writer.root().unwrap();
+            let proofs = writer.prove_range(0..u64::from(leaves)).unwrap();
+            for (leaf, proof) in (0..leaves).zip(&proofs) {
+                assert!(verify_proof::<Sha256>(&root, proof, &leaf_digest::<Sha256>(&[leaf])));
+                assert!(!verify_proof::<Sha256>(
+                    &root,
+                    proof,
+                    &leaf_digest::<Sha256>(&[leaf + 1])
+                ));
+            }
+            assert!(writer.prove(u64::from(leaves)).is_none());
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_proofs() {
+        let writer = accumulator(6);
+        let root = writer.root().unwrap();
+        let leaf = leaf_digest::<Sha256>(&[2]);
+        let proof = writer.prove(2).unwrap();
+        assert_eq!(proof.siblings.len(), 2);
+        assert_eq!(proof.siblings[0].0, Side::Right);
+
+        let mut flipped = proof.clone();
+        flipped.siblings[0].0 = Side::Left;
+        assert!(!verify_proof::<Sha256>(&root, &flipped, &leaf));
+        let mut moved = proof.clone();
+        moved.peak = 1;
+        assert!(!verify_proof::<Sha256>(&root, &moved, &leaf));
+        let mut overflow = proof;
+        overflow.peak = 5;
+        assert!(!verify_proof::<Sha256>(&root, &overflow, &leaf));
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::{BTreeSet, HashSet};
+use std::hash::BuildHasher;
+use std::io::{self, Write};
+use std::mem;
+
+use chksum_core::Hash;
+
+use crate::bloom::BloomFilter;
+
+/// Set of record digests used by [`RecordWriter`] to detect duplicate records.
+///
+/// Implemented for [`HashSet`] and [`BTreeSet`], which detect duplicates exactly at the cost of keeping every distinct
+/// digest in memory. An approximate [`BloomFilter`] bounds the memory use instead, at the cost of false positives. The
+/// unit type detects no duplicates.
+pub trait DigestSet<D> {
+    /// Inserts the digest, returning `false` if it was, possibly, inserted before.
+    fn insert(&mut self, digest: &D) -> bool;
+}
+
+impl<D> DigestSet<D> for () {
+    fn insert(&mut self, _: &D) -> bool {
+        true
+    }
+}
+
+impl<D, S> DigestSet<D> for HashSet<D, S>
+where
+    D: Clone + Eq + std::hash::Hash,
+    S: BuildHasher,
+{
+    fn insert(&mut self, digest: &D) -> bool {
+        !self.contains(digest) && HashSet::insert(self, digest.clone())
+    }
+}
+
+impl<D> DigestSet<D> for BTreeSet<D>
+where
+    D: Clone + Ord,
+{
+    fn insert(&mut self, digest: &D) -> bool {
+        !self.contains(digest) && BTreeSet::insert(self, digest.clone())
+    }
+}
+
+impl<D, S> DigestSet<D> for &mut S
+where
+    S: DigestSet<D> + ?Sized,
+{
+    fn insert(&mut self, digest: &D) -> bool {
+        (**self).insert(digest)
+    }
+}
+
+/// Wraps a writer and calculates the hash digest of every record, as well as of the whole stream, on the fly.
+///
+/// Records are delimited by the separator, like `b'\n'` for CSV or NDJSON. The digest of a record covers its bytes
+/// without the separator, so a record terminated by the separator and a trailing one without it get the same digest.
+/// Empty records between two separators are digested too.
+///
+/// Duplicate records are detected with a [`DigestSet`] enabled by [`RecordWriter::with_dedup`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordWriter<W, H, S = ()>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    hash: H,
+    record: H,
+    separator: u8,
+    pending: bool,
+    records: Vec<H::Digest>,
+    set: S,
+    duplicates: Vec<usize>,
+}
+
+impl<W, H> RecordWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`RecordWriter`] with the given record separator.
+    pub fn new(inner: W, separator: u8) -> Self {
+        Self {
+            inner,
+            hash: H::default(),
+            record: H::default(),
+            separator,
+            pending: false,
+            records: Vec::new(),
+            set: (),
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// Detects duplicate records by inserting digests of the terminated records into the set. Should be called before
+    /// writing.
+    #[must_use]
+    pub fn with_dedup<S>(self, set: S) -> RecordWriter<W, H, S>
+    where
+        S: DigestSet<H::Digest>,
+    {
+        let Self {
+            inner,
+            hash,
+            record,
+            separator,
+            pending,
+            records,
+            duplicates,
+            ..
+        } = self;
+        RecordWriter {
+            inner,
+            hash,
+            record,
+            separator,
+            pending,
+            records,
+            set,
+            duplicates,
+        }
+    }
+}
+
+impl<W, H, S> RecordWriter<W, H, S>
+where
+    W: Write,
+    H: Hash,
+    S: DigestSet<H::Digest>,
+{
+    /// Returns separator of the records.
+    #[must_use]
+    pub const fn separator(&self) -> u8 {
+        self.separator
+    }
+
+    /// Returns digests of the records terminated by the separator.
+    #[must_use]
+    pub fn completed(&self) -> &[H::Digest] {
+        &self.records
+    }
+
+    /// Returns digests of all records, including the trailing one without separator.
+    #[must_use]
+    pub fn records(&self) -> Vec<H::Digest>
+    where
+        H::Digest: Clone,
+    {
+        let mut records = self.records.clone();
+        if self.pending {
+            records.push(self.record.digest());
+        }
+        records
+    }
+
+    /// Returns [`BloomFilter`] of the digests of all records, including the trailing one without separator, sized for
+    /// the given false positive rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is not between 0 and 1, exclusive.
+    #[must_use]
+    pub fn bloom_filter(&self, rate: f64) -> BloomFilter
+    where
+        H::Digest: AsRef<[u8]> + Clone,
+    {
+        let records = self.records();
+        let mut filter = BloomFilter::with_rate(records.len(), rate);
+        for record in &records {
+            filter.insert(record);
+        }
+        filter
+    }
+
+    /// Returns calculated hash digest of the whole stream, separators included.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+
+    /// Returns indices of the terminated records which duplicate an earlier record, in the order they were written.
+    ///
+    /// With an approximate set, some indices may be false positives.
+    #[must_use]
+    pub fn duplicates(&self) -> &[usize] {
+        &self.duplicates
+    }
+
+    /// Returns set of the record digests.
+    #[must_use]
+    pub const fn set(&self) -> &S {
+        &self.set
+    }
+
+    /// Unwraps this [`RecordWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        self.hash.update(data);
+        let mut records = data.split(|&byte| byte == self.separator);
+        // the last part is never terminated by a separator, it continues in the next write
+        let mut part = records.next().unwrap_or_default();
+        for next in records {
+            self.record.update(part);
+            let record = mem::take(&mut self.record).digest();
+            if !self.set.insert(&record) {
+                self.duplicates.push(self.records.len());
+            }
+            self.records.push(record);
+            self.pending = false;
+            part = next;
+        }
+        if !part.is_empty() {
+            self.record.update(part);
+            self.pending = true;
+        }
+    }
+}
+
+impl<W, H, S> Write for RecordWriter<W, H, S>
+where
+    W: Write,
+    H: Hash,
+    S: DigestSet<H::Digest>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,185 @@
+use std::any;
+use std::fmt::Display;
+use std::time::SystemTime;
+
+/// Structured summary of a hashing result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigestReport {
+    /// Name of the writer set with [`Writer::instrument`](crate::Writer::instrument), if any.
+    pub name: Option<String>,
+    /// Name of the hash algorithm, the name of the hash type unless set with [`DigestReport::with_algorithm`].
+    pub algorithm: String,
+    /// Hash digest as hexadecimal string.
+    pub digest: String,
+    /// Number of bytes hashed.
+    pub length: u64,
+    /// Digests of the individual chunks as hexadecimal strings, like in [`HashListWriter::report`](crate::HashListWriter::report),
+    /// empty if the stream was not chunked.
+    pub chunks: Vec<String>,
+    /// Time of the first write, or of the report if nothing was written.
+    pub started: SystemTime,
+    /// Time of the report.
+    pub finished: SystemTime,
+}
+
+impl DigestReport {
+    /// Creates new [`DigestReport`] for the digest calculated with the hash.
+    pub(crate) fn new<H>(digest: impl Display, length: u64, started: Option<SystemTime>) -> Self {
+        let finished = SystemTime::now();
+        Self {
+            name: None,
+            algorithm: algorithm::<H>(),
+            digest: digest.to_string(),
+            length,
+            chunks: Vec::new(),
+            started: started.unwrap_or(finished),
+            finished,
+        }
+    }
+
+    /// Sets name of the hash algorithm, like [`Algorithm::NAME`](crate::Algorithm::NAME).
+    #[must_use]
+    pub fn with_algorithm(mut self, algorithm: impl Into<String>) -> Self {
+        self.algorithm = algorithm.into();
+        self
+    }
+
+    /// Sets digests of the individual chunks.
+    #[must_use]
+    pub fn with_chunks<D>(mut self, chunks: impl IntoIterator<Item = D>) -> Self
+    where
+        D: ToString,
+    {
+        self.chunks = chunks.into_iter().map(|chunk| chunk.to_string()).collect();
+        self
+    }
+
+    /// Returns one-line summary suitable for command line tools, e.g. `sha256=…, 4.2 GiB in 3.1 s, 1.35 GiB/s`.
+    ///
+    /// The algorithm is labelled in lowercase without dashes, so `SHA-256` becomes `sha256`. The summary is prefixed with the writer name, if any, and the throughput is omitted if no time elapsed between
+    /// the first write and the report.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let elapsed = self.finished.duration_since(self.started).unwrap_or_default();
+        let algorithm: String = self
+            .algorithm
+            .chars()
+            .filter(|&char| char != '-')
+            .map(|char| char.to_ascii_lowercase())
+            .collect();
+        let mut summary = self.name.as_ref().map(|name| format!("{name}: ")).unwrap_or_default();
+        summary.push_str(&format!(
+            "{algorithm}={}, {} in {:.1} s",
+            self.digest,
+            format_size(self.length as f64, 1),
+            elapsed.as_secs_f64(),
+        ));
+        if !elapsed.is_zero() {
+            let throughput = self.length as f64 / elapsed.as_secs_f64();
+            summary.push_str(&format!(", {}/s", format_size(throughput, 2)));
+        }
+        summary
+    }
+}
+
+/// Formats number of bytes with binary unit prefix, keeping plain bytes without decimals.
+fn format_size(size: f64, precision: usize) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if size < 1024.0 {
+        return format!("{size:.0} B");
+    }
+    let mut size = size / 1024.0;
+    let mut unit = UNITS[0];
+    for next in &UNITS[1..] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    format!("{size:.precision$} {unit}")
+}
+
+/// Returns name of the hash type, without module path and generic parameters.
+fn algorithm<H>() -> String {
+    let name = any::type_name::<H>();
+    let name = name.split('<').next().unwrap_or(name);
+    let name = name.rsplit("::").next().unwrap_or(name);
+    name.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::num::NonZeroU64;
+
+    use chksum_core::Hash;
+
+    use super::format_size;
+    use crate::testing::Sha256;
+    use crate::{Algorithm, Builder, HashListWriter, Writer};
+
+    #[test]
+    fn labels_algorithm() {
+        let mut writer = Writer::<_, Sha256>::new(Vec::new()).instrument("upload");
+        writer.write_all(b"abc").unwrap();
+        let report = writer.report();
+        assert_eq!(report.name.as_deref(), Some("upload"));
+        // name of the type, `Md<Sha256Engine>` for the test hash
+        assert_eq!(report.algorithm, "Md");
+        assert_eq!(report.length, 3);
+        assert_eq!(report.digest, Sha256::hash(b"abc").to_string());
+
+        let report = report.with_algorithm(Sha256::NAME);
+        assert_eq!(report.algorithm, "SHA-256");
+        let summary = report.summary();
+        assert!(
+            summary.starts_with(&format!("upload: sha256={}, 3 B in ", report.digest)),
+            "{summary}"
+        );
+    }
+
+    #[test]
+    fn hash_list_chunks() {
+        let mut writer = HashListWriter::<_, Sha256>::new(io::sink(), NonZeroU64::new(4).unwrap());
+        writer.write_all(b"abcdef").unwrap();
+        let report = writer.report();
+        assert_eq!(report.digest, writer.digest().to_string());
+        assert_eq!(report.length, 6);
+        assert_eq!(
+            report.chunks,
+            [Sha256::hash(b"abcd").to_string(), Sha256::hash(b"ef").to_string()]
+        );
+    }
+
+    #[test]
+    fn excludes_suffix_like_digest() {
+        let mut writer = Builder::<Sha256>::new().with_suffix("!").build(Vec::new());
+        writer.write_all(b"abc").unwrap();
+        assert_eq!(writer.report().digest, writer.digest().to_string());
+        assert_eq!(writer.report().digest, Sha256::hash(b"abc").to_string());
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn includes_suffix_once_sealed() {
+        use tokio::io::AsyncWriteExt;
+
+        crate::testing::block_on(async {
+            let mut writer = Builder::<Sha256>::new().with_suffix("!").build_async(Vec::new());
+            writer.write_all(b"abc").await.unwrap();
+            assert_eq!(writer.report().digest, Sha256::hash(b"abc").to_string());
+            writer.shutdown().await.unwrap();
+            assert_eq!(writer.report().digest, writer.digest().to_string());
+            assert_eq!(writer.report().digest, Sha256::hash(b"abc!").to_string());
+        });
+    }
+
+    #[test]
+    fn formats_binary_sizes() {
+        assert_eq!(format_size(1023.0, 1), "1023 B");
+        assert_eq!(format_size(1536.0, 1), "1.5 KiB");
+        assert_eq!(format_size(3.0 * 1024.0 * 1024.0 * 1024.0, 2), "3.00 GiB");
+    }
+}
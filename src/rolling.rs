@@ -0,0 +1,29 @@
+/// Offset added to every byte by the rolling checksum of librsync.
+#[cfg(feature = "librsync")]
+const CHAR_OFFSET: u32 = 31;
+
+/// Returns weak checksum of the block, the sums `a` and `b` of zsync as big-endian 16-bit integers.
+#[cfg(feature = "zsync")]
+pub(crate) fn rsum(block: &[u8]) -> [u8; 4] {
+    let (mut a, mut b) = (0u16, 0u16);
+    for (index, &byte) in block.iter().enumerate() {
+        let weight = (block.len() - index) as u16;
+        a = a.wrapping_add(u16::from(byte));
+        b = b.wrapping_add(weight.wrapping_mul(u16::from(byte)));
+    }
+    let [a0, a1] = a.to_be_bytes();
+    let [b0, b1] = b.to_be_bytes();
+    [a0, a1, b0, b1]
+}
+
+/// Returns weak checksum of the block with the rolling checksum of librsync, the sums `s2` and `s1` as the high and low
+/// 16 bits.
+#[cfg(feature = "librsync")]
+pub(crate) fn rollsum(block: &[u8]) -> u32 {
+    let (mut s1, mut s2) = (0u32, 0u32);
+    for &byte in block {
+        s1 = s1.wrapping_add(u32::from(byte) + CHAR_OFFSET);
+        s2 = s2.wrapping_add(s1);
+    }
+    (s2 << 16) | (s1 & 0xFFFF)
+}
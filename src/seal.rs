@@ -0,0 +1,10 @@
+/// Sealing state of an asynchronous writer, advanced by shutdown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Seal {
+    /// Writes are accepted.
+    Open,
+    /// Shutdown of the underlying writer started, writes are rejected.
+    Sealing,
+    /// Shutdown completed, the suffix is folded in and the hash state is final.
+    Sealed,
+}
@@ -0,0 +1,185 @@
+use std::io::{self, Seek, SeekFrom, Write};
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{ready, Context, Poll};
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncSeek;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::Writer;
+
+/// Tracks the position of the underlying writer relative to the end of the hashed stream.
+///
+/// The digest stays valid as long as every write lands exactly at the end of the hashed stream. Seeking alone never
+/// invalidates it, but writing anywhere else does, permanently.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Tracking {
+    /// No seek was performed yet, so every write lands at the end of the hashed stream.
+    Untracked,
+    /// Current position is being queried before the requested seek is started.
+    #[cfg_attr(not(feature = "async-runtime-tokio"), allow(dead_code))]
+    Probing(SeekFrom),
+    /// Positions are known.
+    Tracked { end: u64, position: u64 },
+    /// Some write did not land at the end of the hashed stream.
+    Broken,
+}
+
+impl Tracking {
+    /// Records a write of `n` bytes at the current position.
+    pub(crate) fn advance(&mut self, n: usize) {
+        if let Self::Tracked { end, position } = self {
+            if position != end {
+                *self = Self::Broken;
+            } else {
+                *end += n as u64;
+                *position = *end;
+            }
+        }
+    }
+
+    pub(crate) const fn is_contiguous(&self) -> bool {
+        !matches!(self, Self::Broken)
+    }
+}
+
+impl<W, H> Seek for Writer<W, H>
+where
+    W: Write + Seek,
+    H: Hash,
+{
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let end = match self.tracking {
+            Tracking::Untracked | Tracking::Probing(_) => self.inner.stream_position()?,
+            Tracking::Tracked { end, .. } => end,
+            Tracking::Broken => return self.inner.seek(pos),
+        };
+        // keep the end known even if the seek itself fails
+        self.tracking = Tracking::Tracked { end, position: end };
+        let position = self.inner.seek(pos)?;
+        self.tracking = Tracking::Tracked { end, position };
+        Ok(position)
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncSeek for AsyncWriter<W, H>
+where
+    W: AsyncSeek + tokio::io::AsyncWrite + Unpin,
+    H: Hash + Unpin,
+{
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let Self { inner, tracking, .. } = self.get_mut();
+        if *tracking == Tracking::Untracked {
+            // the end of the hashed stream must be known before moving away from it
+            pin!(inner).start_seek(SeekFrom::Current(0))?;
+            *tracking = Tracking::Probing(position);
+            Ok(())
+        } else {
+            pin!(inner).start_seek(position)
+        }
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        let Self { inner, tracking, .. } = self.get_mut();
+        let mut inner = pin!(inner);
+        if let Tracking::Probing(position) = *tracking {
+            let end = match ready!(inner.as_mut().poll_complete(cx)) {
+                Ok(end) => end,
+                Err(error) => {
+                    *tracking = Tracking::Untracked;
+                    return Poll::Ready(Err(error));
+                },
+            };
+            *tracking = Tracking::Tracked { end, position: end };
+            inner.as_mut().start_seek(position)?;
+        }
+        let position = ready!(inner.poll_complete(cx))?;
+        match tracking {
+            Tracking::Untracked => {
+                *tracking = Tracking::Tracked {
+                    end: position,
+                    position,
+                };
+            },
+            Tracking::Tracked { position: current, .. } => {
+                *current = position;
+            },
+            Tracking::Probing(_) | Tracking::Broken => {},
+        }
+        Poll::Ready(Ok(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+
+    #[cfg(feature = "async-runtime-tokio")]
+    use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+    use super::Tracking;
+    #[cfg(feature = "async-runtime-tokio")]
+    use crate::testing::block_on;
+    use crate::testing::Sha256;
+    use crate::Builder;
+
+    #[test]
+    fn transitions() {
+        let mut tracking = Tracking::Untracked;
+        tracking.advance(4);
+        assert_eq!(tracking, Tracking::Untracked);
+        let mut tracking = Tracking::Tracked { end: 4, position: 4 };
+        tracking.advance(2);
+        assert_eq!(tracking, Tracking::Tracked { end: 6, position: 6 });
+        let mut tracking = Tracking::Tracked { end: 6, position: 2 };
+        tracking.advance(1);
+        assert_eq!(tracking, Tracking::Broken);
+        tracking.advance(1);
+        assert_eq!(tracking, Tracking::Broken);
+        assert!(!tracking.is_contiguous());
+    }
+
+    #[test]
+    fn append_only_verifies() {
+        let mut writer = Builder::<Sha256>::new().build(Cursor::new(Vec::new()));
+        writer.write_all(b"hello").unwrap();
+        // seeking away and back to the end keeps the digest valid
+        writer.seek(SeekFrom::Start(1)).unwrap();
+        writer.seek(SeekFrom::End(0)).unwrap();
+        writer.write_all(b" world").unwrap();
+        assert!(writer.is_contiguous());
+        assert!(writer.verify_written().unwrap());
+    }
+
+    #[test]
+    fn overwrite_breaks_verification() {
+        let mut writer = Builder::<Sha256>::new().build(Cursor::new(Vec::new()));
+        writer.write_all(b"hello world").unwrap();
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.write_all(b"J").unwrap();
+        assert!(!writer.is_contiguous());
+        writer.seek(SeekFrom::End(0)).unwrap();
+        assert!(!writer.verify_written().unwrap());
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_probes_before_seek() {
+        block_on(async {
+            let mut writer = Builder::<Sha256>::new().build_async(Cursor::new(Vec::new()));
+            writer.write_all(b"hello").await.unwrap();
+            assert_eq!(writer.seek(SeekFrom::Start(1)).await.unwrap(), 1);
+            assert_eq!(writer.seek(SeekFrom::End(0)).await.unwrap(), 5);
+            writer.write_all(b" world").await.unwrap();
+            assert!(writer.is_contiguous());
+            writer.seek(SeekFrom::Start(0)).await.unwrap();
+            writer.write_all(b"J").await.unwrap();
+            assert!(!writer.is_contiguous());
+        });
+    }
+}
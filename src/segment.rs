@@ -0,0 +1,287 @@
+use std::fmt::{self, Debug, Formatter};
+use std::io::{self, Write};
+use std::num::NonZeroU64;
+use std::time::{Duration, Instant};
+
+use chksum_core::Hash;
+
+use crate::Writer;
+
+/// Thresholds at which a [`SegmentedLogWriter`] seals the current segment and starts a new one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rotation {
+    /// Maximum number of bytes in a single segment.
+    pub max_bytes: Option<NonZeroU64>,
+    /// Maximum age of a segment, checked on every write.
+    pub max_age: Option<Duration>,
+}
+
+/// Description of a sealed segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Segment<D> {
+    /// Index of the segment, starting at zero.
+    pub index: u64,
+    /// Number of bytes in the segment.
+    pub len: u64,
+    /// Hash digest of the segment contents.
+    pub digest: D,
+    /// Chained digest of all segments sealed so far, including this one.
+    pub head: D,
+}
+
+type SealHook<D> = Box<dyn FnMut(&Segment<D>) + Send>;
+
+struct Current<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    writer: Writer<W, H>,
+    len: u64,
+    started: Instant,
+}
+
+/// Writes an append-only log split into segments, calculating a digest of every segment and a chained log head.
+///
+/// The head after sealing segment `n` is `H(head(n - 1) || digest(n))`, where the head before the first segment is
+/// empty. Segments are created lazily by the provided factory, on the first write after the previous one was sealed.
+///
+/// A segment reaching [`Rotation::max_bytes`] is sealed at the start of the next write or flush, so the write filling
+/// it always reports the accepted bytes and a failure to seal it is returned by the following call.
+pub struct SegmentedLogWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    F: FnMut(u64) -> io::Result<W>,
+{
+    factory: F,
+    rotation: Rotation,
+    current: Option<Current<W, H>>,
+    index: u64,
+    head: Option<H::Digest>,
+    hook: Option<SealHook<H::Digest>>,
+}
+
+impl<W, H, F> SegmentedLogWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    F: FnMut(u64) -> io::Result<W>,
+{
+    /// Creates new [`SegmentedLogWriter`] opening segments with the given factory, called with the segment index.
+    pub fn new(factory: F, rotation: Rotation) -> Self {
+        Self {
+            factory,
+            rotation,
+            current: None,
+            index: 0,
+            head: None,
+            hook: None,
+        }
+    }
+
+    /// Sets hook invoked every time a segment is sealed.
+    #[must_use]
+    pub fn on_seal(mut self, hook: impl FnMut(&Segment<H::Digest>) + Send + 'static) -> Self {
+        self.hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Returns chained digest of all segments sealed so far, or `None` if no segment was sealed yet.
+    #[must_use]
+    pub const fn head(&self) -> Option<&H::Digest> {
+        self.head.as_ref()
+    }
+
+    /// Returns number of segments sealed so far.
+    #[must_use]
+    pub const fn sealed(&self) -> u64 {
+        self.index
+    }
+
+    /// Seals the current segment, if there is one, and returns its description.
+    pub fn seal(&mut self) -> io::Result<Option<Segment<H::Digest>>> {
+        // flush before taking the segment, so it is not lost on error
+        if let Some(current) = &mut self.current {
+            current.writer.flush()?;
+        }
+        let Some(current) = self.current.take() else {
+            return Ok(None);
+        };
+        let digest = current.writer.digest();
+        let mut hash = H::default();
+        if let Some(head) = &self.head {
+            hash.update(head);
+        }
+        hash.update(&digest);
+        let segment = Segment {
+            index: self.index,
+            len: current.len,
+            digest,
+            head: hash.digest(),
+        };
+        if let Some(hook) = &mut self.hook {
+            hook(&segment);
+        }
+        self.index += 1;
+        self.head = Some(hash.digest());
+        Ok(Some(segment))
+    }
+
+    /// Returns `true` if the current segment reached the maximum size and waits to be sealed.
+    fn full(&self) -> bool {
+        let max_bytes = self.rotation.max_bytes.map(NonZeroU64::get);
+        self.current
+            .as_ref()
+            .is_some_and(|current| Some(current.len) == max_bytes)
+    }
+
+    /// Seals the current segment and returns the final log head, or `None` if nothing was ever written.
+    pub fn finish(mut self) -> io::Result<Option<H::Digest>> {
+        self.seal()?;
+        let Self { head, .. } = self;
+        Ok(head)
+    }
+}
+
+impl<W, H, F> Debug for SegmentedLogWriter<W, H, F>
+where
+    W: Write + Debug,
+    H: Hash + Debug,
+    H::Digest: AsRef<[u8]> + Debug,
+    F: FnMut(u64) -> io::Result<W>,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SegmentedLogWriter")
+            .field("rotation", &self.rotation)
+            .field("index", &self.index)
+            .field("head", &self.head)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W, H, F> Write for SegmentedLogWriter<W, H, F>
+where
+    W: Write,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+    F: FnMut(u64) -> io::Result<W>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let expired = self.current.as_ref().is_some_and(|current| {
+            self.rotation
+                .max_age
+                .is_some_and(|max_age| current.started.elapsed() >= max_age)
+        });
+        if expired || self.full() {
+            self.seal()?;
+        }
+        let current = match &mut self.current {
+            Some(current) => current,
+            current @ None => {
+                let inner = (self.factory)(self.index)?;
+                current.insert(Current {
+                    writer: Writer::new(inner),
+                    len: 0,
+                    started: Instant::now(),
+                })
+            },
+        };
+        let length = match self.rotation.max_bytes {
+            Some(max_bytes) => {
+                let remaining = max_bytes.get() - current.len;
+                buf.len().min(usize::try_from(remaining).unwrap_or(usize::MAX))
+            },
+            None => buf.len(),
+        };
+        let n = current.writer.write(&buf[..length])?;
+        current.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.full() {
+            return self.seal().map(drop);
+        }
+        match &mut self.current {
+            Some(current) => current.writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::num::{NonZeroU64, NonZeroUsize};
+    use std::sync::{Arc, Mutex};
+
+    use chksum_core::Hash;
+
+    use super::{Rotation, Segment, SegmentedLogWriter};
+    use crate::test_util::FailingWriter;
+    use crate::testing::{Sha256, TestDigest};
+
+    fn rotation(max_bytes: u64) -> Rotation {
+        Rotation {
+            max_bytes: NonZeroU64::new(max_bytes),
+            max_age: None,
+        }
+    }
+
+    #[test]
+    fn chains_segment_digests() {
+        let segments = Arc::new(Mutex::new(Vec::<Segment<TestDigest>>::new()));
+        let sealed = Arc::clone(&segments);
+        let mut writer = SegmentedLogWriter::<_, Sha256, _>::new(|_| Ok(Vec::new()), rotation(4))
+            .on_seal(move |segment| sealed.lock().unwrap().push(segment.clone()));
+        writer.write_all(b"abcdefghij").unwrap();
+        let head = writer.finish().unwrap();
+
+        let segments = segments.lock().unwrap();
+        let lengths: Vec<_> = segments.iter().map(|segment| segment.len).collect();
+        assert_eq!(lengths, [4, 4, 2]);
+        let mut expected = None::<TestDigest>;
+        for (segment, data) in segments.iter().zip([&b"abcd"[..], b"efgh", b"ij"]) {
+            assert_eq!(segment.digest, Sha256::hash(data));
+            let mut hash = Sha256::default();
+            if let Some(head) = &expected {
+                hash.update(head);
+            }
+            hash.update(&segment.digest);
+            expected = Some(hash.digest());
+            assert_eq!(Some(&segment.head), expected.as_ref());
+        }
+        assert_eq!(head, expected);
+    }
+
+    #[test]
+    fn seals_full_segment_lazily() {
+        // the first segment fails its flush, which happens when it is sealed
+        let calls = |index| NonZeroUsize::new(if index == 0 { 2 } else { usize::MAX }).unwrap();
+        let mut writer = SegmentedLogWriter::<_, Sha256, _>::new(
+            |index| Ok(FailingWriter::on_call(Vec::new(), calls(index))),
+            rotation(4),
+        );
+        assert_eq!(writer.write(b"abcdef").unwrap(), 4);
+        assert_eq!(writer.sealed(), 0);
+        assert!(writer.write(b"ef").is_err());
+        assert_eq!(writer.write(b"ef").unwrap(), 2);
+        assert_eq!(writer.sealed(), 1);
+        assert_eq!(writer.head(), Some(&Sha256::hash(Sha256::hash(b"abcd"))));
+    }
+
+    #[test]
+    fn flush_seals_full_segment() {
+        let mut writer = SegmentedLogWriter::<_, Sha256, _>::new(|_| Ok(Vec::new()), rotation(2));
+        writer.write_all(b"ab").unwrap();
+        assert_eq!(writer.sealed(), 0);
+        writer.flush().unwrap();
+        assert_eq!(writer.sealed(), 1);
+    }
+}
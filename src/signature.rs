@@ -0,0 +1,197 @@
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+
+use crate::rolling::rollsum;
+use crate::Algorithm;
+
+/// Magic number of signatures with MD4 strong sums, `RS_MD4_SIG_MAGIC`.
+const MD4_MAGIC: u32 = 0x7273_0136;
+/// Magic number of signatures with BLAKE2b strong sums, `RS_BLAKE2_SIG_MAGIC`.
+const BLAKE2_MAGIC: u32 = 0x7273_0137;
+
+/// Wraps a writer and calculates a [librsync] signature of the stream, as written by `rdiff signature`, in the same
+/// pass.
+///
+/// Every block gets the rolling checksum of librsync as weak sum and a strong sum with `H`, truncated to the strong
+/// sum length. The trailing block is shorter than the block length, and blocks are buffered until complete.
+///
+/// The signature format depends on the strong sum, derived from [`Algorithm::NAME`]: `MD4` for MD4 signatures and
+/// `BLAKE2b-256`, BLAKE2b with 32-byte output, for BLAKE2 signatures.
+///
+/// [librsync]: https://github.com/librsync/librsync
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignatureWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    inner: W,
+    block: Vec<u8>,
+    block_len: usize,
+    strong_length: usize,
+    sums: Vec<(u32, H::Digest)>,
+}
+
+impl<W, H> SignatureWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Default block length of `rdiff signature`.
+    pub const DEFAULT_BLOCK_LENGTH: NonZeroU32 = match NonZeroU32::new(2048) {
+        Some(length) => length,
+        None => unreachable!(),
+    };
+
+    /// Creates new [`SignatureWriter`] with the given block length and full strong sums.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block length does not fit into memory.
+    pub fn new(inner: W, block_length: NonZeroU32) -> Self {
+        let block_len = usize::try_from(block_length.get()).expect("block length fits into memory");
+        Self {
+            inner,
+            block: Vec::with_capacity(block_len),
+            block_len,
+            strong_length: H::DIGEST_SIZE,
+            sums: Vec::new(),
+        }
+    }
+
+    /// Truncates strong sums to the given length, at most the digest size.
+    #[must_use]
+    pub fn with_strong_length(mut self, length: usize) -> Self {
+        self.strong_length = length.min(H::DIGEST_SIZE);
+        self
+    }
+
+    /// Returns length of the blocks.
+    #[must_use]
+    pub const fn block_length(&self) -> usize {
+        self.block_len
+    }
+
+    /// Returns length of the strong sums.
+    #[must_use]
+    pub const fn strong_length(&self) -> usize {
+        self.strong_length
+    }
+
+    /// Ends the trailing block, flushes the underlying writer and writes the signature into `signature`.
+    ///
+    /// Strong sums other than MD4 and BLAKE2b-256 result in an error of kind [`io::ErrorKind::InvalidInput`].
+    pub fn finish(mut self, mut signature: impl Write) -> io::Result<W> {
+        let magic = match H::NAME {
+            "MD4" => MD4_MAGIC,
+            "BLAKE2b-256" => BLAKE2_MAGIC,
+            _ => {
+                let error = io::Error::new(io::ErrorKind::InvalidInput, "strong sum must be MD4 or BLAKE2b-256");
+                return Err(error);
+            },
+        };
+        if !self.block.is_empty() {
+            self.end_block();
+        }
+        self.inner.flush()?;
+        for value in [magic, self.block_len as u32, self.strong_length as u32] {
+            signature.write_all(&value.to_be_bytes())?;
+        }
+        for (weak, strong) in &self.sums {
+            signature.write_all(&weak.to_be_bytes())?;
+            signature.write_all(&strong.as_ref()[..self.strong_length])?;
+        }
+        let Self { inner, .. } = self;
+        Ok(inner)
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let remaining = self.block_len - self.block.len();
+            let (chunk, rest) = data.split_at(data.len().min(remaining));
+            self.block.extend_from_slice(chunk);
+            if self.block.len() == self.block_len {
+                self.end_block();
+            }
+            data = rest;
+        }
+    }
+
+    fn end_block(&mut self) {
+        self.sums.push((rollsum(&self.block), H::hash(&self.block)));
+        self.block.clear();
+    }
+}
+
+impl<W, H> Write for SignatureWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::num::NonZeroU32;
+
+    use super::SignatureWriter;
+    use crate::rolling::rollsum;
+    use crate::testing::{Md4, Sha256};
+
+    #[test]
+    fn rollsum_known_answers() {
+        // s1 = 3 * 31 + 97 + 98 + 99 = 387, s2 = 128 + 257 + 387 = 772
+        assert_eq!(rollsum(b"abc"), 0x0304_0183);
+        assert_eq!(rollsum(b""), 0);
+        // s1 overflows 16 bits
+        let block: Vec<u8> = (0..=255).cycle().take(1024).collect();
+        assert_eq!(rollsum(&block), 0xE800_7A00);
+    }
+
+    #[test]
+    fn md4_signature() {
+        // layout of `rdiff signature -H md4 -b 64 -S 8`: header, then weak and truncated strong sum of every block
+        let expected =
+            "727301360000004000000008f1d81ee86ef8335914d4892ee2b01edcbb58d1ef4277b3ee00d5008a25d39c0edf385445";
+        let data = b"hello world, ".repeat(10);
+        for piece in [1, 63, 64, data.len()] {
+            let mut writer =
+                SignatureWriter::<_, Md4>::new(Vec::new(), NonZeroU32::new(64).unwrap()).with_strong_length(8);
+            assert_eq!(writer.block_length(), 64);
+            for piece in data.chunks(piece) {
+                writer.write_all(piece).unwrap();
+            }
+            let mut signature = Vec::new();
+            assert_eq!(writer.finish(&mut signature).unwrap(), data);
+            let hex: String = signature.iter().map(|byte| format!("{byte:02x}")).collect();
+            assert_eq!(hex, expected.replace(' ', ""));
+        }
+    }
+
+    #[test]
+    fn empty_signature() {
+        let writer = SignatureWriter::<_, Md4>::new(io::sink(), SignatureWriter::<io::Sink, Md4>::DEFAULT_BLOCK_LENGTH);
+        let mut signature = Vec::new();
+        writer.finish(&mut signature).unwrap();
+        assert_eq!(signature, [0x72, 0x73, 0x01, 0x36, 0, 0, 0x08, 0, 0, 0, 0, 16]);
+    }
+
+    #[test]
+    fn rejects_other_strong_sums() {
+        let writer = SignatureWriter::<_, Sha256>::new(io::sink(), NonZeroU32::new(64).unwrap());
+        let error = writer.finish(io::sink()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,114 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::mpsc::{Sender, SyncSender};
+
+/// Consumer of chunk digests produced by chunking writers, like [`HashListWriter`](crate::HashListWriter) and
+/// [`CaibxWriter`](crate::CaibxWriter).
+///
+/// Chunks are passed in stream order as soon as they are completed, the trailing chunk once the writer is finished.
+/// The unit type discards the chunks. Pass the sink by mutable reference to keep it once the writer is finished.
+pub trait ChunkSink<D> {
+    /// Consumes digest of the chunk at the given offset and of the given length.
+    fn on_chunk(&mut self, offset: u64, length: u64, digest: &D);
+
+    /// Consumes digest of the whole stream of the given length, like the top digest of a
+    /// [`HashListWriter`](crate::HashListWriter), once the writer is finished.
+    fn on_finish(&mut self, length: u64, digest: &D) {
+        let _ = (length, digest);
+    }
+}
+
+impl<D> ChunkSink<D> for () {
+    fn on_chunk(&mut self, _: u64, _: u64, _: &D) {}
+}
+
+/// Collects offsets, lengths and digests of the chunks.
+impl<D> ChunkSink<D> for Vec<(u64, u64, D)>
+where
+    D: Clone,
+{
+    fn on_chunk(&mut self, offset: u64, length: u64, digest: &D) {
+        self.push((offset, length, digest.clone()));
+    }
+}
+
+/// Sends offsets, lengths and digests of the chunks, ignoring a disconnected receiver.
+impl<D> ChunkSink<D> for Sender<(u64, u64, D)>
+where
+    D: Clone,
+{
+    fn on_chunk(&mut self, offset: u64, length: u64, digest: &D) {
+        let _ = self.send((offset, length, digest.clone()));
+    }
+}
+
+/// Sends offsets, lengths and digests of the chunks, blocking while the channel is full and ignoring a disconnected
+/// receiver.
+impl<D> ChunkSink<D> for SyncSender<(u64, u64, D)>
+where
+    D: Clone,
+{
+    fn on_chunk(&mut self, offset: u64, length: u64, digest: &D) {
+        let _ = self.send((offset, length, digest.clone()));
+    }
+}
+
+impl<D, S> ChunkSink<D> for &mut S
+where
+    S: ChunkSink<D> + ?Sized,
+{
+    fn on_chunk(&mut self, offset: u64, length: u64, digest: &D) {
+        (**self).on_chunk(offset, length, digest);
+    }
+
+    fn on_finish(&mut self, length: u64, digest: &D) {
+        (**self).on_finish(length, digest);
+    }
+}
+
+/// Writes a chunk manifest, a line `<offset> <length> <digest>` for every chunk.
+///
+/// Failures cannot be reported while chunks are consumed, so writing stops at the first error, which is returned by
+/// [`ChunkLog::finish`].
+#[derive(Debug)]
+pub struct ChunkLog<W>
+where
+    W: Write,
+{
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W> ChunkLog<W>
+where
+    W: Write,
+{
+    /// Creates new [`ChunkLog`] writing into the given writer.
+    pub const fn new(inner: W) -> Self {
+        Self { inner, error: None }
+    }
+
+    /// Flushes the manifest and returns the underlying writer, or the first error.
+    pub fn finish(self) -> io::Result<W> {
+        let Self { mut inner, error } = self;
+        if let Some(error) = error {
+            return Err(error);
+        }
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+impl<D, W> ChunkSink<D> for ChunkLog<W>
+where
+    D: Display,
+    W: Write,
+{
+    fn on_chunk(&mut self, offset: u64, length: u64, digest: &D) {
+        if self.error.is_none() {
+            if let Err(error) = writeln!(self.inner, "{offset} {length} {digest}") {
+                self.error = Some(error);
+            }
+        }
+    }
+}
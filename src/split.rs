@@ -0,0 +1,405 @@
+use std::io::{self, Write};
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{ready, Context, Poll};
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+use crate::builder::{self, Suffix};
+#[cfg(feature = "async-runtime-tokio")]
+use crate::seal::Seal;
+#[cfg(feature = "async-runtime-tokio")]
+use crate::update::Pending;
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{Error, UpdateOrder, Writer};
+
+#[derive(Debug)]
+struct Shared<H> {
+    hash: Mutex<H>,
+    count: AtomicU64,
+}
+
+impl<H> Shared<H> {
+    fn new(hash: H, count: u64) -> (Arc<Self>, DigestHandle<H>) {
+        let shared = Self {
+            hash: Mutex::new(hash),
+            count: AtomicU64::new(count),
+        };
+        let shared = Arc::new(shared);
+        let handle = DigestHandle {
+            shared: Arc::clone(&shared),
+        };
+        (shared, handle)
+    }
+
+    fn record(&self, buf: &[u8])
+    where
+        H: Hash,
+    {
+        let mut hash = self.lock();
+        hash.update(buf);
+        self.count.fetch_add(buf.len() as u64, Ordering::Relaxed);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, H> {
+        self.hash.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Folds the suffix into the hash.
+    fn fold(&self, suffix: &mut Vec<Suffix>)
+    where
+        H: Hash,
+    {
+        let mut hash = self.lock();
+        builder::fold(&mut *hash, suffix, self.count());
+    }
+}
+
+/// Writing half of a split [`Writer`].
+///
+/// Created by [`Writer::split`]. The byte count, the suffix configured with [`Builder`](crate::Builder), the expected
+/// length, the update order and the error context carry over from the writer, only its statistics and report do not.
+#[derive(Debug)]
+pub struct SplitWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    inner: W,
+    shared: Arc<Shared<H>>,
+    suffix: Vec<Suffix>,
+    expected: Option<u64>,
+    order: UpdateOrder,
+    context: bool,
+}
+
+impl<W, H> SplitWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    pub(crate) fn new(writer: Writer<W, H>) -> (Self, DigestHandle<H>) {
+        let Writer {
+            inner,
+            hash,
+            count,
+            suffix,
+            expected,
+            order,
+            context,
+            ..
+        } = writer;
+        let (shared, handle) = Shared::new(hash, count);
+        let writer = Self {
+            inner,
+            shared,
+            suffix,
+            expected,
+            order,
+            context,
+        };
+        (writer, handle)
+    }
+
+    /// Flushes the writer and returns the final hash digest, including the suffix, like [`Writer::finalize`].
+    ///
+    /// Once finalized, the [`DigestHandle`] reports the final digest as well.
+    pub fn finalize(mut self) -> io::Result<H::Digest> {
+        self.flush()?;
+        let actual = self.shared.count();
+        if let Some(expected) = self.expected.filter(|&expected| expected != actual) {
+            return Err(Error::LengthMismatch { expected, actual }.into());
+        }
+        self.shared.fold(&mut self.suffix);
+        Ok(self.shared.lock().digest())
+    }
+
+    /// Unwraps this [`SplitWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.shared.lock().digest()
+    }
+}
+
+impl<W, H> Write for SplitWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let offset = self.shared.count();
+        // the lock is only taken to update the hash, so handles never block the IO itself
+        let result = match self.order {
+            UpdateOrder::AfterWrite => self.inner.write(buf),
+            UpdateOrder::BeforeWrite => {
+                self.shared.record(buf);
+                self.inner.write_all(buf).map(|()| buf.len())
+            },
+        };
+        match result {
+            Ok(n) => {
+                if self.order == UpdateOrder::AfterWrite {
+                    self.shared.record(&buf[..n]);
+                }
+                Ok(n)
+            },
+            Err(error) if self.context => Err(Error::write(offset, error).into()),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writing half of a split [`AsyncWriter`].
+///
+/// Created by [`AsyncWriter::split`]. The half can be lent to [`tokio::io::copy`] while its [`DigestHandle`] reports
+/// the progress of the transfer.
+///
+/// The byte count, the suffix configured with [`Builder`](crate::Builder), the sealing state and the error context
+/// carry over from the writer. Shutdown seals the half like [`AsyncWriter`], folding in the suffix, and writes to a
+/// sealed half result in [`Error::Finalized`].
+#[cfg(feature = "async-runtime-tokio")]
+#[derive(Debug)]
+pub struct AsyncSplitWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    inner: W,
+    shared: Arc<Shared<H>>,
+    suffix: Vec<Suffix>,
+    order: UpdateOrder,
+    pending: Pending,
+    context: bool,
+    seal: Seal,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncSplitWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+{
+    pub(crate) fn new(writer: AsyncWriter<W, H>) -> (Self, DigestHandle<H>) {
+        let AsyncWriter {
+            inner,
+            hash,
+            count,
+            suffix,
+            order,
+            pending,
+            context,
+            seal,
+            ..
+        } = writer;
+        let (shared, handle) = Shared::new(hash, count);
+        let writer = Self {
+            inner,
+            shared,
+            suffix,
+            order,
+            pending,
+            context,
+            seal,
+        };
+        (writer, handle)
+    }
+
+    /// Returns `true` once the half was shut down and the digest is final, see [`AsyncWriter::sealed`].
+    #[must_use]
+    pub const fn sealed(&self) -> bool {
+        matches!(self.seal, Seal::Sealed)
+    }
+
+    /// Unwraps this [`AsyncSplitWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.shared.lock().digest()
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWrite for AsyncSplitWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let Self {
+            inner,
+            shared,
+            order,
+            pending,
+            context,
+            seal,
+            ..
+        } = self.get_mut();
+        if *seal != Seal::Open {
+            let error = io::Error::from(Error::Finalized);
+            return Poll::Ready(Err(error));
+        }
+        let offset = shared.count() - pending.get();
+        let poll = pin!(inner).poll_write(cx, buf);
+        if let Some(data) = order.hashed(pending, buf, &poll) {
+            shared.record(data);
+        }
+        match poll {
+            Poll::Ready(Err(error)) if *context => Poll::Ready(Err(Error::write(offset, error).into())),
+            poll => poll,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self { inner, .. } = self.get_mut();
+        pin!(inner).poll_flush(cx)
+    }
+
+    /// Shuts down the underlying writer and seals the digest, see [`AsyncWriter`].
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let Self {
+            inner,
+            shared,
+            suffix,
+            seal,
+            ..
+        } = self.get_mut();
+        match seal {
+            Seal::Sealed => return Poll::Ready(Ok(())),
+            Seal::Open => *seal = Seal::Sealing,
+            Seal::Sealing => {},
+        }
+        ready!(pin!(inner).poll_shutdown(cx))?;
+        shared.fold(suffix);
+        *seal = Seal::Sealed;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Cheap handle for querying the state of a [`SplitWriter`] or an [`AsyncSplitWriter`], possibly from another thread.
+#[derive(Debug)]
+pub struct DigestHandle<H> {
+    shared: Arc<Shared<H>>,
+}
+
+impl<H> DigestHandle<H>
+where
+    H: Hash,
+{
+    /// Returns number of bytes hashed so far.
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.shared.count()
+    }
+
+    /// Returns snapshot of the hash digest of bytes written so far.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.shared.lock().digest()
+    }
+
+    /// Returns number of bytes hashed so far along with the matching digest snapshot.
+    #[must_use]
+    pub fn snapshot(&self) -> (u64, H::Digest) {
+        let hash = self.shared.lock();
+        let count = self.shared.count();
+        (count, hash.digest())
+    }
+}
+
+impl<H> Clone for DigestHandle<H> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::thread;
+
+    use chksum_core::Hash;
+
+    use crate::testing::Sha256;
+    use crate::{Builder, Writer};
+
+    #[test]
+    fn shares_count_and_digest() {
+        let mut writer = Writer::<_, Sha256>::new(Vec::new());
+        writer.write_all(b"hello ").unwrap();
+        let (mut writer, handle) = writer.split();
+        assert_eq!(handle.count(), 6);
+
+        let observer = handle.clone();
+        let hashed = thread::spawn(move || {
+            writer.write_all(b"world").unwrap();
+            let snapshot = observer.snapshot();
+            (writer, snapshot)
+        });
+        let (writer, snapshot) = hashed.join().unwrap();
+        assert_eq!(snapshot, (11, Sha256::hash(b"hello world")));
+        assert_eq!(handle.count(), 11);
+        assert_eq!(handle.digest(), writer.digest());
+        assert_eq!(writer.into_inner(), b"hello world");
+    }
+
+    #[test]
+    fn finalize_applies_suffix() {
+        let writer = Builder::<Sha256>::new().with_suffix("!").build(Vec::new());
+        let (mut writer, handle) = writer.split();
+        writer.write_all(b"data").unwrap();
+        assert_eq!(handle.digest(), Sha256::hash(b"data"));
+        assert_eq!(writer.finalize().unwrap(), Sha256::hash(b"data!"));
+        assert_eq!((handle.count(), handle.digest()), (4, Sha256::hash(b"data!")));
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_halves() {
+        use tokio::io::AsyncWriteExt;
+
+        use crate::testing::block_on;
+
+        let writer = Builder::<Sha256>::new().with_suffix("!").build_async(Vec::new());
+        let (mut writer, handle) = writer.split();
+        block_on(async {
+            writer.write_all(b"hello ").await.unwrap();
+            writer.write_all(b"world").await.unwrap();
+            assert_eq!((handle.count(), handle.digest()), (11, Sha256::hash(b"hello world")));
+            writer.shutdown().await.unwrap();
+            assert!(writer.sealed());
+            assert_eq!(handle.digest(), Sha256::hash(b"hello world!"));
+            assert!(writer.write_all(b"more").await.is_err());
+        });
+        assert_eq!(handle.count(), 11);
+        assert_eq!(writer.into_inner(), b"hello world");
+    }
+}
@@ -0,0 +1,292 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+use crate::temp::{SaveOptions, Temporary};
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::{Error, Writer};
+
+/// A trait for hashes able to export and import their internal state.
+pub trait ResumableHash: Hash {
+    /// Returns serialized internal state.
+    #[must_use]
+    fn export_state(&self) -> Vec<u8>;
+
+    /// Creates new hash from serialized internal state.
+    ///
+    /// Invalid state should result in an error of kind [`io::ErrorKind::InvalidData`].
+    fn import_state(state: &[u8]) -> io::Result<Self>;
+}
+
+/// Saved progress of a hashed stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct State {
+    /// Number of bytes hashed.
+    pub offset: u64,
+    /// Serialized internal state of the hash.
+    pub hash: Vec<u8>,
+}
+
+/// Partial results of an aborted writer, created by [`Writer::abort`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbortReport<W> {
+    /// Number of bytes written by the aborted writer, excluding the ones hashed before resuming.
+    pub bytes_written: u64,
+    /// Progress of the stream, which resumes it with [`Writer::resume_from`] once saved in a store.
+    pub partial_digest_state: State,
+    /// The underlying writer, for resuming the transfer or cleaning up after it.
+    pub inner: W,
+}
+
+/// A trait for stores of [`State`]s, keyed by an identifier.
+pub trait StateStore {
+    /// Saves the state, replacing previous one.
+    fn save(&mut self, id: &str, state: &State) -> io::Result<()>;
+
+    /// Loads the state, if any.
+    fn load(&self, id: &str) -> io::Result<Option<State>>;
+
+    /// Removes the state, if any.
+    fn remove(&mut self, id: &str) -> io::Result<()>;
+}
+
+/// [`StateStore`] keeping each state in a separate file of a directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsStateStore {
+    directory: PathBuf,
+    options: SaveOptions,
+}
+
+impl FsStateStore {
+    /// Creates new [`FsStateStore`] in the given directory, which must exist.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        let directory = directory.into();
+        Self {
+            directory,
+            options: SaveOptions::new(),
+        }
+    }
+
+    /// Sets options of saving the states.
+    #[must_use]
+    pub fn with_options(mut self, options: SaveOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        if id.is_empty() || id.starts_with('.') || id.contains(['/', '\\']) {
+            let error = io::Error::new(io::ErrorKind::InvalidInput, "invalid state identifier");
+            return Err(error);
+        }
+        Ok(self.directory.join(format!("{id}.state")))
+    }
+}
+
+impl StateStore for FsStateStore {
+    fn save(&mut self, id: &str, state: &State) -> io::Result<()> {
+        let path = self.path(id)?;
+        let (temporary, mut file) = Temporary::create(&path, &self.options)?;
+        file.write_all(&state.offset.to_be_bytes())?;
+        file.write_all(&state.hash)?;
+        file.sync_all()?;
+        temporary.persist(path)
+    }
+
+    fn load(&self, id: &str) -> io::Result<Option<State>> {
+        let data = match fs::read(self.path(id)?) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        if data.len() < 8 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated state"));
+        }
+        let (offset, hash) = data.split_at(8);
+        let offset = u64::from_be_bytes(offset.try_into().expect("8 bytes"));
+        let hash = hash.to_vec();
+        Ok(Some(State { offset, hash }))
+    }
+
+    fn remove(&mut self, id: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(id)?) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn import<H>(id: &str, state: &[u8]) -> Result<H, Error>
+where
+    H: ResumableHash,
+{
+    H::import_state(state).map_err(|source| {
+        Error::StateRestore {
+            id: id.to_owned(),
+            source,
+        }
+    })
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write,
+    H: ResumableHash,
+{
+    /// Creates new [`Writer`] resuming from the state saved in the store, or from scratch if there is none.
+    ///
+    /// The underlying writer should be positioned after the first [`Writer::count`] bytes of the stream.
+    ///
+    /// A state which cannot be imported results in [`Error::StateRestore`].
+    pub fn resume_from(store: &impl StateStore, id: &str, inner: W) -> Result<Self, Error> {
+        let mut writer = Self::new(inner);
+        if let Some(State { offset, hash }) = store.load(id)? {
+            writer.hash = import::<H>(id, &hash)?;
+            writer.count = offset;
+        }
+        Ok(writer)
+    }
+
+    /// Saves the current state to the store.
+    pub fn save_state(&self, store: &mut impl StateStore, id: &str) -> io::Result<()> {
+        let offset = self.count;
+        let hash = self.hash.export_state();
+        store.save(id, &State { offset, hash })
+    }
+
+    /// Aborts this writer, returning the partial results instead of losing them like dropping does.
+    ///
+    /// Nothing is flushed, so the underlying writer may lag behind the state if it buffers data.
+    #[must_use]
+    pub fn abort(self) -> AbortReport<W> {
+        let bytes_written = self.stats.bytes();
+        let partial_digest_state = State {
+            offset: self.count,
+            hash: self.hash.export_state(),
+        };
+        let Self { inner, .. } = self;
+        AbortReport {
+            bytes_written,
+            partial_digest_state,
+            inner,
+        }
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: ResumableHash,
+{
+    /// Creates new [`AsyncWriter`] resuming from the state saved in the store, or from scratch if there is none.
+    ///
+    /// The underlying writer should be positioned after the first [`AsyncWriter::count`] bytes of the stream.
+    ///
+    /// A state which cannot be imported results in [`Error::StateRestore`].
+    pub fn resume_from(store: &impl StateStore, id: &str, inner: W) -> Result<Self, Error> {
+        let mut writer = Self::new(inner);
+        if let Some(State { offset, hash }) = store.load(id)? {
+            writer.hash = import::<H>(id, &hash)?;
+            writer.count = offset;
+        }
+        Ok(writer)
+    }
+
+    /// Saves the current state to the store.
+    pub fn save_state(&self, store: &mut impl StateStore, id: &str) -> io::Result<()> {
+        let offset = self.count;
+        let hash = self.hash.export_state();
+        store.save(id, &State { offset, hash })
+    }
+
+    /// Aborts this writer, returning the partial results instead of losing them like dropping does.
+    ///
+    /// Nothing is flushed, so the underlying writer may lag behind the state if it buffers data.
+    #[must_use]
+    pub fn abort(self) -> AbortReport<W> {
+        let bytes_written = self.stats.bytes();
+        let partial_digest_state = State {
+            offset: self.count,
+            hash: self.hash.export_state(),
+        };
+        let Self { inner, .. } = self;
+        AbortReport {
+            bytes_written,
+            partial_digest_state,
+            inner,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::{fs, io, process, thread};
+
+    use super::{FsStateStore, State, StateStore};
+
+    fn directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chksum-writer-state-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = directory("round-trip");
+        let mut store = FsStateStore::new(&path);
+        assert_eq!(store.load("upload").unwrap(), None);
+        let state = State {
+            offset: 1 << 40,
+            hash: b"state".to_vec(),
+        };
+        store.save("upload", &state).unwrap();
+        assert_eq!(store.load("upload").unwrap(), Some(state));
+        store.save("upload", &State::default()).unwrap();
+        assert_eq!(store.load("upload").unwrap(), Some(State::default()));
+        store.remove("upload").unwrap();
+        store.remove("upload").unwrap();
+        assert_eq!(store.load("upload").unwrap(), None);
+
+        fs::write(path.join("short.state"), [0; 7]).unwrap();
+        assert_eq!(store.load("short").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        for id in ["", ".hidden", "a/b", "a\\b"] {
+            assert_eq!(store.load(id).unwrap_err().kind(), io::ErrorKind::InvalidInput, "{id}");
+        }
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_saves() {
+        let path = directory("concurrent");
+        let savers: Vec<_> = (0..8u8)
+            .map(|index| {
+                let mut store = FsStateStore::new(&path);
+                thread::spawn(move || {
+                    let state = State {
+                        offset: u64::from(index),
+                        hash: vec![index; 4096],
+                    };
+                    for _ in 0..20 {
+                        store.save("shared", &state).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for saver in savers {
+            saver.join().unwrap();
+        }
+        let state = FsStateStore::new(&path).load("shared").unwrap().unwrap();
+        assert_eq!(state.hash, vec![state.offset as u8; 4096]);
+        // only the saved state is left
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 1);
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
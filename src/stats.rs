@@ -0,0 +1,50 @@
+/// Statistics of write calls, with a histogram of their sizes.
+///
+/// Many small writes are a sign that the writer should be wrapped in a buffer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteStats {
+    writes: u64,
+    bytes: u64,
+    histogram: [u64; 8],
+}
+
+impl WriteStats {
+    /// Lower bounds of the histogram buckets, each bucket counts writes smaller than the next bound.
+    pub const BOUNDS: [usize; 8] = [0, 16, 64, 256, 1024, 4096, 16 * 1024, 64 * 1024];
+
+    /// Creates new empty [`WriteStats`].
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            writes: 0,
+            bytes: 0,
+            histogram: [0; 8],
+        }
+    }
+
+    /// Returns number of successful write calls.
+    #[must_use]
+    pub const fn writes(&self) -> u64 {
+        self.writes
+    }
+
+    /// Returns number of bytes written.
+    #[must_use]
+    pub const fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns number of writes in each bucket defined by [`WriteStats::BOUNDS`].
+    #[must_use]
+    pub const fn histogram(&self) -> &[u64; 8] {
+        &self.histogram
+    }
+
+    /// Records a write of `n` bytes.
+    pub(crate) fn record(&mut self, n: usize) {
+        self.writes += 1;
+        self.bytes += n as u64;
+        let bucket = Self::BOUNDS.iter().rposition(|&bound| n >= bound).unwrap_or(0);
+        self.histogram[bucket] += 1;
+    }
+}
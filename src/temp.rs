@@ -0,0 +1,369 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(windows)]
+use std::thread;
+#[cfg(windows)]
+use std::time::Duration;
+
+/// Number of retries of a rename failing due to another process holding the file open.
+#[cfg(windows)]
+const RETRIES: u32 = 5;
+/// Delay before the first retry, doubled with every next one.
+#[cfg(windows)]
+const BACKOFF: Duration = Duration::from_millis(10);
+/// Error of files opened by another process without sharing deletes, returned by renames replacing them.
+#[cfg(windows)]
+const ERROR_ACCESS_DENIED: i32 = 5;
+/// Error of files opened by another process without sharing access.
+#[cfg(windows)]
+const ERROR_SHARING_VIOLATION: i32 = 32;
+
+/// Error of renames across file systems, `EXDEV`.
+#[cfg(not(windows))]
+const CROSS_DEVICE: i32 = 18;
+/// Error of renames across file systems, `ERROR_NOT_SAME_DEVICE`.
+#[cfg(windows)]
+const CROSS_DEVICE: i32 = 17;
+
+/// Counter distinguishing temporary files created by one process.
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Options of saves replacing files through temporary files, used by [`FsStateStore`](crate::FsStateStore) and
+/// [`FileStore`](crate::FileStore).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SaveOptions {
+    keep_temp_on_failure: bool,
+    temp_dir: Option<PathBuf>,
+    #[cfg(unix)]
+    mode: Option<u32>,
+}
+
+impl SaveOptions {
+    /// Creates new [`SaveOptions`] with defaults.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the temporary file of a failed save instead of removing it, for debugging.
+    #[must_use]
+    pub const fn keep_temp_on_failure(mut self, keep: bool) -> Self {
+        self.keep_temp_on_failure = keep;
+        self
+    }
+
+    /// Sets permissions of the saved file to the given mode, like `0o640`, instead of keeping the permissions of the
+    /// replaced file.
+    ///
+    /// The mode is applied to the temporary file before it replaces the target, so the saved file never has other
+    /// permissions, and it is not affected by the umask.
+    #[cfg(unix)]
+    #[must_use]
+    pub const fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Creates temporary files in the given directory instead of next to the target file.
+    ///
+    /// If the directory is on another file system than the target, the temporary file is copied next to the target,
+    /// synced and compared with the original before it replaces the target, so saves take longer, but still never
+    /// leave a partially written target behind.
+    #[must_use]
+    pub fn with_temp_dir(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(directory.into());
+        self
+    }
+}
+
+/// Temporary file replacing the target file once persisted, removed on drop otherwise.
+///
+/// Removal on drop covers early returns on errors as well as unwinding panics, so failed saves leave no files behind
+/// unless [`SaveOptions::keep_temp_on_failure`] is set.
+///
+/// It is created next to the target file, unless [`SaveOptions::with_temp_dir`] is set, and copied to the file system
+/// of the target when the rename fails with a cross-device error.
+#[derive(Debug)]
+pub(crate) struct Temporary {
+    path: PathBuf,
+    persisted: bool,
+    keep: bool,
+    #[cfg(unix)]
+    mode: Option<u32>,
+}
+
+impl Temporary {
+    /// Creates new temporary file next to the target file, or in the temporary directory of the options.
+    ///
+    /// The name is the name of the target followed by the process ID, a counter and `.tmp`, and the file is created
+    /// exclusively, so concurrent saves of the same target never write to the same temporary file.
+    pub(crate) fn create(target: &Path, options: &SaveOptions) -> io::Result<(Self, File)> {
+        let name = target
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "target path has no file name"))?;
+        loop {
+            let mut temporary = name.to_os_string();
+            temporary.push(format!(
+                ".{}.{}.tmp",
+                process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            let path = match &options.temp_dir {
+                Some(directory) => directory.join(temporary),
+                None => target.with_file_name(temporary),
+            };
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => {
+                    let temporary = Self {
+                        path,
+                        persisted: false,
+                        keep: options.keep_temp_on_failure,
+                        #[cfg(unix)]
+                        mode: options.mode,
+                    };
+                    return Ok((temporary, file));
+                },
+                // left behind by a process with the same ID
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {},
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Renames the temporary file to the target path, replacing it.
+    ///
+    /// Permissions of the replaced file, or the mode set with [`SaveOptions::with_mode`], are applied to the temporary
+    /// file before the rename, so the target never becomes accessible to more users than intended.
+    ///
+    /// If the temporary file is on another file system, it is copied next to the target first, and the copy is
+    /// synced and compared with the temporary file. A copy which differs results in an error of kind
+    /// [`io::ErrorKind::InvalidData`], and the target is left untouched.
+    ///
+    /// On Windows, [`fs::rename`] replaces the target with `MoveFileExW` and fails while another process, like an
+    /// antivirus scanner or an indexer, holds either file open. Such failures are retried with exponential backoff.
+    pub(crate) fn persist(mut self, target: impl AsRef<Path>) -> io::Result<()> {
+        let target = target.as_ref();
+        if let Some(permissions) = self.permissions() {
+            fs::set_permissions(&self.path, permissions)?;
+        } else {
+            match fs::metadata(target) {
+                Ok(metadata) => fs::set_permissions(&self.path, metadata.permissions())?,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {},
+                Err(error) => return Err(error),
+            }
+        }
+        match replace(&self.path, target) {
+            Err(error) if is_cross_device(&error) => {
+                self.copy_to(target)?;
+                // the copy replaced the target, so the temporary file is not needed even for debugging
+                fs::remove_file(&self.path)?;
+            },
+            result => result?,
+        }
+        self.persisted = true;
+        Ok(())
+    }
+
+    /// Returns permissions set by the options, if any.
+    fn permissions(&self) -> Option<fs::Permissions> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            self.mode.map(fs::Permissions::from_mode)
+        }
+        #[cfg(not(unix))]
+        None
+    }
+
+    /// Replaces the target with a verified copy of the temporary file created next to it.
+    fn copy_to(&self, target: &Path) -> io::Result<()> {
+        let options = SaveOptions::new().keep_temp_on_failure(self.keep);
+        let (mut copy, mut file) = Self::create(target, &options)?;
+        io::copy(&mut File::open(&self.path)?, &mut file)?;
+        file.sync_all()?;
+        drop(file);
+        if !same_contents(&self.path, &copy.path)? {
+            let error = io::Error::new(io::ErrorKind::InvalidData, "copy of temporary file differs");
+            return Err(error);
+        }
+        fs::set_permissions(&copy.path, fs::metadata(&self.path)?.permissions())?;
+        replace(&copy.path, target)?;
+        copy.persisted = true;
+        Ok(())
+    }
+}
+
+/// Renames the file to the target path, replacing it.
+#[cfg(not(windows))]
+fn replace(path: &Path, target: &Path) -> io::Result<()> {
+    fs::rename(path, target)
+}
+
+/// Renames the file to the target path, replacing it, and retries renames failing due to sharing violations.
+///
+/// [`fs::rename`] replaces the target atomically with `MoveFileExW` and `MOVEFILE_REPLACE_EXISTING`; calling
+/// `ReplaceFileW` directly would need unsafe code, which the crate forbids.
+#[cfg(windows)]
+fn replace(path: &Path, target: &Path) -> io::Result<()> {
+    let mut delay = BACKOFF;
+    let mut retries = RETRIES;
+    loop {
+        match fs::rename(path, target) {
+            Err(error) if retries > 0 && is_sharing_violation(&error) => {
+                thread::sleep(delay);
+                delay *= 2;
+                retries -= 1;
+            },
+            result => break result,
+        }
+    }
+}
+
+/// Returns `true` if both files have the same contents.
+fn same_contents(left: &Path, right: &Path) -> io::Result<bool> {
+    let mut left = BufReader::new(File::open(left)?);
+    let mut right = BufReader::new(File::open(right)?);
+    loop {
+        let (chunk, other) = (left.fill_buf()?, right.fill_buf()?);
+        if chunk.is_empty() || other.is_empty() {
+            return Ok(chunk.is_empty() && other.is_empty());
+        }
+        let length = chunk.len().min(other.len());
+        if chunk[..length] != other[..length] {
+            return Ok(false);
+        }
+        left.consume(length);
+        right.consume(length);
+    }
+}
+
+impl Drop for Temporary {
+    fn drop(&mut self) {
+        if !self.persisted && !self.keep {
+            // the file may not exist anymore, and there is no way to report the error
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Returns `true` for errors of renames across file systems.
+fn is_cross_device(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(CROSS_DEVICE)
+}
+
+/// Returns `true` for errors of files held open by another process.
+#[cfg(windows)]
+fn is_sharing_violation(error: &io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(ERROR_ACCESS_DENIED | ERROR_SHARING_VIOLATION)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::{fs, process};
+
+    use super::{same_contents, SaveOptions, Temporary};
+
+    fn directory(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chksum-writer-temp-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn compares_contents() {
+        let directory = directory("compare");
+        let files = [
+            ("a", &b"data"[..]),
+            ("b", b"data"),
+            ("c", b"date"),
+            ("d", b"dat"),
+            ("e", b""),
+        ];
+        for (name, data) in files {
+            fs::write(directory.join(name), data).unwrap();
+        }
+        let same = |left: &str, right: &str| same_contents(&directory.join(left), &directory.join(right)).unwrap();
+        assert!(same("a", "b"));
+        assert!(same("e", "e"));
+        assert!(!same("a", "c"));
+        assert!(!same("a", "d"));
+        assert!(!same("d", "a"));
+        assert!(!same("e", "a"));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn persists_from_temp_dir() {
+        let directory = directory("temp-dir");
+        let temporaries = directory.join("temporaries");
+        fs::create_dir(&temporaries).unwrap();
+        let target = directory.join("target");
+        fs::write(&target, "old").unwrap();
+        let options = SaveOptions::new().with_temp_dir(&temporaries);
+        let (temporary, _) = Temporary::create(&target, &options).unwrap();
+        fs::write(&temporary.path, "new").unwrap();
+        temporary.persist(&target).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&temporaries).unwrap().count(), 0);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn applies_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = directory("mode");
+        let mode = |path: &PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let (new, existing) = (directory.join("new"), directory.join("existing"));
+        fs::write(&existing, "old").unwrap();
+        fs::set_permissions(&existing, fs::Permissions::from_mode(0o600)).unwrap();
+        for target in [&new, &existing] {
+            let (temporary, _) = Temporary::create(target, &SaveOptions::new()).unwrap();
+            temporary.persist(target).unwrap();
+        }
+        assert_eq!(mode(&existing), 0o600);
+
+        for target in [&new, &existing] {
+            let (temporary, _) = Temporary::create(target, &SaveOptions::new().with_mode(0o640)).unwrap();
+            temporary.persist(target).unwrap();
+            assert_eq!(mode(target), 0o640);
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copies_across_file_systems() {
+        use std::os::unix::fs::MetadataExt;
+
+        // shared memory is usually a separate file system
+        let temporaries = PathBuf::from("/dev/shm");
+        let directory = directory("cross-device");
+        let Ok(metadata) = fs::metadata(&temporaries) else {
+            return;
+        };
+        if metadata.dev() == fs::metadata(&directory).unwrap().dev() {
+            return;
+        }
+        let target = directory.join("target");
+        let options = SaveOptions::new().with_temp_dir(&temporaries);
+        let (temporary, _) = Temporary::create(&target, &options).unwrap();
+        let path = temporary.path.clone();
+        fs::write(&path, vec![7; 100_000]).unwrap();
+        temporary.persist(&target).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), vec![7; 100_000]);
+        assert!(!path.exists());
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,513 @@
+//! Utilities for testing hashing pipelines against edge cases.
+
+use std::io::{self, IoSlice, Write};
+use std::num::NonZeroUsize;
+#[cfg(feature = "async-runtime-tokio")]
+use std::pin::{pin, Pin};
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::{ready, Context, Poll};
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+use crate::Writer;
+
+/// Condition under which [`FailingWriter`] fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Failure {
+    AfterBytes(u64),
+    OnCall(NonZeroUsize),
+}
+
+/// Wraps a writer and fails with an error of kind [`io::ErrorKind::Other`] once the condition is met.
+///
+/// Wrapping an asynchronous writer, every poll of a write or flush counts as a call.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailingWriter<W> {
+    inner: W,
+    failure: Failure,
+    written: u64,
+    calls: usize,
+}
+
+impl<W> FailingWriter<W> {
+    /// Creates new [`FailingWriter`] accepting `n` bytes and failing every write afterwards.
+    ///
+    /// The write crossing the limit is shortened to end exactly at it.
+    pub const fn after_bytes(inner: W, n: u64) -> Self {
+        Self::new(inner, Failure::AfterBytes(n))
+    }
+
+    /// Creates new [`FailingWriter`] failing the `k`-th call to write or flush, counting from one.
+    pub const fn on_call(inner: W, k: NonZeroUsize) -> Self {
+        Self::new(inner, Failure::OnCall(k))
+    }
+
+    const fn new(inner: W, failure: Failure) -> Self {
+        Self {
+            inner,
+            failure,
+            written: 0,
+            calls: 0,
+        }
+    }
+
+    /// Unwraps this [`FailingWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    fn call(&mut self) -> io::Result<()> {
+        self.calls += 1;
+        match self.failure {
+            Failure::OnCall(k) if self.calls == k.get() => Err(injected()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the part of the buffer to pass to the underlying writer.
+    fn admit<'a>(&mut self, buf: &'a [u8]) -> io::Result<&'a [u8]> {
+        self.call()?;
+        match self.failure {
+            Failure::AfterBytes(n) => {
+                let remaining = n - self.written;
+                if remaining == 0 && !buf.is_empty() {
+                    return Err(injected());
+                }
+                Ok(&buf[..buf.len().min(usize::try_from(remaining).unwrap_or(usize::MAX))])
+            },
+            Failure::OnCall(_) => Ok(buf),
+        }
+    }
+}
+
+impl<W> Write for FailingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buf = self.admit(buf)?;
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.call()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W> AsyncWrite for FailingWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        let buf = this.admit(buf)?;
+        let n = ready!(pin!(&mut this.inner).poll_write(cx, buf))?;
+        this.written += n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        this.call()?;
+        pin!(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        pin!(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+fn injected() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "injected failure")
+}
+
+/// Wraps a writer and accepts at most the given number of bytes per write.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShortWriter<W> {
+    inner: W,
+    limit: NonZeroUsize,
+}
+
+impl<W> ShortWriter<W> {
+    /// Creates new [`ShortWriter`] accepting at most `limit` bytes per write.
+    pub const fn new(inner: W, limit: NonZeroUsize) -> Self {
+        Self { inner, limit }
+    }
+
+    /// Unwraps this [`ShortWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+}
+
+impl<W> Write for ShortWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(self.limit.get());
+        self.inner.write(&buf[..length])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W> AsyncWrite for ShortWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        let length = buf.len().min(this.limit.get());
+        pin!(&mut this.inner).poll_write(cx, &buf[..length])
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        pin!(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        pin!(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+/// Wraps a writer and fails every `n`-th call to write or flush with an error of kind
+/// [`io::ErrorKind::Interrupted`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InterruptingWriter<W> {
+    inner: W,
+    every: NonZeroUsize,
+    calls: usize,
+}
+
+impl<W> InterruptingWriter<W>
+where
+    W: Write,
+{
+    /// Creates new [`InterruptingWriter`] interrupting every `n`-th call, counting from one.
+    pub const fn new(inner: W, every: NonZeroUsize) -> Self {
+        Self { inner, every, calls: 0 }
+    }
+
+    /// Unwraps this [`InterruptingWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    fn call(&mut self) -> io::Result<()> {
+        self.calls += 1;
+        if self.calls % self.every.get() == 0 {
+            Err(io::Error::new(io::ErrorKind::Interrupted, "injected interruption"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<W> Write for InterruptingWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.call()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.call()?;
+        self.inner.flush()
+    }
+}
+
+/// Wraps an asynchronous writer and returns [`Poll::Pending`] the given number of times before each operation is
+/// passed to it, waking the task right away.
+#[cfg(feature = "async-runtime-tokio")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlowAsyncWriter<W> {
+    inner: W,
+    pending: usize,
+    remaining: usize,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W> SlowAsyncWriter<W>
+where
+    W: AsyncWrite,
+{
+    /// Creates new [`SlowAsyncWriter`] returning [`Poll::Pending`] `pending` times before each operation.
+    pub const fn new(inner: W, pending: usize) -> Self {
+        Self {
+            inner,
+            pending,
+            remaining: pending,
+        }
+    }
+
+    /// Unwraps this [`SlowAsyncWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { inner, .. } = self;
+        inner
+    }
+
+    /// Returns `true` if the operation should be delayed once more.
+    fn delay(&mut self, cx: &mut Context<'_>) -> bool {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            cx.waker().wake_by_ref();
+            true
+        } else {
+            self.remaining = self.pending;
+            false
+        }
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W> AsyncWrite for SlowAsyncWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize, io::Error>> {
+        let this = self.get_mut();
+        if this.delay(cx) {
+            return Poll::Pending;
+        }
+        pin!(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        if this.delay(cx) {
+            return Poll::Pending;
+        }
+        pin!(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        let this = self.get_mut();
+        if this.delay(cx) {
+            return Poll::Pending;
+        }
+        pin!(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Layout of writes delivering a stream, for property testing writers.
+///
+/// Patterns are derived deterministically from a seed, so any property testing framework can drive them by generating
+/// the seed, and a failing case can be reproduced from the seed alone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WritePattern {
+    /// Lengths of consecutive chunks, summing to the length of the stream.
+    pub chunks: Vec<usize>,
+    /// Whether chunks are grouped into vectored writes.
+    pub vectored: bool,
+    /// Maximal number of bytes accepted by the underlying writer per write, if limited.
+    pub limit: Option<NonZeroUsize>,
+}
+
+impl WritePattern {
+    /// Creates new [`WritePattern`] for a stream of the given length from the seed.
+    #[must_use]
+    pub fn from_seed(seed: u64, length: usize) -> Self {
+        let mut rng = XorShift::new(seed);
+        let mut chunks = Vec::new();
+        let mut remaining = length;
+        while remaining > 0 {
+            // favour short chunks, with an occasional large one
+            let bound = if rng.next() % 8 == 0 {
+                remaining
+            } else {
+                remaining.min(16)
+            };
+            let chunk = 1 + rng.below(bound);
+            chunks.push(chunk);
+            remaining -= chunk;
+        }
+        let vectored = rng.next() % 2 == 0;
+        let limit = NonZeroUsize::new(rng.below(8));
+        Self {
+            chunks,
+            vectored,
+            limit,
+        }
+    }
+
+    /// Writes the data into [`Writer`] following this pattern and returns the written bytes with the digest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of the data differs from the sum of the chunk lengths.
+    pub fn write<H>(&self, data: &[u8]) -> (Vec<u8>, H::Digest)
+    where
+        H: Hash,
+    {
+        assert_eq!(
+            self.chunks.iter().sum::<usize>(),
+            data.len(),
+            "pattern does not match data length"
+        );
+        let limit = self.limit.unwrap_or(NonZeroUsize::MAX);
+        let mut writer = Writer::<_, H>::new(ShortWriter::new(Vec::new(), limit));
+        let mut offset = 0;
+        let mut chunks = self.chunks.iter();
+        while let Some(&chunk) = chunks.next() {
+            if self.vectored {
+                let mut slices = vec![&data[offset..offset + chunk]];
+                offset += chunk;
+                if let Some(&chunk) = chunks.next() {
+                    slices.push(&data[offset..offset + chunk]);
+                    offset += chunk;
+                }
+                write_all_vectored(&mut writer, &slices);
+            } else {
+                writer
+                    .write_all(&data[offset..offset + chunk])
+                    .expect("write to vector");
+                offset += chunk;
+            }
+        }
+        let digest = writer.digest();
+        (writer.into_inner().into_inner(), digest)
+    }
+}
+
+fn write_all_vectored(writer: &mut impl Write, slices: &[&[u8]]) {
+    let (mut index, mut offset) = (0, 0);
+    while index < slices.len() {
+        let mut bufs = vec![IoSlice::new(&slices[index][offset..])];
+        bufs.extend(slices[index + 1..].iter().map(|slice| IoSlice::new(slice)));
+        let mut n = writer.write_vectored(&bufs).expect("write to vector");
+        assert!(
+            n > 0 || bufs.iter().all(|buf| buf.is_empty()),
+            "vectored write made no progress"
+        );
+        while index < slices.len() && n >= slices[index].len() - offset {
+            n -= slices[index].len() - offset;
+            (index, offset) = (index + 1, 0);
+        }
+        offset += n;
+    }
+}
+
+/// Asserts that writing the data following the pattern produces the data unchanged and the digest of the one-shot
+/// hash.
+///
+/// # Panics
+///
+/// Panics with the pattern in the message if the assertion fails.
+#[track_caller]
+pub fn assert_pattern_matches_oneshot<H>(data: &[u8], pattern: &WritePattern)
+where
+    H: Hash,
+    H::Digest: PartialEq,
+{
+    let (written, digest) = pattern.write::<H>(data);
+    if let Some(message) = data_mismatch(data, &written) {
+        panic!("{message} for {pattern:?}");
+    }
+    if let Some(message) = digest_mismatch::<H>(data, &digest) {
+        panic!("{message} for {pattern:?}");
+    }
+}
+
+/// Asserts that the digest equals the digest of the data calculated with the one-shot hash.
+///
+/// # Panics
+///
+/// Panics with both digests and the length of the data in the message if the assertion fails.
+#[track_caller]
+pub fn assert_digest_matches_oneshot<H>(data: &[u8], digest: &H::Digest)
+where
+    H: Hash,
+    H::Digest: PartialEq,
+{
+    if let Some(message) = digest_mismatch::<H>(data, digest) {
+        panic!("{message}");
+    }
+}
+
+/// Asserts that the written data equals the expected data.
+///
+/// # Panics
+///
+/// Panics with the offset of the first differing byte in the message if the assertion fails.
+#[track_caller]
+pub fn assert_data_matches(expected: &[u8], written: &[u8]) {
+    if let Some(message) = data_mismatch(expected, written) {
+        panic!("{message}");
+    }
+}
+
+fn digest_mismatch<H>(data: &[u8], digest: &H::Digest) -> Option<String>
+where
+    H: Hash,
+    H::Digest: PartialEq,
+{
+    let expected = H::hash(data);
+    (*digest != expected).then(|| {
+        let length = data.len();
+        format!("digest differs from one-shot hash of {length} bytes\n  expected: {expected}\n    actual: {digest}")
+    })
+}
+
+fn data_mismatch(expected: &[u8], written: &[u8]) -> Option<String> {
+    let offset = expected
+        .iter()
+        .zip(written)
+        .position(|(expected, written)| expected != written)
+        .or_else(|| (expected.len() != written.len()).then_some(expected.len().min(written.len())))?;
+    let excerpt = |data: &[u8]| data[offset..data.len().min(offset + 16)].to_vec();
+    let (expected_length, written_length) = (expected.len(), written.len());
+    Some(format!(
+        "written data differs at offset {offset} ({expected_length} bytes expected, {written_length} written)\n  \
+         expected: {:02x?}\n   written: {:02x?}",
+        excerpt(expected),
+        excerpt(written),
+    ))
+}
+
+/// Minimal xorshift generator, good enough for deriving write patterns.
+struct XorShift(u64);
+
+impl XorShift {
+    const fn new(seed: u64) -> Self {
+        const MIX: u64 = 0x9E37_79B9_7F4A_7C15;
+        // zero is a fixed point of xorshift
+        match seed ^ MIX {
+            0 => Self(MIX),
+            state => Self(state),
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns number in range `0..bound`, or zero for zero bound.
+    fn below(&mut self, bound: usize) -> usize {
+        match bound {
+            0 => 0,
+            bound => usize::try_from(self.next() % bound as u64).unwrap_or(0),
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! Reference hashes and helpers shared by the unit tests.
+
+use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "async-runtime-tokio")]
+use std::future::Future;
+
+use chksum_core::{Digest, Hash};
+
+use crate::Algorithm;
+
+/// Digest of the reference hashes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub(crate) struct TestDigest(Vec<u8>);
+
+impl AsRef<[u8]> for TestDigest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Display for TestDigest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl Digest for TestDigest {}
+
+/// Compression function of a Merkle-Damgård hash with 64-byte blocks.
+pub(crate) trait Engine: Clone + Default {
+    /// Whether the length in the padding and the words of the state are big-endian.
+    const BIG_ENDIAN: bool;
+
+    fn compress(&mut self, block: &[u8; 64]);
+
+    fn output(&self) -> Vec<u8>;
+}
+
+/// Reference Merkle-Damgård hash built on the compression function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Md<E> {
+    engine: E,
+    buffer: Vec<u8>,
+    length: u64,
+}
+
+impl<E> Hash for Md<E>
+where
+    E: Engine,
+{
+    type Digest = TestDigest;
+
+    fn update<T>(&mut self, data: T)
+    where
+        T: AsRef<[u8]>,
+    {
+        let data = data.as_ref();
+        self.length += data.len() as u64;
+        self.buffer.extend_from_slice(data);
+        let blocks = self.buffer.len() / 64 * 64;
+        for block in self.buffer[..blocks].chunks_exact(64) {
+            self.engine.compress(block.try_into().unwrap());
+        }
+        self.buffer.drain(..blocks);
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn digest(&self) -> TestDigest {
+        let mut engine = self.engine.clone();
+        let mut tail = self.buffer.clone();
+        tail.push(0x80);
+        while tail.len() % 64 != 56 {
+            tail.push(0);
+        }
+        let bits = self.length.wrapping_mul(8);
+        tail.extend_from_slice(
+            &if E::BIG_ENDIAN {
+                bits.to_be_bytes()
+            } else {
+                bits.to_le_bytes()
+            },
+        );
+        for block in tail.chunks_exact(64) {
+            engine.compress(block.try_into().unwrap());
+        }
+        TestDigest(engine.output())
+    }
+}
+
+fn words<const N: usize>(block: &[u8; 64], big_endian: bool) -> [u32; N] {
+    let mut words = [0; N];
+    for (word, bytes) in words.iter_mut().zip(block.chunks_exact(4)) {
+        let bytes = bytes.try_into().unwrap();
+        *word = if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+    }
+    words
+}
+
+fn output(state: &[u32], big_endian: bool) -> Vec<u8> {
+    state
+        .iter()
+        .flat_map(|word| {
+            if big_endian {
+                word.to_be_bytes()
+            } else {
+                word.to_le_bytes()
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Sha1Engine([u32; 5]);
+
+impl Default for Sha1Engine {
+    fn default() -> Self {
+        Self([0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476, 0xC3D2_E1F0])
+    }
+}
+
+impl Engine for Sha1Engine {
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0; 80];
+        w[..16].copy_from_slice(&words::<16>(block, true));
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = self.0;
+        for (i, &w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            (a, b, c, d, e) = (t, a, b.rotate_left(30), c, d);
+        }
+        for (state, value) in self.0.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    fn output(&self) -> Vec<u8> {
+        output(&self.0, true)
+    }
+}
+
+const SHA256_K: [u32; 64] = [
+    0x428A_2F98,
+    0x7137_4491,
+    0xB5C0_FBCF,
+    0xE9B5_DBA5,
+    0x3956_C25B,
+    0x59F1_11F1,
+    0x923F_82A4,
+    0xAB1C_5ED5,
+    0xD807_AA98,
+    0x1283_5B01,
+    0x2431_85BE,
+    0x550C_7DC3,
+    0x72BE_5D74,
+    0x80DE_B1FE,
+    0x9BDC_06A7,
+    0xC19B_F174,
+    0xE49B_69C1,
+    0xEFBE_4786,
+    0x0FC1_9DC6,
+    0x240C_A1CC,
+    0x2DE9_2C6F,
+    0x4A74_84AA,
+    0x5CB0_A9DC,
+    0x76F9_88DA,
+    0x983E_5152,
+    0xA831_C66D,
+    0xB003_27C8,
+    0xBF59_7FC7,
+    0xC6E0_0BF3,
+    0xD5A7_9147,
+    0x06CA_6351,
+    0x1429_2967,
+    0x27B7_0A85,
+    0x2E1B_2138,
+    0x4D2C_6DFC,
+    0x5338_0D13,
+    0x650A_7354,
+    0x766A_0ABB,
+    0x81C2_C92E,
+    0x9272_2C85,
+    0xA2BF_E8A1,
+    0xA81A_664B,
+    0xC24B_8B70,
+    0xC76C_51A3,
+    0xD192_E819,
+    0xD699_0624,
+    0xF40E_3585,
+    0x106A_A070,
+    0x19A4_C116,
+    0x1E37_6C08,
+    0x2748_774C,
+    0x34B0_BCB5,
+    0x391C_0CB3,
+    0x4ED8_AA4A,
+    0x5B9C_CA4F,
+    0x682E_6FF3,
+    0x748F_82EE,
+    0x78A5_636F,
+    0x84C8_7814,
+    0x8CC7_0208,
+    0x90BE_FFFA,
+    0xA450_6CEB,
+    0xBEF9_A3F7,
+    0xC671_78F2,
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Sha256Engine([u32; 8]);
+
+impl Default for Sha256Engine {
+    fn default() -> Self {
+        Self([
+            0x6A09_E667,
+            0xBB67_AE85,
+            0x3C6E_F372,
+            0xA54F_F53A,
+            0x510E_527F,
+            0x9B05_688C,
+            0x1F83_D9AB,
+            0x5BE0_CD19,
+        ])
+    }
+}
+
+impl Engine for Sha256Engine {
+    const BIG_ENDIAN: bool = true;
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0; 64];
+        w[..16].copy_from_slice(&words::<16>(block, true));
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.0;
+        for (&k, &w) in SHA256_K.iter().zip(&w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(k).wrapping_add(w);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            (a, b, c, d, e, f, g, h) = (t1.wrapping_add(t2), a, b, c, d.wrapping_add(t1), e, f, g);
+        }
+        for (state, value) in self.0.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    fn output(&self) -> Vec<u8> {
+        output(&self.0, true)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Md4Engine([u32; 4]);
+
+impl Default for Md4Engine {
+    fn default() -> Self {
+        Self([0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476])
+    }
+}
+
+impl Engine for Md4Engine {
+    const BIG_ENDIAN: bool = false;
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let x = words::<16>(block, false);
+        let [mut a, mut b, mut c, mut d] = self.0;
+        for (round, k, shift) in [
+            (0, 0, [3, 7, 11, 19]),
+            (1, 0x5A82_7999, [3, 5, 9, 13]),
+            (2, 0x6ED9_EBA1, [3, 9, 11, 15]),
+        ] {
+            for i in 0..16 {
+                let (f, index) = match round {
+                    0 => ((b & c) | (!b & d), i),
+                    1 => ((b & c) | (b & d) | (c & d), i % 4 * 4 + i / 4),
+                    _ => (b ^ c ^ d, [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15][i]),
+                };
+                let t = a
+                    .wrapping_add(f)
+                    .wrapping_add(x[index])
+                    .wrapping_add(k)
+                    .rotate_left(shift[i % 4]);
+                (a, b, c, d) = (d, t, b, c);
+            }
+        }
+        for (state, value) in self.0.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    fn output(&self) -> Vec<u8> {
+        output(&self.0, false)
+    }
+}
+
+/// Reference MD4.
+pub(crate) type Md4 = Md<Md4Engine>;
+
+/// Reference SHA-1.
+pub(crate) type Sha1 = Md<Sha1Engine>;
+
+/// Reference SHA-256.
+pub(crate) type Sha256 = Md<Sha256Engine>;
+
+impl Algorithm for Md4 {
+    const BLOCK_SIZE: usize = 64;
+    const DIGEST_SIZE: usize = 16;
+    const NAME: &'static str = "MD4";
+}
+
+impl Algorithm for Sha1 {
+    const BLOCK_SIZE: usize = 64;
+    const DIGEST_SIZE: usize = 20;
+    const NAME: &'static str = "SHA-1";
+}
+
+impl Algorithm for Sha256 {
+    const BLOCK_SIZE: usize = 64;
+    const DIGEST_SIZE: usize = 32;
+    const NAME: &'static str = "SHA-256";
+}
+
+/// Runs the future to completion on a current-thread runtime.
+#[cfg(feature = "async-runtime-tokio")]
+pub(crate) fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn md4_known_answers() {
+    assert_eq!(Md4::hash("").to_string(), "31d6cfe0d16ae931b73c59d7e0c089c0");
+    assert_eq!(Md4::hash("abc").to_string(), "a448017aaf21d8525fc10ae87aa6729d");
+    assert_eq!(
+        Md4::hash("12345678901234567890123456789012345678901234567890123456789012345678901234567890").to_string(),
+        "e33b4ddc9c38f2199c3e7b164fcc0536"
+    );
+}
+
+#[test]
+fn sha1_known_answers() {
+    assert_eq!(Sha1::hash("").to_string(), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(
+        Sha1::hash("abc").to_string(),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+    let million = vec![b'a'; 1_000_000];
+    assert_eq!(
+        Sha1::hash(million).to_string(),
+        "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+}
+
+#[test]
+fn sha256_known_answers() {
+    assert_eq!(
+        Sha256::hash("").to_string(),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        Sha256::hash("abc").to_string(),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        Sha256::hash("abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq").to_string(),
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+}
+
+#[test]
+fn updates_in_pieces() {
+    let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+    let mut hash = Sha256::default();
+    for piece in data.chunks(7) {
+        hash.update(piece);
+    }
+    assert_eq!(hash.digest(), Sha256::hash(&data));
+}
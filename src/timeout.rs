@@ -0,0 +1,39 @@
+use std::io;
+use std::time::Duration;
+
+use chksum_core::Hash;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::time;
+
+use crate::AsyncWriter;
+
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite + Unpin,
+    H: Hash + Unpin,
+{
+    /// Writes the entire buffer, failing with an error of kind [`io::ErrorKind::TimedOut`] if it does not complete
+    /// within the given duration.
+    ///
+    /// Only bytes accepted by the underlying writer before the deadline are hashed, so the digest stays consistent
+    /// with the written data. Use [`AsyncWriter::count`] to find out how much of the buffer was written.
+    ///
+    /// Must be called within a Tokio runtime with the time driver enabled.
+    pub async fn write_all_timeout(&mut self, buf: &[u8], duration: Duration) -> io::Result<()> {
+        match time::timeout(duration, self.write_all(buf)).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "write timed out")),
+        }
+    }
+
+    /// Shuts down the writer, failing with an error of kind [`io::ErrorKind::TimedOut`] if it does not complete
+    /// within the given duration.
+    ///
+    /// Must be called within a Tokio runtime with the time driver enabled.
+    pub async fn shutdown_timeout(&mut self, duration: Duration) -> io::Result<()> {
+        match time::timeout(duration, self.shutdown()).await {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "shutdown timed out")),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+#[cfg(feature = "async-runtime-tokio")]
+use std::io;
+#[cfg(feature = "async-runtime-tokio")]
+use std::task::Poll;
+
+/// Order of the hash update relative to the write to the underlying writer.
+///
+/// Set with [`Writer::with_update_order`](crate::Writer::with_update_order),
+/// `AsyncWriter::with_update_order` or [`Builder::with_update_order`](crate::Builder::with_update_order).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpdateOrder {
+    /// The hash is updated with the bytes the underlying writer accepted, so the digest reflects delivered data.
+    ///
+    /// A partial write hashes only the accepted prefix, and a failed write hashes nothing.
+    #[default]
+    AfterWrite,
+    /// The hash is updated with the whole buffer before it is written, so the digest reflects intended data.
+    ///
+    /// The buffer is then written with [`Write::write_all`](std::io::Write::write_all), so a write either accepts the
+    /// whole buffer or fails, and after a failure the digest includes bytes the underlying writer may not have
+    /// received. Useful with transactional sinks, which discard the data on failure anyway.
+    ///
+    /// An asynchronous write cannot loop until the whole buffer is accepted, so `AsyncWriter` hashes the buffer once
+    /// and returns the partial write as usual. The bytes not accepted yet are not hashed again when the write is
+    /// retried with the same data, only the bytes past them are.
+    BeforeWrite,
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl UpdateOrder {
+    /// Returns the bytes to hash for the completed poll of an asynchronous write of the buffer.
+    pub(crate) fn hashed<'a>(
+        self,
+        pending: &mut Pending,
+        buf: &'a [u8],
+        poll: &Poll<io::Result<usize>>,
+    ) -> Option<&'a [u8]> {
+        match (self, poll) {
+            (Self::AfterWrite, Poll::Ready(Ok(n))) => Some(&buf[..*n]),
+            (Self::AfterWrite, _) => None,
+            (Self::BeforeWrite, poll) => {
+                let fresh = pending.fresh(buf);
+                if let Poll::Ready(Ok(n)) = poll {
+                    pending.accept(*n);
+                }
+                Some(fresh).filter(|fresh| !fresh.is_empty())
+            },
+        }
+    }
+}
+
+/// Bytes hashed by [`UpdateOrder::BeforeWrite`] but not accepted by the underlying asynchronous writer yet.
+#[cfg(feature = "async-runtime-tokio")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Pending(usize);
+
+#[cfg(feature = "async-runtime-tokio")]
+impl Pending {
+    /// Creates new [`Pending`] with no bytes hashed ahead.
+    pub(crate) const fn new() -> Self {
+        Self(0)
+    }
+
+    /// Returns the part of the buffer past the bytes hashed by an earlier write.
+    fn fresh<'a>(&mut self, buf: &'a [u8]) -> &'a [u8] {
+        let start = self.0.min(buf.len());
+        self.0 = self.0.max(buf.len());
+        &buf[start..]
+    }
+
+    fn accept(&mut self, n: usize) {
+        self.0 = self.0.saturating_sub(n);
+    }
+
+    /// Returns the number of bytes hashed but not accepted yet.
+    pub(crate) const fn get(self) -> u64 {
+        self.0 as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "async-runtime-tokio")]
+    use std::future::{poll_fn, Future};
+    use std::io::{self, Write};
+    use std::num::NonZeroUsize;
+    #[cfg(feature = "async-runtime-tokio")]
+    use std::pin::pin;
+    #[cfg(feature = "async-runtime-tokio")]
+    use std::task::Poll;
+
+    use chksum_core::Hash;
+    #[cfg(feature = "async-runtime-tokio")]
+    use tokio::io::AsyncWriteExt;
+
+    use super::UpdateOrder;
+    #[cfg(feature = "async-runtime-tokio")]
+    use crate::test_util::SlowAsyncWriter;
+    use crate::test_util::{FailingWriter, ShortWriter};
+    #[cfg(feature = "async-runtime-tokio")]
+    use crate::testing::block_on;
+    use crate::testing::Sha256;
+    use crate::{Builder, Error};
+
+    const DATA: &[u8] = b"hello world";
+
+    fn limit(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    fn offset(error: &io::Error) -> Option<u64> {
+        match error.get_ref()?.downcast_ref()? {
+            Error::Write { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn short_write_after_write() {
+        let mut writer = Builder::<Sha256>::new().build(ShortWriter::new(Vec::new(), limit(3)));
+        assert_eq!(writer.write(DATA).unwrap(), 3);
+        assert_eq!(writer.count(), 3);
+        assert_eq!(writer.digest(), Sha256::hash(b"hel"));
+    }
+
+    #[test]
+    fn short_write_before_write() {
+        let mut writer = Builder::<Sha256>::new()
+            .with_update_order(UpdateOrder::BeforeWrite)
+            .build(ShortWriter::new(Vec::new(), limit(3)));
+        assert_eq!(writer.write(DATA).unwrap(), DATA.len());
+        assert_eq!(writer.digest(), Sha256::hash(DATA));
+        assert_eq!(writer.into_inner().into_inner(), DATA);
+    }
+
+    #[test]
+    fn failed_write_after_write() {
+        let mut writer = Builder::<Sha256>::new()
+            .build(FailingWriter::after_bytes(Vec::new(), 5))
+            .with_error_context();
+        assert_eq!(writer.write(DATA).unwrap(), 5);
+        let error = writer.write(&DATA[5..]).unwrap_err();
+        assert_eq!(offset(&error), Some(5));
+        assert_eq!(writer.digest(), Sha256::hash(b"hello"));
+    }
+
+    #[test]
+    fn failed_write_before_write() {
+        let mut writer = Builder::<Sha256>::new()
+            .with_update_order(UpdateOrder::BeforeWrite)
+            .build(FailingWriter::after_bytes(Vec::new(), 5))
+            .with_error_context();
+        let error = writer.write(DATA).unwrap_err();
+        assert_eq!(offset(&error), Some(0));
+        assert_eq!(writer.digest(), Sha256::hash(DATA));
+        assert_eq!(writer.into_inner().into_inner(), b"hello");
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_short_write_after_write() {
+        block_on(async {
+            let mut writer = Builder::<Sha256>::new().build_async(ShortWriter::new(Vec::new(), limit(3)));
+            assert_eq!(writer.write(DATA).await.unwrap(), 3);
+            assert_eq!(writer.count(), 3);
+            assert_eq!(writer.digest(), Sha256::hash(b"hel"));
+        });
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_short_write_before_write() {
+        block_on(async {
+            let mut writer = Builder::<Sha256>::new()
+                .with_update_order(UpdateOrder::BeforeWrite)
+                .build_async(ShortWriter::new(Vec::new(), limit(3)));
+            assert_eq!(writer.write(DATA).await.unwrap(), 3);
+            assert_eq!(writer.digest(), Sha256::hash(DATA));
+            assert_eq!(writer.staged_len(), 8);
+            // retrying the rest hashes nothing again
+            writer.write_all(&DATA[3..]).await.unwrap();
+            assert_eq!(writer.count(), DATA.len() as u64);
+            assert_eq!(writer.staged_len(), 0);
+            assert_eq!(writer.digest(), Sha256::hash(DATA));
+            // retrying with more data hashes only the bytes past the pending ones
+            assert_eq!(writer.write(b"!!").await.unwrap(), 2);
+            assert_eq!(writer.digest(), Sha256::hash(b"hello world!!"));
+            assert_eq!(writer.into_inner().into_inner(), b"hello world!!");
+        });
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_retry_with_longer_buffer() {
+        block_on(async {
+            let mut writer = Builder::<Sha256>::new()
+                .with_update_order(UpdateOrder::BeforeWrite)
+                .build_async(ShortWriter::new(Vec::new(), limit(3)));
+            assert_eq!(writer.write(b"hello").await.unwrap(), 3);
+            writer.write_all(b"lo world").await.unwrap();
+            assert_eq!(writer.digest(), Sha256::hash(DATA));
+            assert_eq!(writer.into_inner().into_inner(), DATA);
+        });
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_failed_write_after_write() {
+        block_on(async {
+            let mut writer = Builder::<Sha256>::new()
+                .build_async(FailingWriter::after_bytes(Vec::new(), 5))
+                .with_error_context();
+            let error = writer.write_all(DATA).await.unwrap_err();
+            assert_eq!(offset(&error), Some(5));
+            assert_eq!(writer.digest(), Sha256::hash(b"hello"));
+        });
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_failed_write_before_write() {
+        block_on(async {
+            let mut writer = Builder::<Sha256>::new()
+                .with_update_order(UpdateOrder::BeforeWrite)
+                .build_async(FailingWriter::after_bytes(Vec::new(), 5))
+                .with_error_context();
+            let error = writer.write_all(DATA).await.unwrap_err();
+            assert_eq!(offset(&error), Some(5));
+            assert_eq!(writer.count(), DATA.len() as u64);
+            assert_eq!(writer.staged_len(), 6);
+            assert_eq!(writer.digest(), Sha256::hash(DATA));
+            assert_eq!(writer.into_inner().into_inner(), b"hello");
+        });
+    }
+
+    #[cfg(feature = "async-runtime-tokio")]
+    #[test]
+    fn async_cancelled_write_all() {
+        block_on(async {
+            let inner = SlowAsyncWriter::new(ShortWriter::new(Vec::new(), limit(3)), 1);
+            let mut writer = Builder::<Sha256>::new().build_async(inner);
+            {
+                let mut write = pin!(writer.write_all(DATA));
+                for _ in 0..2 {
+                    poll_fn(|cx| Poll::Ready(write.as_mut().poll(cx).is_ready())).await;
+                }
+            }
+            assert_eq!(writer.count(), 3);
+            assert_eq!(writer.staged_len(), 0);
+            assert_eq!(writer.digest(), Sha256::hash(b"hel"));
+            assert_eq!(writer.into_inner().into_inner().into_inner(), b"hel");
+        });
+    }
+}
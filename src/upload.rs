@@ -0,0 +1,157 @@
+use std::io::{self, Write};
+
+use crate::state::{import, ResumableHash, State, StateStore};
+use crate::Error;
+
+/// Tracks a resumable upload, like a tus upload or a Google Cloud Storage resumable session, hashing only the data
+/// committed by the server.
+///
+/// Written data is kept pending until the server reports it as committed with [`ResumableUpload::commit`]. Only then
+/// it is hashed and the hash state is saved to the [`StateStore`], so the saved state always matches the committed
+/// offset. Data the server did not commit stays pending and should be sent again, starting at
+/// [`ResumableUpload::committed`].
+///
+/// On resume, the offset reported by the server is validated against the saved state before continuing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResumableUpload<H, S>
+where
+    H: ResumableHash,
+    S: StateStore,
+{
+    hash: H,
+    store: S,
+    id: String,
+    committed: u64,
+    pending: Vec<u8>,
+}
+
+impl<H, S> ResumableUpload<H, S>
+where
+    H: ResumableHash,
+    S: StateStore,
+{
+    /// Creates new [`ResumableUpload`] resuming from the state saved in the store under `id`, or from scratch if there
+    /// is none.
+    ///
+    /// The `remote` offset is the number of bytes committed by the server, like the `Upload-Offset` header of tus. If
+    /// it differs from the saved state, the result is [`Error::OffsetMismatch`], and a state which cannot be imported
+    /// results in [`Error::StateRestore`].
+    pub fn resume(store: S, id: impl Into<String>, remote: u64) -> Result<Self, Error> {
+        let id = id.into();
+        let (committed, hash) = match store.load(&id)? {
+            Some(State { offset, hash }) => (offset, import::<H>(&id, &hash)?),
+            None => (0, H::default()),
+        };
+        if committed != remote {
+            return Err(Error::OffsetMismatch {
+                local: committed,
+                remote,
+            });
+        }
+        Ok(Self {
+            hash,
+            store,
+            id,
+            committed,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Returns number of bytes committed by the server.
+    #[must_use]
+    pub const fn committed(&self) -> u64 {
+        self.committed
+    }
+
+    /// Returns data written but not yet committed by the server.
+    #[must_use]
+    pub fn pending(&self) -> &[u8] {
+        &self.pending
+    }
+
+    /// Returns value of the `Content-Range` header for sending the pending data, like `bytes 0-1023/*`, or [`None`] if
+    /// there is no pending data.
+    ///
+    /// The total length of the upload should be given once known, with the last part.
+    #[must_use]
+    pub fn content_range(&self, total: Option<u64>) -> Option<String> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let start = self.committed;
+        let end = start + self.pending.len() as u64 - 1;
+        let range = match total {
+            Some(total) => format!("bytes {start}-{end}/{total}"),
+            None => format!("bytes {start}-{end}/*"),
+        };
+        Some(range)
+    }
+
+    /// Records the offset committed by the server, hashing the newly committed data and saving the state.
+    ///
+    /// An offset before the committed one or past the pending data results in [`Error::OffsetMismatch`].
+    pub fn commit(&mut self, offset: u64) -> Result<(), Error> {
+        let end = self.committed + self.pending.len() as u64;
+        if offset < self.committed || offset > end {
+            return Err(Error::OffsetMismatch {
+                local: end,
+                remote: offset,
+            });
+        }
+        let length = (offset - self.committed) as usize;
+        self.hash.update(&self.pending[..length]);
+        self.pending.drain(..length);
+        self.committed = offset;
+        let hash = self.hash.export_state();
+        self.store.save(&self.id, &State { offset, hash })?;
+        Ok(())
+    }
+
+    /// Records the offset committed by the server from value of the `Range` header, like `bytes=0-1023` returned by
+    /// Google Cloud Storage, see [`ResumableUpload::commit`].
+    ///
+    /// A malformed range, or one not starting at zero, results in an error of kind [`io::ErrorKind::InvalidData`].
+    pub fn commit_range(&mut self, range: &str) -> Result<(), Error> {
+        let end = range
+            .trim()
+            .strip_prefix("bytes=0-")
+            .and_then(|end| end.parse::<u64>().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed committed range"))?;
+        self.commit(end + 1)
+    }
+
+    /// Returns calculated hash digest of the committed data.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+
+    /// Removes the saved state and returns the hash digest, once all data is committed.
+    ///
+    /// Pending data results in [`Error::OffsetMismatch`].
+    pub fn finish(mut self) -> Result<H::Digest, Error> {
+        if !self.pending.is_empty() {
+            return Err(Error::OffsetMismatch {
+                local: self.committed + self.pending.len() as u64,
+                remote: self.committed,
+            });
+        }
+        self.store.remove(&self.id)?;
+        Ok(self.hash.digest())
+    }
+}
+
+impl<H, S> Write for ResumableUpload<H, S>
+where
+    H: ResumableHash,
+    S: StateStore,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::Write;
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::Writer;
+
+/// Universally unique identifier, as defined by RFC 9562.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+    /// Namespace for fully qualified domain names, as defined by RFC 9562.
+    pub const NAMESPACE_DNS: Self = Self([
+        0x6B, 0xA7, 0xB8, 0x10, 0x9D, 0xAD, 0x11, 0xD1, 0x80, 0xB4, 0x00, 0xC0, 0x4F, 0xD4, 0x30, 0xC8,
+    ]);
+    /// Namespace for ISO object identifiers, as defined by RFC 9562.
+    pub const NAMESPACE_OID: Self = Self([
+        0x6B, 0xA7, 0xB8, 0x12, 0x9D, 0xAD, 0x11, 0xD1, 0x80, 0xB4, 0x00, 0xC0, 0x4F, 0xD4, 0x30, 0xC8,
+    ]);
+    /// Namespace for URLs, as defined by RFC 9562.
+    pub const NAMESPACE_URL: Self = Self([
+        0x6B, 0xA7, 0xB8, 0x11, 0x9D, 0xAD, 0x11, 0xD1, 0x80, 0xB4, 0x00, 0xC0, 0x4F, 0xD4, 0x30, 0xC8,
+    ]);
+    /// Namespace for X.500 distinguished names, as defined by RFC 9562.
+    pub const NAMESPACE_X500: Self = Self([
+        0x6B, 0xA7, 0xB8, 0x14, 0x9D, 0xAD, 0x11, 0xD1, 0x80, 0xB4, 0x00, 0xC0, 0x4F, 0xD4, 0x30, 0xC8,
+    ]);
+
+    /// Creates new [`Uuid`] from its bytes.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns bytes of the identifier.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// Derives name-based identifier of the version from the namespace and the name, like version 5 does with SHA-1.
+    fn derive<H>(namespace: Self, name: &[u8], version: u8) -> Option<Self>
+    where
+        H: Hash,
+        H::Digest: AsRef<[u8]>,
+    {
+        let mut hash = H::default();
+        hash.update(namespace.0);
+        hash.update(name);
+        let digest = hash.digest();
+        let mut bytes: [u8; 16] = digest.as_ref().get(..16)?.try_into().ok()?;
+        bytes[6] = (bytes[6] & 0x0F) | version << 4;
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+        Some(Self(bytes))
+    }
+}
+
+impl Display for Uuid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (index, byte) in self.0.iter().enumerate() {
+            if matches!(index, 4 | 6 | 8 | 10) {
+                f.write_str("-")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Returns version 8 identifier derived from the namespace and the calculated hash digest.
+    ///
+    /// The identifier consists of the first 16 bytes of `H(namespace || digest)`, with the version and variant bits
+    /// overwritten, so it only depends on the namespace, the hash algorithm and the written data. Returns [`None`] if
+    /// the digest is shorter than 16 bytes.
+    #[must_use]
+    pub fn digest_uuid(&self, namespace: Uuid) -> Option<Uuid> {
+        Uuid::derive::<H>(namespace, self.digest().as_ref(), 8)
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: Hash,
+    H::Digest: AsRef<[u8]>,
+{
+    /// Returns version 8 identifier derived from the namespace and the calculated hash digest.
+    ///
+    /// See [`Writer::digest_uuid`] for details.
+    #[must_use]
+    pub fn digest_uuid(&self, namespace: Uuid) -> Option<Uuid> {
+        Uuid::derive::<H>(namespace, self.digest().as_ref(), 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::Uuid;
+    use crate::testing::{Sha1, Sha256};
+    use crate::Writer;
+
+    #[test]
+    fn rfc4122_version5() {
+        let vectors = [
+            (
+                Uuid::NAMESPACE_DNS,
+                "www.example.com",
+                "2ed6657d-e927-568b-95e1-2665a8aea6a2",
+            ),
+            (
+                Uuid::NAMESPACE_URL,
+                "https://example.com/",
+                "dd2c1780-811a-5296-81c5-178a0ef488bc",
+            ),
+        ];
+        for (namespace, name, uuid) in vectors {
+            let derived = Uuid::derive::<Sha1>(namespace, name.as_bytes(), 5).unwrap();
+            assert_eq!(derived.to_string(), uuid, "{name}");
+        }
+    }
+
+    #[test]
+    fn derives_from_digest() {
+        let mut writer = Writer::<_, Sha256>::new(Vec::new());
+        writer.write_all(b"hello world").unwrap();
+        let uuid = writer.digest_uuid(Uuid::NAMESPACE_DNS).unwrap();
+        assert_eq!(uuid.to_string(), "f3933adf-bfbb-8817-a9d7-d509305afca6");
+        assert_ne!(writer.digest_uuid(Uuid::NAMESPACE_URL), Some(uuid));
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::{self, Write};
+
+use chksum_core::Hash;
+
+/// Collects written bytes into a [`Vec`] and calculates their hash digest on the fly.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VecWriter<H>
+where
+    H: Hash,
+{
+    buffer: Vec<u8>,
+    hash: H,
+}
+
+impl<H> VecWriter<H>
+where
+    H: Hash,
+{
+    /// Creates new [`VecWriter`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates new [`VecWriter`] with at least the specified capacity preallocated.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        let buffer = Vec::with_capacity(capacity);
+        let hash = H::default();
+        Self { buffer, hash }
+    }
+
+    /// Creates new [`VecWriter`] with provided hash.
+    #[must_use]
+    pub const fn with_hash(hash: H) -> Self {
+        let buffer = Vec::new();
+        Self { buffer, hash }
+    }
+
+    /// Returns capacity of the internal buffer.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+
+    /// Returns bytes written so far.
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns number of bytes written so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns `true` if no bytes were written yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.hash.digest()
+    }
+
+    /// Unwraps this [`VecWriter`], returning the written bytes.
+    #[must_use]
+    pub fn into_inner(self) -> Vec<u8> {
+        let Self { buffer, .. } = self;
+        buffer
+    }
+
+    /// Unwraps this [`VecWriter`], returning the written bytes along with their hash digest.
+    #[must_use]
+    pub fn into_parts(self) -> (Vec<u8>, H::Digest) {
+        let digest = self.hash.digest();
+        let Self { buffer, .. } = self;
+        (buffer, digest)
+    }
+}
+
+impl<H> AsRef<[u8]> for VecWriter<H>
+where
+    H: Hash,
+{
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl<H> Write for VecWriter<H>
+where
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        self.hash.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
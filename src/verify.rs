@@ -0,0 +1,248 @@
+#[cfg(feature = "content-digest")]
+use std::fmt::Write as _;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+#[cfg(feature = "manifest")]
+use std::path::{Path, PathBuf};
+
+use chksum_core::Hash;
+
+#[cfg(feature = "manifest")]
+use crate::manifest::ManifestEntry;
+use crate::seek::Tracking;
+#[cfg(feature = "content-digest")]
+use crate::{base64, Algorithm};
+use crate::{Error, Writer};
+
+#[cfg(feature = "manifest")]
+/// Outcome of verifying a single manifest entry, `Ok(true)` if the digest matches.
+pub type VerifyResult = (PathBuf, io::Result<bool>);
+
+/// Wraps a writer and checks the calculated hash digest against an expected one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VerifyingWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    writer: Writer<W, H>,
+    expected: String,
+}
+
+impl<W, H> VerifyingWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    /// Creates new [`VerifyingWriter`] expecting the given hexadecimal digest.
+    pub fn new(inner: W, expected: impl Into<String>) -> Self {
+        let hash = H::default();
+        Self::with_hash(inner, hash, expected)
+    }
+
+    /// Creates new [`VerifyingWriter`] with provided hash, expecting the given hexadecimal digest.
+    pub fn with_hash(inner: W, hash: H, expected: impl Into<String>) -> Self {
+        let writer = Writer::with_hash(inner, hash);
+        let expected = expected.into();
+        Self { writer, expected }
+    }
+
+    /// Returns expected hash digest.
+    #[must_use]
+    pub fn expected(&self) -> &str {
+        &self.expected
+    }
+
+    /// Returns calculated hash digest.
+    #[must_use]
+    pub fn digest(&self) -> H::Digest {
+        self.writer.digest()
+    }
+
+    /// Returns `true` if calculated hash digest matches the expected one, ignoring case.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.digest().to_string().eq_ignore_ascii_case(&self.expected)
+    }
+
+    /// Flushes the writer and returns the underlying writer if the calculated hash digest matches the expected one.
+    ///
+    /// A different digest results in [`Error::DigestMismatch`].
+    pub fn finish(mut self) -> Result<W, Error> {
+        self.flush()?;
+        if !self.is_valid() {
+            let expected = self.expected.clone();
+            let actual = self.digest().to_string();
+            return Err(Error::DigestMismatch {
+                expected,
+                actual,
+                offset: None,
+            });
+        }
+        Ok(self.into_inner())
+    }
+
+    /// Unwraps this [`VerifyingWriter`], returning the underlying writer.
+    #[must_use]
+    pub fn into_inner(self) -> W {
+        let Self { writer, .. } = self;
+        writer.into_inner()
+    }
+}
+
+impl<W, H> Write for VerifyingWriter<W, H>
+where
+    W: Write,
+    H: Hash,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(feature = "content-digest")]
+impl<W, H> VerifyingWriter<W, H>
+where
+    W: Write,
+    H: Algorithm,
+{
+    /// Creates new [`VerifyingWriter`] expecting the digest of the `Content-Digest` header defined by RFC 9530, like
+    /// `sha-256=:<base64>:`, so servers can verify request bodies while streaming them to the destination.
+    ///
+    /// The digest of the algorithm with [`Algorithm::NAME`], ignoring case, is expected, others are skipped. A
+    /// header without such digest, or with a malformed one, results in an error of kind
+    /// [`io::ErrorKind::InvalidData`], usually answered with `400 Bad Request`. A digest mismatch reported by
+    /// [`VerifyingWriter::finish`] is usually answered with `422 Unprocessable Content`.
+    pub fn from_content_digest(inner: W, header: &str) -> io::Result<Self> {
+        let value = header
+            .split(',')
+            .filter_map(|member| member.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(H::NAME))
+            .map(|(_, value)| value.split(';').next().unwrap_or_default().trim())
+            .ok_or_else(|| {
+                let message = format!("no {} digest in Content-Digest header", H::NAME);
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            })?;
+        let value = value
+            .strip_prefix(':')
+            .and_then(|value| value.strip_suffix(':'))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed Content-Digest header"))?;
+        Self::from_base64(inner, value)
+    }
+
+    /// Creates new [`VerifyingWriter`] expecting the base64 digest of the legacy `Content-MD5` header.
+    ///
+    /// See [`VerifyingWriter::from_content_digest`] for details.
+    pub fn from_content_md5(inner: W, header: &str) -> io::Result<Self> {
+        Self::from_base64(inner, header.trim())
+    }
+
+    fn from_base64(inner: W, digest: &str) -> io::Result<Self> {
+        let digest = base64::decode(digest)
+            .filter(|digest| digest.len() == H::DIGEST_SIZE)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed base64 digest"))?;
+        let mut expected = String::with_capacity(digest.len() * 2);
+        for byte in digest {
+            let _ = write!(expected, "{byte:02x}");
+        }
+        Ok(Self::new(inner, expected))
+    }
+}
+
+#[cfg(feature = "manifest")]
+impl ManifestEntry {
+    /// Creates new [`VerifyingWriter`] expecting the digest of this entry.
+    pub fn verifying_writer<W, H>(&self, inner: W) -> VerifyingWriter<W, H>
+    where
+        W: Write,
+        H: Hash,
+    {
+        VerifyingWriter::new(inner, self.digest.clone())
+    }
+}
+
+#[cfg(feature = "manifest")]
+/// Verifies the manifest entries, streaming each file opened by `open`, returning an iterator over per-entry results.
+pub fn verify_manifest<H, F, R>(
+    entries: impl IntoIterator<Item = ManifestEntry>,
+    mut open: F,
+) -> impl Iterator<Item = VerifyResult>
+where
+    H: Hash,
+    F: FnMut(&Path) -> io::Result<R>,
+    R: Read,
+{
+    entries.into_iter().map(move |entry| {
+        let result = verify::<H, _>(&entry, &mut open);
+        (entry.path, result)
+    })
+}
+
+#[cfg(feature = "manifest")]
+fn verify<H, R>(entry: &ManifestEntry, open: impl FnOnce(&Path) -> io::Result<R>) -> io::Result<bool>
+where
+    H: Hash,
+    R: Read,
+{
+    let mut reader = open(&entry.path)?;
+    let mut writer = entry.verifying_writer::<_, H>(io::sink());
+    io::copy(&mut reader, &mut writer)?;
+    Ok(writer.is_valid())
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Read + Write + Seek,
+    H: Hash,
+    H::Digest: PartialEq,
+{
+    /// Reads the written data back from the underlying writer and checks it against the calculated hash digest.
+    ///
+    /// The hashed stream is assumed to end at the position of the last write. The position of the underlying writer is
+    /// restored afterwards. Returns `Ok(false)` if the data differs or some write did not land at the end of the
+    /// hashed stream.
+    ///
+    /// The data is rehashed starting from the default hash state, use [`Writer::verify_written_with_hash`] for
+    /// writers created with a different one.
+    pub fn verify_written(&mut self) -> io::Result<bool> {
+        self.verify_written_with_hash(H::default())
+    }
+
+    /// Reads the written data back from the underlying writer and checks it against the calculated hash digest,
+    /// rehashing it starting from the provided hash.
+    ///
+    /// See [`Writer::verify_written`] for details.
+    pub fn verify_written_with_hash(&mut self, hash: H) -> io::Result<bool> {
+        let end = match self.tracking {
+            Tracking::Untracked | Tracking::Probing(_) => self.inner.stream_position()?,
+            Tracking::Tracked { end, .. } => end,
+            Tracking::Broken => return Ok(false),
+        };
+        let mut writer = Writer::with_hash(io::sink(), hash);
+        let length = read_back(&mut self.inner, end, self.count, &mut writer)?;
+        Ok(length == self.count && writer.digest() == self.digest())
+    }
+}
+
+/// Copies `length` bytes ending at `end` from the reader to the writer, restoring the reader position afterwards.
+///
+/// Returns number of bytes copied, which is less than `length` if the reader ended early.
+pub(crate) fn read_back<R>(reader: &mut R, end: u64, length: u64, writer: &mut impl Write) -> io::Result<u64>
+where
+    R: Read + Seek,
+{
+    let start = end.checked_sub(length).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "hashed stream starts before the beginning of the writer",
+        )
+    })?;
+    let position = reader.stream_position()?;
+    reader.seek(SeekFrom::Start(start))?;
+    let copied = io::copy(&mut reader.by_ref().take(length), writer);
+    reader.seek(SeekFrom::Start(position))?;
+    copied
+}
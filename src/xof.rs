@@ -0,0 +1,60 @@
+use std::io::{self, Read, Write};
+
+use chksum_core::Hash;
+#[cfg(feature = "async-runtime-tokio")]
+use tokio::io::AsyncWrite;
+
+#[cfg(feature = "async-runtime-tokio")]
+use crate::AsyncWriter;
+use crate::Writer;
+
+/// A trait for hashes with extendable output, like SHAKE or BLAKE3.
+pub trait ExtendableHash: Hash {
+    /// The type of the reader over the output.
+    type Reader: Read;
+
+    /// Returns reader over the output of unlimited length, without consuming the hash.
+    #[must_use]
+    fn finalize_xof(&self) -> Self::Reader;
+}
+
+fn read_xof(mut reader: impl Read, length: usize) -> io::Result<Vec<u8>> {
+    let mut output = vec![0; length];
+    reader.read_exact(&mut output)?;
+    Ok(output)
+}
+
+impl<W, H> Writer<W, H>
+where
+    W: Write + ?Sized,
+    H: ExtendableHash,
+{
+    /// Returns reader over the extendable output of the hash.
+    #[must_use]
+    pub fn xof_reader(&self) -> H::Reader {
+        self.hash.finalize_xof()
+    }
+
+    /// Returns the given number of bytes of the extendable output of the hash.
+    pub fn digest_xof(&self, length: usize) -> io::Result<Vec<u8>> {
+        read_xof(self.xof_reader(), length)
+    }
+}
+
+#[cfg(feature = "async-runtime-tokio")]
+impl<W, H> AsyncWriter<W, H>
+where
+    W: AsyncWrite,
+    H: ExtendableHash,
+{
+    /// Returns reader over the extendable output of the hash.
+    #[must_use]
+    pub fn xof_reader(&self) -> H::Reader {
+        self.hash.finalize_xof()
+    }
+
+    /// Returns the given number of bytes of the extendable output of the hash.
+    pub fn digest_xof(&self, length: usize) -> io::Result<Vec<u8>> {
+        read_xof(self.xof_reader(), length)
+    }
+}
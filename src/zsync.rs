@@ -0,0 +1,198 @@
+use std::io::{self, Write};
+use std::mem;
+use std::num::NonZeroU64;
+
+use crate::rolling::rsum;
+use crate::Algorithm;
+
+/// Version of zsync written into control files.
+const VERSION: &str = "0.6.2";
+
+/// Wraps a writer and calculates the block checksums of a [zsync] control file in the same pass.
+///
+/// Every block gets the weak rolling checksum of zsync and a strong checksum with `H`, which zsync clients expect to
+/// be MD4. The whole stream is hashed with `S`, written under [`Algorithm::NAME`] as header name, so `SHA-1` for
+/// zsync clients. The trailing block is padded with zeros, like zsync does, and blocks are buffered until complete.
+///
+/// Lengths of the stored checksums depend on the length of the stream, so full checksums are kept until
+/// [`ZsyncWriter::finish`] truncates them with the formulas of `zsyncmake`.
+///
+/// [zsync]: http://zsync.moria.org.uk/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZsyncWriter<W, H, S>
+where
+    W: Write,
+    H: Algorithm,
+    S: Algorithm,
+{
+    inner: W,
+    hash: S,
+    block_size: NonZeroU64,
+    block_len: usize,
+    block: Vec<u8>,
+    length: u64,
+    checksums: Vec<([u8; 4], H::Digest)>,
+}
+
+impl<W, H, S> ZsyncWriter<W, H, S>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+    S: Algorithm,
+{
+    /// Creates new [`ZsyncWriter`] with the given block size, `zsyncmake` uses 2048 bytes for files up to 100 MB and
+    /// 4096 bytes otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block size does not fit into memory.
+    pub fn new(inner: W, block_size: NonZeroU64) -> Self {
+        let block_len = usize::try_from(block_size.get()).expect("block size fits into memory");
+        Self {
+            inner,
+            hash: S::default(),
+            block_size,
+            block_len,
+            block: Vec::with_capacity(block_len),
+            length: 0,
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Returns size of the blocks.
+    #[must_use]
+    pub const fn block_size(&self) -> NonZeroU64 {
+        self.block_size
+    }
+
+    /// Returns calculated hash digest of the whole stream.
+    #[must_use]
+    pub fn digest(&self) -> S::Digest {
+        self.hash.digest()
+    }
+
+    /// Ends the trailing block, flushes the underlying writer and writes the control file into `control`, for the
+    /// file of the given name downloadable from the given URL.
+    pub fn finish(mut self, mut control: impl Write, filename: &str, url: &str) -> io::Result<W> {
+        if !self.block.is_empty() {
+            self.end_block();
+        }
+        self.inner.flush()?;
+        let (sequence, weak, strong) = hash_lengths(self.length, self.block_size.get());
+        let strong = strong.min(H::DIGEST_SIZE);
+        let header = [
+            format!("zsync: {VERSION}"),
+            format!("Filename: {filename}"),
+            format!("Blocksize: {}", self.block_size),
+            format!("Length: {}", self.length),
+            format!("Hash-Lengths: {sequence},{weak},{strong}"),
+            format!("URL: {url}"),
+            format!("{}: {}", S::NAME, self.hash.digest()),
+            String::new(),
+            String::new(),
+        ];
+        control.write_all(header.join("\n").as_bytes())?;
+        for (rsum, checksum) in &self.checksums {
+            control.write_all(&rsum[4 - weak..])?;
+            control.write_all(&checksum.as_ref()[..strong])?;
+        }
+        let Self { inner, .. } = self;
+        Ok(inner)
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.hash.update(data);
+        self.length += data.len() as u64;
+        while !data.is_empty() {
+            let remaining = self.block_len - self.block.len();
+            let (chunk, rest) = data.split_at(data.len().min(remaining));
+            self.block.extend_from_slice(chunk);
+            if self.block.len() == self.block_len {
+                self.end_block();
+            }
+            data = rest;
+        }
+    }
+
+    fn end_block(&mut self) {
+        let mut block = mem::take(&mut self.block);
+        block.resize(self.block_len, 0);
+        self.checksums.push((rsum(&block), H::hash(&block)));
+        block.clear();
+        self.block = block;
+    }
+}
+
+impl<W, H, S> Write for ZsyncWriter<W, H, S>
+where
+    W: Write,
+    H: Algorithm,
+    H::Digest: AsRef<[u8]>,
+    S: Algorithm,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Returns number of sequential matches and lengths of the weak and strong checksums, like `zsyncmake`.
+fn hash_lengths(length: u64, block_size: u64) -> (usize, usize, usize) {
+    let sequence = if length > block_size { 2 } else { 1 };
+    let blocks = (1 + length / block_size) as f64;
+    let (length, block_size) = ((length.max(1)) as f64, block_size as f64);
+    let weak = (((length.ln() + block_size.ln()) / 2f64.ln() - 8.6) / f64::from(sequence) / 8.0).ceil();
+    let weak = (weak as usize).clamp(2, 4);
+    let strong = ((20.0 + (length.ln() + blocks.ln()) / 2f64.ln()) / f64::from(sequence) / 8.0).ceil() as usize;
+    let minimum = ((7.9 + (20.0 + blocks.ln() / 2f64.ln())) / 8.0) as usize;
+    (sequence as usize, weak, strong.max(minimum).min(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::num::NonZeroU64;
+
+    use chksum_core::Hash;
+
+    use super::{hash_lengths, ZsyncWriter};
+    use crate::testing::{Md4, Sha1};
+
+    #[test]
+    fn hash_lengths_like_zsyncmake() {
+        assert_eq!(hash_lengths(10, 4), (2, 2, 3));
+        assert_eq!(hash_lengths(3, 4), (1, 2, 3));
+        assert_eq!(hash_lengths(100_000_000, 2048), (2, 2, 5));
+        assert_eq!(hash_lengths(1 << 40, 4096), (2, 3, 6));
+    }
+
+    #[test]
+    fn control_file() {
+        let mut writer = ZsyncWriter::<_, Md4, Sha1>::new(Vec::new(), NonZeroU64::new(4).unwrap());
+        for chunk in b"abcdefghij".chunks(3) {
+            writer.write_all(chunk).unwrap();
+        }
+        let mut control = Vec::new();
+        let inner = writer.finish(&mut control, "file.bin", "file.bin").unwrap();
+        assert_eq!(inner, b"abcdefghij");
+
+        let mut expected = b"zsync: 0.6.2\nFilename: file.bin\nBlocksize: 4\nLength: 10\nHash-Lengths: 2,2,3\n\
+            URL: file.bin\nSHA-1: d68c19a0a345b7eab78d5e11e991c026ec60db63\n\n"
+            .to_vec();
+        for (rsum, block) in [
+            ([0x03, 0xD4], &b"abcd"[..]),
+            ([0x03, 0xFC], b"efgh"),
+            ([0x02, 0xE2], b"ij\0\0"),
+        ] {
+            expected.extend_from_slice(&rsum);
+            expected.extend_from_slice(&Md4::hash(block).as_ref()[..3]);
+        }
+        assert_eq!(control, expected);
+    }
+}